@@ -1,7 +1,14 @@
-use crate::transcription;
-use log::{debug, error};
+use crate::commands::{self, CommandRequest};
+use crate::transcription::{self, JsonError};
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io;
-use tokio::io::{AsyncBufReadExt, stdin};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader, Lines, stdin};
 
 /// Complete audio data received from JSON input
 #[derive(Debug, Clone)]
@@ -9,31 +16,320 @@ pub struct AudioData {
     /// Raw audio data bytes
     pub data: Vec<u8>,
     /// Timestamp when data was received
-    #[allow(
-        dead_code,
-        reason = "Timestamp field kept for future logging and debugging purposes"
-    )]
     pub timestamp: std::time::Instant,
+    /// Position of the chunk in a sequenced stream (if the client numbers its chunks)
+    pub seq: Option<u64>,
+    /// Duration the client declared for the chunk in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Start of this audio within the request in milliseconds, non-zero for
+    /// pieces split off by `--max-buffer-ms`
+    pub offset_ms: u64,
+    /// Transcription options sent with the request
+    pub options: Option<transcription::TranscriptionOptions>,
+    /// Format the audio was received in, before conversion to 16 kHz mono
+    pub input_audio: InputAudioInfo,
+}
+
+impl AudioData {
+    /// Duration of the chunk's 16-bit samples at 16 kHz in milliseconds
+    pub fn computed_duration_ms(&self) -> u64 {
+        samples_to_ms(self.data.len() / 2)
+    }
+
+    /// Compare the declared duration with the one implied by the samples
+    ///
+    /// # Arguments
+    /// * `tolerance_ms` - Largest difference that is not reported
+    ///
+    /// # Returns
+    /// * `Option<DurationMismatch>` - Both durations if they diverge, None if
+    ///   they agree or no duration was declared
+    pub fn duration_mismatch(&self, tolerance_ms: u64) -> Option<DurationMismatch> {
+        let declared_ms = self.duration_ms?;
+        let computed_ms = self.computed_duration_ms();
+        (declared_ms.abs_diff(computed_ms) > tolerance_ms).then_some(DurationMismatch {
+            declared_ms,
+            computed_ms,
+        })
+    }
+}
+
+/// Declared and computed durations of a chunk that diverge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurationMismatch {
+    /// Duration declared by the client in milliseconds
+    pub declared_ms: u64,
+    /// Duration implied by the sample count in milliseconds
+    pub computed_ms: u64,
+}
+
+/// Largest declared vs computed duration difference tolerated by default
+pub const DEFAULT_DURATION_TOLERANCE_MS: u64 = 100;
+
+/// Format of the PCM samples carried by a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormatInfo {
+    /// Samples per second
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: u16,
+    /// Bits per sample
+    pub bits_per_sample: u16,
+}
+
+impl Default for AudioFormatInfo {
+    /// Raw requests are assumed to already be in Whisper's native format
+    fn default() -> Self {
+        Self {
+            sample_rate: SAMPLE_RATE,
+            channels: 1,
+            bits_per_sample: 16,
+        }
+    }
+}
+
+/// How the server interpreted a request's audio, echoed back in the response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputAudioInfo {
+    /// Sample rate of the audio as received
+    pub sample_rate: u32,
+    /// Channel count of the audio as received
+    pub channels: u16,
+    /// Bits per sample of the audio as received
+    pub bit_depth: u16,
+    /// Sample encoding (e.g. "pcm_s16le")
+    pub encoding: String,
+    /// Whether the audio was resampled to 16 kHz
+    pub resampled: bool,
+    /// Whether multiple channels were mixed down to mono
+    pub downmixed: bool,
+    /// Fraction of samples at full scale (0.0 to 1.0)
+    #[serde(default)]
+    pub clipping_ratio: f32,
+}
+
+impl Default for InputAudioInfo {
+    /// Raw PCM already in Whisper's native format
+    fn default() -> Self {
+        Self::new(AudioFormatInfo::default(), PCM_S16LE)
+    }
+}
+
+impl InputAudioInfo {
+    /// Describe received audio before any conversion has been applied
+    ///
+    /// # Arguments
+    /// * `format` - Format of the received samples
+    /// * `encoding` - Sample encoding identifier
+    pub fn new(format: AudioFormatInfo, encoding: &str) -> Self {
+        Self {
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            bit_depth: format.bits_per_sample,
+            encoding: encoding.to_string(),
+            resampled: false,
+            downmixed: false,
+            clipping_ratio: 0.0,
+        }
+    }
+}
+
+/// Magnitude at or above which a sample counts as clipped (full scale for 16-bit PCM)
+pub const CLIPPING_LEVEL: f32 = 32767.0 / 32768.0;
+
+/// Clipping ratio above which clients are warned that their input gain is too high
+pub const CLIPPING_WARNING_RATIO: f32 = 0.01;
+
+/// Fraction of samples at full scale
+///
+/// # Arguments
+/// * `samples` - Normalised samples in [-1.0, 1.0]
+///
+/// # Returns
+/// * `f32` - Ratio of clipped samples (0.0 for empty input)
+pub fn clipping_ratio(samples: impl IntoIterator<Item = f32>) -> f32 {
+    let (total, clipped) = samples
+        .into_iter()
+        .fold((0usize, 0usize), |(total, clipped), s| {
+            (total + 1, clipped + usize::from(s.abs() >= CLIPPING_LEVEL))
+        });
+    if total == 0 {
+        return 0.0;
+    }
+    clipped as f32 / total as f32
+}
+
+/// Decode little-endian 16-bit PCM into normalised samples
+///
+/// A trailing odd byte is half a sample with no meaningful value, so it is
+/// dropped rather than decoded as silence.
+///
+/// # Arguments
+/// * `bytes` - Raw PCM bytes
+///
+/// # Returns
+/// * `impl Iterator<Item = f32>` - Samples in [-1.0, 1.0)
+pub fn pcm16_samples(bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    bytes
+        .chunks_exact(2) // 16-bit samples are 2 bytes (little endian order)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0)
+}
+
+/// Decode a range of samples from little-endian 16-bit PCM
+///
+/// Converting one window at a time keeps peak memory proportional to the
+/// window rather than the whole clip. When `pre_emphasis` is set, the
+/// sample before the window is decoded too so the filter output matches
+/// filtering the whole clip.
+///
+/// # Arguments
+/// * `bytes` - Raw PCM bytes of the whole clip
+/// * `range` - Sample range to decode
+/// * `pre_emphasis` - Pre-emphasis coefficient to apply, if any
+///
+/// # Returns
+/// * `Vec<f32>` - Samples of the range
+pub fn pcm16_window(bytes: &[u8], range: Range<usize>, pre_emphasis: Option<f32>) -> Vec<f32> {
+    let Some(coef) = pre_emphasis else {
+        return pcm16_samples(&bytes[range.start * 2..range.end * 2]).collect();
+    };
+    let from = range.start.saturating_sub(1);
+    let mut samples: Vec<f32> = pcm16_samples(&bytes[from * 2..range.end * 2]).collect();
+    apply_pre_emphasis(&mut samples, coef);
+    if from < range.start {
+        samples.remove(0);
+    }
+    samples
+}
+
+/// Encoding of raw little-endian 16-bit PCM
+pub const PCM_S16LE: &str = "pcm_s16le";
+
+/// A message read from stdin
+#[derive(Debug)]
+pub enum InputMessage {
+    /// Audio to transcribe
+    Audio(Box<AudioData>),
+    /// Control command
+    Command(CommandRequest),
+}
+
+/// Line source requests are read from
+///
+/// Reads stdin by default. A FIFO is re-opened whenever its writer
+/// disconnects, so successive clients can each write their own requests and
+/// the input only ends with an error.
+pub struct InputReader {
+    /// Lines of the currently open input
+    lines: Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>,
+    /// FIFO to re-open at end of stream (None for stdin)
+    fifo: Option<PathBuf>,
+}
+
+impl InputReader {
+    /// Read requests from any reader, ending with it
+    ///
+    /// # Arguments
+    /// * `reader` - Source of newline-delimited requests
+    pub fn new(reader: impl AsyncRead + Unpin + Send + 'static) -> Self {
+        Self {
+            lines: BufReader::new(Box::new(reader) as Box<dyn AsyncRead + Unpin + Send>).lines(),
+            fifo: None,
+        }
+    }
+
+    /// Read requests from stdin
+    pub fn stdin() -> Self {
+        Self::new(stdin())
+    }
+
+    /// Read requests from a FIFO, waiting for the first writer to connect
+    ///
+    /// # Arguments
+    /// * `path` - Path of an existing FIFO
+    ///
+    /// # Returns
+    /// * `io::Result<Self>` - Reader on success, error if the path is not a FIFO or cannot be opened
+    pub async fn fifo(path: &Path) -> io::Result<Self> {
+        check_fifo(path)?;
+        Ok(Self {
+            lines: open_fifo_lines(path).await?,
+            fifo: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Read the next line, re-opening the FIFO when its writer disconnects
+    ///
+    /// Cancel safe: if the future is dropped before it completes, no input
+    /// is lost and the next call continues where it left off.
+    ///
+    /// # Returns
+    /// * `io::Result<Option<String>>` - Next line, or None at the end of stdin
+    pub async fn next_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match (self.lines.next_line().await?, &self.fifo) {
+                (None, Some(path)) => {
+                    debug!("FIFO writer disconnected, waiting for the next one");
+                    self.lines = open_fifo_lines(path).await?;
+                }
+                (line, _) => return Ok(line),
+            }
+        }
+    }
+}
+
+/// Open a FIFO for reading, blocking until a writer connects
+async fn open_fifo_lines(
+    path: &Path,
+) -> io::Result<Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>> {
+    let file = tokio::fs::File::open(path).await?;
+    Ok(BufReader::new(Box::new(file) as Box<dyn AsyncRead + Unpin + Send>).lines())
+}
+
+/// Check that a path is a FIFO, since re-opening a regular file would replay it forever
+#[cfg(unix)]
+fn check_fifo(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if std::fs::metadata(path)?.file_type().is_fifo() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a FIFO", path.display()),
+        ))
+    }
+}
+
+/// Check that a path is a FIFO
+///
+/// FIFOs are not supported on this platform.
+#[cfg(not(unix))]
+fn check_fifo(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "FIFO input is not supported on this platform",
+    ))
 }
 
 /// JSON reader for audio data
 ///
-/// This function reads complete JSON payloads from stdin and parses them.
+/// This function reads the next complete JSON payload from the input and parses it.
 /// It handles JSON validation and provides proper error handling and logging.
+/// Like [`InputReader::next_line`], it may be cancelled without losing input.
 ///
 /// # Arguments
+/// * `input` - Input to read the payload from
 ///
 /// # Returns
-/// * `Result<Option<AudioData>, String>` - Audio data if available, None if end of stream, error if failed
-pub async fn read_json_audio() -> Result<Option<AudioData>, String> {
+/// * `Result<Option<InputMessage>, JsonError>` - Audio data or a command if available, None if end of stream, error if failed
+pub async fn read_json_audio(input: &mut InputReader) -> Result<Option<InputMessage>, JsonError> {
     debug!("Starting JSON audio data read operation");
-    let stdin = stdin();
-    let mut reader = tokio::io::BufReader::new(stdin).lines();
 
-    // Read complete JSON payload from stdin
-    debug!("Reading JSON payload from stdin on each new line");
+    // Read complete JSON payload from the input
+    debug!("Reading JSON payload from the input on each new line");
 
-    match reader.next_line().await {
+    match input.next_line().await {
         Ok(None) => {
             // End of stream
             debug!("End of JSON stream detected");
@@ -42,6 +338,12 @@ pub async fn read_json_audio() -> Result<Option<AudioData>, String> {
         Ok(Some(json_buffer)) => {
             debug!("Read {} bytes from stdin", json_buffer.len());
 
+            // Control commands are handled separately from audio requests
+            if let Some(command) = commands::parse_command(&json_buffer) {
+                debug!("Received command: {}", command.command);
+                return Ok(Some(InputMessage::Command(command)));
+            }
+
             // Parse JSON payload
             match serde_json::from_str::<transcription::TranscriptionRequest>(&json_buffer) {
                 Ok(request) => {
@@ -55,34 +357,682 @@ pub async fn read_json_audio() -> Result<Option<AudioData>, String> {
                                 audio_data.len()
                             );
 
+                            let (input_audio, audio_data) = normalize_audio(
+                                request.audio_data.format_hint(),
+                                raw_audio_format(request.options.as_ref()),
+                                audio_data,
+                            )?;
+
                             let audio = AudioData {
                                 data: audio_data,
                                 timestamp: std::time::Instant::now(),
+                                seq: request.seq,
+                                duration_ms: request.duration_ms,
+                                options: request.options,
+                                offset_ms: 0,
+                                input_audio,
                             };
 
-                            Ok(Some(audio))
-                        }
-                        Err(e) => {
-                            error!("Failed to extract audio data from JSON: {}", e);
-                            Err(format!("Failed to extract audio data: {}", e))
-                        }
+                            Ok(Some(InputMessage::Audio(Box::new(audio))))
+                        }
+                        Err(e) => {
+                            error!("Failed to extract audio data from JSON: {}", e);
+                            Err(e)
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to parse JSON payload: {}", e);
+                    Err(e.into())
+                }
+            }
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+            // Read was interrupted, try again
+            Err(JsonError::ReadError("Read interrupted".to_string()))
+        }
+        Err(e) => {
+            // Log error and return it
+            error!("Error reading from stdin: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Sample rate expected by Whisper
+pub const SAMPLE_RATE: u32 = 16000;
+
+/// Whether `bytes` start with the RIFF/WAVE magic of a WAV file
+pub fn has_wav_magic(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+/// Parse a WAV file into its format and PCM data
+///
+/// Chunks other than `fmt ` and `data` are skipped. A `data` chunk whose
+/// declared size runs past the end of the input (as written by streaming
+/// encoders) is cut at the end of the input.
+///
+/// # Arguments
+/// * `bytes` - Complete WAV file
+///
+/// # Returns
+/// * `Result<(AudioFormatInfo, Vec<u8>), String>` - Format and PCM data on success, error message on failure
+pub fn parse_wav(bytes: &[u8]) -> Result<(AudioFormatInfo, Vec<u8>), String> {
+    if !has_wav_magic(bytes) {
+        return Err("Missing RIFF/WAVE header".to_string());
+    }
+
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]) as usize;
+        let body_start = offset + 8;
+        let body_end = body_start.saturating_add(size).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err(format!("fmt chunk is {} bytes, expected 16", body.len()));
+                }
+                let encoding = u16::from_le_bytes([body[0], body[1]]);
+                // 1 is integer PCM, 0xFFFE is WAVE_FORMAT_EXTENSIBLE
+                if encoding != 1 && encoding != 0xFFFE {
+                    return Err(format!("Unsupported WAV encoding: {}", encoding));
+                }
+                format = Some(AudioFormatInfo {
+                    channels: u16::from_le_bytes([body[2], body[3]]),
+                    sample_rate: u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+                    bits_per_sample: u16::from_le_bytes([body[14], body[15]]),
+                });
+            }
+            b"data" => {
+                let format = format.ok_or("WAV data chunk precedes its fmt chunk")?;
+                return Ok((format, body.to_vec()));
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even length
+        offset = body_start.saturating_add(size).saturating_add(size % 2);
+    }
+
+    Err("WAV file has no data chunk".to_string())
+}
+
+/// Strip the WAV container from a request's audio, leaving raw PCM
+///
+/// Audio is treated as WAV when the format hint is "wav" or the bytes start
+/// with the RIFF/WAVE magic; anything else is returned unchanged.
+///
+/// # Arguments
+/// * `format_hint` - Format hint sent with the audio
+/// * `bytes` - Decoded audio bytes
+///
+/// # Returns
+/// * `Result<(Option<AudioFormatInfo>, Vec<u8>), JsonError>` - WAV format (None for raw audio) and PCM data on success, error on failure
+pub fn strip_wav_container(
+    format_hint: Option<&str>,
+    bytes: Vec<u8>,
+) -> Result<(Option<AudioFormatInfo>, Vec<u8>), JsonError> {
+    let is_wav = format_hint.is_some_and(|hint| hint.eq_ignore_ascii_case("wav"));
+    if !is_wav && !has_wav_magic(&bytes) {
+        return Ok((None, bytes));
+    }
+
+    let (format, data) =
+        parse_wav(&bytes).map_err(|e| JsonError::AudioDataError(format!("Invalid WAV: {}", e)))?;
+    debug!(
+        "Parsed WAV audio: {} Hz, {} channels, {} bits, {} data bytes",
+        format.sample_rate,
+        format.channels,
+        format.bits_per_sample,
+        data.len()
+    );
+    if data.is_empty() {
+        return Err(JsonError::EmptyAudio("WAV data chunk is empty".to_string()));
+    }
+    Ok((Some(format), data))
+}
+
+/// Sample rates accepted for incoming audio
+///
+/// Bounding the rate bounds how much resampling can grow a request: a rate
+/// of a few Hz would otherwise turn a small request into billions of samples.
+pub const INPUT_SAMPLE_RATES: RangeInclusive<u32> = 8000..=192000;
+
+/// Error message for a sample rate outside `INPUT_SAMPLE_RATES`
+pub fn sample_rate_error(sample_rate: u32) -> String {
+    format!(
+        "sample rate {} Hz is outside the supported range {}-{} Hz",
+        sample_rate,
+        INPUT_SAMPLE_RATES.start(),
+        INPUT_SAMPLE_RATES.end()
+    )
+}
+
+/// Format of raw (non-WAV) audio as declared in the request options
+///
+/// # Arguments
+/// * `options` - Options sent with the request, if any
+///
+/// # Returns
+/// * `AudioFormatInfo` - Declared sample rate and channel count, 16 kHz mono when not given
+pub fn raw_audio_format(options: Option<&transcription::TranscriptionOptions>) -> AudioFormatInfo {
+    let defaults = AudioFormatInfo::default();
+    AudioFormatInfo {
+        sample_rate: options
+            .and_then(|options| options.sample_rate)
+            .unwrap_or(defaults.sample_rate),
+        channels: options
+            .and_then(|options| options.channels)
+            .unwrap_or(defaults.channels),
+        ..defaults
+    }
+}
+
+/// Convert a request's audio to 16 kHz mono 16-bit PCM
+///
+/// The format comes from the WAV header when there is one, otherwise from
+/// `raw_format`. Multi-channel audio is mixed down to mono and audio at
+/// another rate is resampled to 16 kHz. The received format and the
+/// conversions applied are returned so responses can report them.
+///
+/// # Arguments
+/// * `format_hint` - Format hint sent with the audio
+/// * `raw_format` - Format of the audio when it is not WAV
+/// * `bytes` - Decoded audio bytes
+///
+/// # Returns
+/// * `Result<(InputAudioInfo, Vec<u8>), JsonError>` - Received format and 16 kHz mono PCM data on success, error on failure
+pub fn normalize_audio(
+    format_hint: Option<&str>,
+    raw_format: AudioFormatInfo,
+    bytes: Vec<u8>,
+) -> Result<(InputAudioInfo, Vec<u8>), JsonError> {
+    let (wav_format, data) = strip_wav_container(format_hint, bytes)?;
+    if wav_format.is_none() {
+        // The raw format comes from the request options
+        if !INPUT_SAMPLE_RATES.contains(&raw_format.sample_rate) {
+            return Err(JsonError::InvalidFieldValue(
+                "sample_rate".to_string(),
+                sample_rate_error(raw_format.sample_rate),
+            ));
+        }
+        if raw_format.channels == 0 {
+            return Err(JsonError::InvalidFieldValue(
+                "channels".to_string(),
+                "Channel count must be greater than 0".to_string(),
+            ));
+        }
+    }
+    let format = wav_format.unwrap_or(raw_format);
+    if !INPUT_SAMPLE_RATES.contains(&format.sample_rate) {
+        return Err(JsonError::AudioDataError(format!(
+            "WAV {}",
+            sample_rate_error(format.sample_rate)
+        )));
+    }
+    if format.channels == 0 || format.bits_per_sample != 16 {
+        return Err(JsonError::AudioDataError(format!(
+            "Audio must be 16-bit PCM with a non-zero channel count, got {} channels, {} bits",
+            format.channels, format.bits_per_sample
+        )));
+    }
+    let mut input_audio = InputAudioInfo::new(format, PCM_S16LE);
+    if format == AudioFormatInfo::default() {
+        return Ok((input_audio, data));
+    }
+
+    let mut samples: Vec<f32> = pcm16_samples(&data).collect();
+    if format.channels > 1 {
+        input_audio.downmixed = true;
+        debug!("Mixing {} channels down to mono", format.channels);
+        if !samples.len().is_multiple_of(format.channels as usize) {
+            warn!("Audio ends with an incomplete frame, dropping it");
+        }
+        samples = downmix_to_mono(&samples, format.channels);
+    }
+    if format.sample_rate != SAMPLE_RATE {
+        input_audio.resampled = true;
+        debug!(
+            "Resampling audio from {} Hz to {} Hz",
+            format.sample_rate, SAMPLE_RATE
+        );
+        samples = resample_to_16k(&samples, format.sample_rate);
+    }
+    Ok((input_audio, pcm16_bytes(&samples)))
+}
+
+/// Mix interleaved multi-channel audio down to mono
+///
+/// Each output sample is the average of one frame's channels. A trailing
+/// incomplete frame is dropped.
+///
+/// # Arguments
+/// * `samples` - Interleaved samples
+/// * `channels` - Number of interleaved channels
+///
+/// # Returns
+/// * `Vec<f32>` - Mono samples, one per frame
+pub fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels as usize)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Encode normalised samples as little-endian 16-bit PCM
+///
+/// # Arguments
+/// * `samples` - Samples in [-1.0, 1.0], clamped to the 16-bit range
+///
+/// # Returns
+/// * `Vec<u8>` - Raw PCM bytes
+pub fn pcm16_bytes(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| {
+            ((sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16).to_le_bytes()
+        })
+        .collect()
+}
+
+/// Zero crossings of the windowed-sinc kernel on each side of a resampled point
+const RESAMPLE_ZERO_CROSSINGS: f64 = 16.0;
+
+/// Resample audio to Whisper's 16 kHz sample rate
+///
+/// Uses Hann-windowed sinc interpolation. When downsampling, the kernel's
+/// cutoff is lowered to the output Nyquist frequency so content above 8 kHz
+/// is filtered out instead of aliasing into the speech band.
+///
+/// # Arguments
+/// * `samples` - Mono samples at `input_rate`
+/// * `input_rate` - Sample rate of `samples` in Hz
+///
+/// # Returns
+/// * `Vec<f32>` - Samples at 16 kHz
+pub fn resample_to_16k(samples: &[f32], input_rate: u32) -> Vec<f32> {
+    if input_rate == SAMPLE_RATE || input_rate == 0 || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = SAMPLE_RATE as f64 / input_rate as f64;
+    // Cutoff relative to the input Nyquist frequency
+    let cutoff = ratio.min(1.0);
+    let half_width = RESAMPLE_ZERO_CROSSINGS / cutoff;
+    let output_len = (samples.len() as f64 * ratio).round() as usize;
+    let last = samples.len() as isize - 1;
+
+    (0..output_len)
+        .map(|i| {
+            let center = i as f64 / ratio;
+            let first = ((center - half_width).ceil() as isize).max(0);
+            let end = ((center + half_width).floor() as isize).min(last);
+            let (mut sum, mut weight_sum) = (0.0, 0.0);
+            for j in first..=end {
+                let offset = j as f64 - center;
+                let window = 0.5 * (1.0 + (std::f64::consts::PI * offset / half_width).cos());
+                let weight = sinc(cutoff * offset) * window;
+                sum += samples[j as usize] as f64 * weight;
+                weight_sum += weight;
+            }
+            // Normalising by the weights keeps unit gain, including at the edges
+            if weight_sum.abs() > f64::EPSILON {
+                (sum / weight_sum) as f32
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// Normalised sinc function, sin(pi x) / (pi x)
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        let pi_x = std::f64::consts::PI * x;
+        pi_x.sin() / pi_x
+    }
+}
+
+/// Frame length used for energy computation (10 ms at 16 kHz)
+pub const ENERGY_FRAME_SAMPLES: usize = 160;
+
+/// RMS level below which a frame is considered silent (about -40 dBFS)
+pub const DEFAULT_SILENCE_THRESHOLD: f32 = 0.01;
+
+/// Compute the RMS energy of consecutive frames
+///
+/// # Arguments
+/// * `samples` - Audio samples
+/// * `frame_len` - Number of samples per frame (the last frame may be shorter)
+///
+/// # Returns
+/// * `Vec<f32>` - RMS energy for each frame
+pub fn frame_energies(samples: &[f32], frame_len: usize) -> Vec<f32> {
+    samples
+        .chunks(frame_len.max(1))
+        .map(|frame| (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt())
+        .collect()
+}
+
+/// Whether any frame reaches the silence threshold
+///
+/// Uses the same frames as [`frame_energies`] without collecting them, so it
+/// can run over samples as they are decoded.
+///
+/// # Arguments
+/// * `samples` - Audio samples
+/// * `threshold` - RMS level at or above which a frame counts as speech
+///
+/// # Returns
+/// * `bool` - False if every frame is below the threshold
+pub fn contains_speech(samples: impl IntoIterator<Item = f32>, threshold: f32) -> bool {
+    let (mut sum, mut count) = (0.0f32, 0usize);
+    for sample in samples {
+        sum += sample * sample;
+        count += 1;
+        if count == ENERGY_FRAME_SAMPLES {
+            if (sum / count as f32).sqrt() >= threshold {
+                return true;
+            }
+            (sum, count) = (0.0, 0);
+        }
+    }
+    count > 0 && (sum / count as f32).sqrt() >= threshold
+}
+
+/// Amount of audio removed by [`trim_silence`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SilenceTrim {
+    /// Samples removed from the start
+    pub leading_samples: usize,
+    /// Samples removed from the end
+    pub trailing_samples: usize,
+}
+
+impl SilenceTrim {
+    /// Leading trim in milliseconds
+    pub fn leading_ms(&self) -> u64 {
+        samples_to_ms(self.leading_samples)
+    }
+
+    /// Trailing trim in milliseconds
+    pub fn trailing_ms(&self) -> u64 {
+        samples_to_ms(self.trailing_samples)
+    }
+}
+
+/// Convert a 16 kHz sample count to milliseconds
+pub fn samples_to_ms(samples: usize) -> u64 {
+    samples as u64 * 1000 / SAMPLE_RATE as u64
+}
+
+/// Convert milliseconds to a 16 kHz sample count
+pub fn ms_to_samples(ms: u64) -> usize {
+    (ms * SAMPLE_RATE as u64 / 1000) as usize
+}
+
+/// Split `len` samples into windows of `window` samples sharing `overlap` samples
+///
+/// # Arguments
+/// * `len` - Total number of samples
+/// * `window` - Window length in samples
+/// * `overlap` - Samples shared by consecutive windows (must be less than `window`)
+///
+/// # Returns
+/// * `Vec<Range<usize>>` - Sample ranges covering the whole input
+pub fn split_windows(len: usize, window: usize, overlap: usize) -> Vec<Range<usize>> {
+    if window == 0 || len <= window {
+        return std::iter::once(0..len).collect();
+    }
+
+    let step = window.saturating_sub(overlap).max(1);
+    let mut windows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + window).min(len);
+        windows.push(start..end);
+        if end == len {
+            break;
+        }
+        start += step;
+    }
+    windows
+}
+
+/// Remove leading and trailing frames whose energy is below `threshold`
+///
+/// Audio that is silent throughout is left untouched so callers can decide
+/// how to handle it.
+///
+/// # Arguments
+/// * `samples` - Audio samples to trim in place
+/// * `threshold` - RMS level below which a frame counts as silence
+///
+/// # Returns
+/// * `SilenceTrim` - How much audio was removed from each end
+pub fn trim_silence(samples: &mut Vec<f32>, threshold: f32) -> SilenceTrim {
+    let energies = frame_energies(samples, ENERGY_FRAME_SAMPLES);
+    let Some(first) = energies.iter().position(|&e| e >= threshold) else {
+        return SilenceTrim::default();
+    };
+    let last = energies
+        .iter()
+        .rposition(|&e| e >= threshold)
+        .unwrap_or(first);
+
+    let start = first * ENERGY_FRAME_SAMPLES;
+    let end = ((last + 1) * ENERGY_FRAME_SAMPLES).min(samples.len());
+    let trim = SilenceTrim {
+        leading_samples: start,
+        trailing_samples: samples.len() - end,
+    };
+
+    samples.truncate(end);
+    samples.drain(..start);
+    trim
+}
+
+/// Apply a pre-emphasis filter in place
+///
+/// Computes `y[n] = x[n] - coef * x[n-1]`, boosting high frequencies to make
+/// consonants easier to recognise. The first sample is left unchanged.
+///
+/// # Arguments
+/// * `samples` - Audio samples to filter
+/// * `coef` - Filter coefficient in the range [0.0, 1.0)
+pub fn apply_pre_emphasis(samples: &mut [f32], coef: f32) {
+    // Walk backwards so each x[n-1] is still the unfiltered value
+    for n in (1..samples.len()).rev() {
+        samples[n] -= coef * samples[n - 1];
+    }
+}
+
+/// RMS level automatic gain control aims for by default (about -20 dBFS)
+pub const DEFAULT_AGC_TARGET_RMS: f32 = 0.1;
+
+/// Largest gain automatic gain control applies by default (+20 dB)
+pub const DEFAULT_AGC_MAX_GAIN: f32 = 10.0;
+
+/// Settings of the automatic gain control step (`--agc`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AgcSettings {
+    /// RMS level the buffer is scaled towards
+    pub target_rms: f32,
+    /// Largest gain applied, so near-silent buffers are not boosted into noise
+    pub max_gain: f32,
+}
+
+impl Default for AgcSettings {
+    fn default() -> Self {
+        Self {
+            target_rms: DEFAULT_AGC_TARGET_RMS,
+            max_gain: DEFAULT_AGC_MAX_GAIN,
+        }
+    }
+}
+
+/// Scale a buffer towards a target RMS level
+///
+/// Unlike peak normalization, the gain follows the buffer's average level,
+/// so a single loud click does not keep quiet speech quiet. Loud buffers are
+/// attenuated, quiet ones boosted up to `max_gain`, and the result is clamped
+/// to [-1.0, 1.0].
+///
+/// # Arguments
+/// * `samples` - Audio samples to scale in place
+/// * `settings` - Target level and gain cap
+///
+/// # Returns
+/// * `f32` - Gain applied (1.0 for a silent buffer)
+pub fn apply_agc(samples: &mut [f32], settings: AgcSettings) -> f32 {
+    if samples.is_empty() {
+        return 1.0;
+    }
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    if rms <= f32::EPSILON {
+        return 1.0;
+    }
+
+    let gain = (settings.target_rms / rms).min(settings.max_gain);
+    for sample in samples.iter_mut() {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
+    gain
+}
+
+/// Preprocessing steps whose order can be set with `--preprocess-order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreprocessStep {
+    /// Trim leading/trailing silence (`--trim-silence`)
+    #[serde(rename = "trim")]
+    TrimSilence,
+    /// Pre-emphasis filter (`--pre-emphasis`)
+    PreEmphasis,
+}
+
+impl PreprocessStep {
+    /// Order used when none is configured: trimming first keeps the silence
+    /// threshold measured on the unfiltered signal
+    pub const DEFAULT_ORDER: [PreprocessStep; 2] =
+        [PreprocessStep::TrimSilence, PreprocessStep::PreEmphasis];
+
+    /// Identifier accepted by `--preprocess-order`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreprocessStep::TrimSilence => "trim",
+            PreprocessStep::PreEmphasis => "pre-emphasis",
+        }
+    }
+}
+
+impl FromStr for PreprocessStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PreprocessStep::DEFAULT_ORDER
+            .iter()
+            .copied()
+            .find(|step| step.as_str() == s)
+            .ok_or_else(|| format!("Invalid preprocessing step: {}", s))
+    }
+}
+
+/// Preprocessing applied to decoded samples before inference
+///
+/// Steps run in the configured order; a step that is not enabled is skipped.
+/// Automatic gain control runs last, so it levels the samples Whisper sees.
+#[derive(Debug, Clone)]
+pub struct AudioPipeline {
+    /// Order the steps run in
+    order: Vec<PreprocessStep>,
+    /// Silence threshold, when trimming is enabled
+    trim_threshold: Option<f32>,
+    /// Pre-emphasis coefficient, when the filter is enabled
+    pre_emphasis: Option<f32>,
+    /// Gain control settings, when AGC is enabled
+    agc: Option<AgcSettings>,
+}
+
+impl AudioPipeline {
+    /// Create a pipeline with every step disabled
+    ///
+    /// # Arguments
+    /// * `order` - Order the steps run in
+    pub fn new(order: &[PreprocessStep]) -> Self {
+        Self {
+            order: order.to_vec(),
+            trim_threshold: None,
+            pre_emphasis: None,
+            agc: None,
+        }
+    }
+
+    /// Enable silence trimming
+    pub fn with_trim_silence(mut self, threshold: f32) -> Self {
+        self.trim_threshold = Some(threshold);
+        self
+    }
+
+    /// Enable the pre-emphasis filter
+    pub fn with_pre_emphasis(mut self, coef: f32) -> Self {
+        self.pre_emphasis = Some(coef);
+        self
+    }
+
+    /// Enable automatic gain control
+    pub fn with_agc(mut self, settings: AgcSettings) -> Self {
+        self.agc = Some(settings);
+        self
+    }
+
+    /// Run the enabled steps on `samples` in order
+    ///
+    /// # Returns
+    /// * `Option<SilenceTrim>` - How much silence was trimmed, if trimming is enabled
+    pub fn run(&self, samples: &mut Vec<f32>) -> Option<SilenceTrim> {
+        let mut silence_trim = None;
+        for step in &self.order {
+            match step {
+                PreprocessStep::TrimSilence => {
+                    if let Some(threshold) = self.trim_threshold {
+                        let trim = trim_silence(samples, threshold);
+                        debug!(
+                            "Trimmed {} ms of leading and {} ms of trailing silence",
+                            trim.leading_ms(),
+                            trim.trailing_ms()
+                        );
+                        silence_trim = Some(trim);
                     }
                 }
-                Err(e) => {
-                    error!("Failed to parse JSON payload: {}", e);
-                    Err(format!("Invalid JSON payload: {}", e))
+                PreprocessStep::PreEmphasis => {
+                    if let Some(coef) = self.pre_emphasis {
+                        debug!("Applying pre-emphasis filter with coefficient {}", coef);
+                        apply_pre_emphasis(samples, coef);
+                    }
                 }
             }
         }
-        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
-            // Read was interrupted, try again
-            Err("Read interrupted".to_string())
-        }
-        Err(e) => {
-            // Log error and return it
-            error!("Error reading from stdin: {}", e);
-            Err(format!("Error reading from stdin: {}", e))
+        if let Some(settings) = self.agc {
+            let gain = apply_agc(samples, settings);
+            debug!("Applied automatic gain of {:.2}", gain);
         }
+        silence_trim
     }
 }
 
@@ -121,10 +1071,25 @@ pub trait AudioProcessor: Send + Sync {
     fn clear_data(&mut self);
 }
 
+/// Snapshot of the audio buffer reported by the `buffer_status` command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferStatus {
+    /// Bytes received since the buffer was last drained
+    pub total_bytes_received: u64,
+    /// Bytes currently waiting to be transcribed
+    pub buffered_bytes: usize,
+    /// Whether the buffer holds audio ready for transcription
+    pub is_ready: bool,
+    /// Buffered audio length in seconds, assuming 16 kHz 16-bit mono PCM
+    pub estimated_seconds: f32,
+}
+
 /// Simple audio buffer for handling complete audio data
 pub struct AudioBuffer {
     audio_data: Option<AudioData>,
     total_bytes_received: u64,
+    /// Largest amount of audio handed out per take, in bytes (unbounded when None)
+    max_take_bytes: Option<usize>,
 }
 
 impl AudioBuffer {
@@ -133,6 +1098,23 @@ impl AudioBuffer {
         Self {
             audio_data: None,
             total_bytes_received: 0,
+            max_take_bytes: None,
+        }
+    }
+
+    /// Create a buffer that flushes at most `max_buffer_ms` of audio at a time
+    ///
+    /// Longer input stays buffered and is handed out by subsequent takes, so
+    /// each piece is transcribed and reported without waiting for the rest.
+    ///
+    /// # Arguments
+    /// * `max_buffer_ms` - Longest piece of audio returned by `take_audio_data`
+    pub fn with_max_buffer_ms(max_buffer_ms: u64) -> Self {
+        // 16-bit samples, kept whole
+        let max_take_bytes = (ms_to_samples(max_buffer_ms) * 2).max(2);
+        Self {
+            max_take_bytes: Some(max_take_bytes),
+            ..Self::new()
         }
     }
 
@@ -173,17 +1155,191 @@ impl AudioBuffer {
         self.audio_data.is_some()
     }
 
+    /// Report the current buffer state
+    pub fn status(&self) -> BufferStatus {
+        let buffered_bytes = self
+            .audio_data
+            .as_ref()
+            .map_or(0, |audio_data| audio_data.data.len());
+        BufferStatus {
+            total_bytes_received: self.total_bytes_received,
+            buffered_bytes,
+            is_ready: self.is_ready(),
+            estimated_seconds: (buffered_bytes / 2) as f32 / SAMPLE_RATE as f32,
+        }
+    }
+
     /// Take the audio data for processing
     ///
+    /// With a maximum buffer length, only the leading piece up to that length
+    /// is taken and the remainder stays buffered.
+    ///
     /// # Returns
     /// * `Option<AudioData>` - Some(audio_data) if available, None otherwise
     pub fn take_audio_data(&mut self) -> Option<AudioData> {
-        let audio_data = self.audio_data.take();
-        if audio_data.is_some() {
-            self.total_bytes_received = 0;
-            debug!("Took audio data for processing");
+        let mut audio_data = self.audio_data.take()?;
+        if let Some(max_bytes) = self.max_take_bytes
+            && audio_data.data.len() > max_bytes
+        {
+            let remainder = AudioData {
+                data: audio_data.data.split_off(max_bytes),
+                timestamp: audio_data.timestamp,
+                seq: audio_data.seq,
+                duration_ms: None,
+                options: audio_data.options.clone(),
+                offset_ms: audio_data.offset_ms + samples_to_ms(max_bytes / 2),
+                input_audio: audio_data.input_audio.clone(),
+            };
+            debug!(
+                "Flushing {} bytes, {} bytes stay buffered",
+                audio_data.data.len(),
+                remainder.data.len()
+            );
+            self.total_bytes_received = remainder.data.len() as u64;
+            self.audio_data = Some(remainder);
+            return Some(audio_data);
+        }
+        self.total_bytes_received = 0;
+        debug!("Took audio data for processing");
+        Some(audio_data)
+    }
+}
+
+/// How long sequenced chunks wait for a missing earlier chunk by default
+pub const DEFAULT_REORDER_TIMEOUT_MS: u64 = 2000;
+
+/// Reassembles sequenced audio chunks that arrive out of order
+///
+/// Sequence numbers start at 0 and increase by one per chunk. Chunks are
+/// held until every earlier chunk has arrived and are then released
+/// concatenated in sequence order. If a held chunk has waited longer than
+/// the timeout, the missing chunks before it are given up on; callers that
+/// wait for input should stop waiting at `deadline` and call `expire` so the
+/// held audio is not stuck behind a chunk that never arrives. A retried
+/// chunk whose `seq` was already received is ignored, so its audio is
+/// never appended twice.
+#[derive(Debug)]
+pub struct ReorderBuffer {
+    /// Sequence number of the next chunk to release
+    next_seq: u64,
+    /// Chunks waiting for earlier ones, keyed by sequence number
+    pending: BTreeMap<u64, AudioData>,
+    /// How long a held chunk waits for missing earlier chunks
+    timeout: Duration,
+}
+
+impl ReorderBuffer {
+    /// Create an empty reorder buffer
+    ///
+    /// # Arguments
+    /// * `timeout` - How long a held chunk waits for missing earlier chunks
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            next_seq: 0,
+            pending: BTreeMap::new(),
+            timeout,
+        }
+    }
+
+    /// Add a chunk and release any audio that is now in order
+    ///
+    /// # Arguments
+    /// * `seq` - Sequence number of the chunk
+    /// * `chunk` - Audio of the chunk
+    /// * `now` - Current time, compared against when held chunks arrived
+    ///
+    /// # Returns
+    /// * `Option<AudioData>` - Contiguous audio ready for transcription, without a sequence number
+    pub fn push(&mut self, seq: u64, chunk: AudioData, now: Instant) -> Option<AudioData> {
+        if self.is_duplicate(seq) {
+            debug!("Ignoring already received audio chunk {}", seq);
+        } else {
+            self.pending.insert(seq, chunk);
+        }
+        self.expire(now)
+    }
+
+    /// When the longest-held chunk stops waiting for the chunks before it
+    ///
+    /// # Returns
+    /// * `Option<Instant>` - Deadline of the oldest held chunk, None if nothing is held
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .values()
+            .map(|held| held.timestamp + self.timeout)
+            .min()
+    }
+
+    /// Skip missing chunks that timed out and release the audio that is now in order
+    ///
+    /// # Arguments
+    /// * `now` - Current time, compared against when held chunks arrived
+    ///
+    /// # Returns
+    /// * `Option<AudioData>` - Contiguous audio ready for transcription, without a sequence number
+    pub fn expire(&mut self, now: Instant) -> Option<AudioData> {
+        let timed_out = self
+            .pending
+            .values()
+            .any(|held| now.saturating_duration_since(held.timestamp) >= self.timeout);
+        if timed_out
+            && let Some(&first) = self.pending.keys().next()
+            && first > self.next_seq
+        {
+            warn!(
+                "Audio chunks {}..{} missing after {:?}, skipping them",
+                self.next_seq, first, self.timeout
+            );
+            self.next_seq = first;
+        }
+
+        let mut released: Option<AudioData> = None;
+        while let Some(chunk) = self.pending.remove(&self.next_seq) {
+            self.next_seq += 1;
+            match &mut released {
+                Some(audio) => audio.data.extend(chunk.data),
+                None => released = Some(chunk),
+            }
         }
-        audio_data
+        released.map(|audio| AudioData {
+            seq: None,
+            duration_ms: None,
+            ..audio
+        })
+    }
+
+    /// Release every held chunk in sequence order, skipping any gaps
+    ///
+    /// # Returns
+    /// * `Option<AudioData>` - Remaining audio, or None if nothing is held
+    pub fn drain(&mut self) -> Option<AudioData> {
+        let pending = std::mem::take(&mut self.pending);
+        let (&last, _) = pending.last_key_value()?;
+        self.next_seq = last + 1;
+        pending
+            .into_values()
+            .reduce(|mut audio, chunk| {
+                audio.data.extend(chunk.data);
+                audio
+            })
+            .map(|audio| AudioData {
+                seq: None,
+                duration_ms: None,
+                ..audio
+            })
+    }
+
+    /// Whether a chunk with this sequence number was already received or skipped
+    ///
+    /// # Arguments
+    /// * `seq` - Sequence number of the incoming chunk
+    pub fn is_duplicate(&self, seq: u64) -> bool {
+        seq < self.next_seq || self.pending.contains_key(&seq)
+    }
+
+    /// Number of chunks waiting for earlier ones
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
     }
 }
 
@@ -221,6 +1377,9 @@ impl AudioProcessor for AudioBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transcription::{
+        TranscriptionSegment, offset_segment_timestamps, round_segment_timestamps,
+    };
 
     #[test]
     fn test_audio_buffer_basic() {
@@ -232,6 +1391,11 @@ mod tests {
         let audio_data = AudioData {
             data: vec![1, 2, 3, 4],
             timestamp: std::time::Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
         };
 
         buffer.set_audio_data(audio_data);
@@ -248,6 +1412,11 @@ mod tests {
         let audio_data = AudioData {
             data: vec![1, 2, 3, 4],
             timestamp: std::time::Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
         };
 
         buffer.set_audio_data(audio_data);
@@ -269,6 +1438,11 @@ mod tests {
         let audio_data = AudioData {
             data: vec![1, 2, 3],
             timestamp: std::time::Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
         };
 
         buffer.set_audio_data(audio_data);
@@ -287,6 +1461,11 @@ mod tests {
         let audio_data = AudioData {
             data: vec![1, 2, 3],
             timestamp: std::time::Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
         };
 
         // Test AudioProcessor trait implementation
@@ -304,6 +1483,725 @@ mod tests {
         assert!(buffer.accumulated_data().is_empty());
     }
 
+    #[test]
+    fn test_input_audio_info_for_raw_pcm() {
+        let info = InputAudioInfo::new(AudioFormatInfo::default(), PCM_S16LE);
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["sample_rate"], 16000);
+        assert_eq!(json["channels"], 1);
+        assert_eq!(json["bit_depth"], 16);
+        assert_eq!(json["encoding"], "pcm_s16le");
+        assert_eq!(json["resampled"], false);
+        assert_eq!(json["downmixed"], false);
+    }
+
+    #[tokio::test]
+    async fn test_input_audio_info_for_48k_wav() {
+        use base64::Engine;
+
+        let wav = wav_file(48000, 1, 16, &pcm16_bytes(&sine_second(440.0, 48000)));
+        let line = format!(
+            "{{\"audio_data\":{{\"data\":\"{}\"}}}}\n",
+            base64::engine::general_purpose::STANDARD.encode(wav)
+        );
+        let mut input = InputReader::new(io::Cursor::new(line.into_bytes()));
+        let Some(InputMessage::Audio(audio)) = read_json_audio(&mut input).await.unwrap() else {
+            panic!("expected audio");
+        };
+
+        assert_eq!(audio.data.len(), 32000);
+        let json = serde_json::to_value(&audio.input_audio).unwrap();
+        assert_eq!(json["sample_rate"], 48000);
+        assert_eq!(json["channels"], 1);
+        assert_eq!(json["resampled"], true);
+        assert_eq!(json["downmixed"], false);
+    }
+
+    #[test]
+    fn test_clipping_ratio_of_clipped_buffer() {
+        // A tone driven well past full scale and then saturated
+        let samples: Vec<f32> = (0..1600)
+            .map(|i| (4.0 * (i as f32 * 0.05).sin()).clamp(-1.0, 1.0))
+            .collect();
+        assert!(clipping_ratio(samples.iter().copied()) > 0.5);
+
+        let quiet: Vec<f32> = samples.iter().map(|s| s * 0.5).collect();
+        assert_eq!(clipping_ratio(quiet.iter().copied()), 0.0);
+        assert_eq!(clipping_ratio([]), 0.0);
+    }
+
+    #[test]
+    fn test_split_windows_with_overlap() {
+        let windows = split_windows(10, 4, 1);
+        assert_eq!(windows, vec![0..4, 3..7, 6..10]);
+
+        let windows = split_windows(3, 4, 1);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0], 0..3);
+    }
+
+    #[test]
+    fn test_apply_pre_emphasis() {
+        let mut samples = vec![1.0, 1.0, 0.5, 0.0];
+        apply_pre_emphasis(&mut samples, 0.5);
+
+        assert_eq!(samples, vec![1.0, 0.5, 0.0, -0.25]);
+    }
+
+    #[test]
+    fn test_apply_pre_emphasis_zero_coefficient_is_identity() {
+        let mut samples = vec![0.25, -0.5, 0.75];
+        apply_pre_emphasis(&mut samples, 0.0);
+
+        assert_eq!(samples, vec![0.25, -0.5, 0.75]);
+    }
+
+    fn tone(len: usize, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|n| amplitude * (2.0 * std::f32::consts::PI * 440.0 * n as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_frame_energies() {
+        let samples = vec![0.0; 160]
+            .into_iter()
+            .chain(vec![0.5; 160])
+            .collect::<Vec<_>>();
+        let energies = frame_energies(&samples, 160);
+
+        assert_eq!(energies.len(), 2);
+        assert_eq!(energies[0], 0.0);
+        assert!((energies[1] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_trim_silence_removes_padding() {
+        // 100 ms of silence, 200 ms of tone, 50 ms of silence
+        let mut samples = vec![0.0; 1600];
+        samples.extend(tone(3200, 0.5));
+        samples.extend(vec![0.0; 800]);
+
+        let trim = trim_silence(&mut samples, DEFAULT_SILENCE_THRESHOLD);
+
+        assert_eq!(trim.leading_ms(), 100);
+        assert_eq!(trim.trailing_ms(), 50);
+        assert_eq!(samples.len(), 3200);
+
+        // Segments timed against the trimmed buffer move back by the padding
+        let mut segments = vec![TranscriptionSegment {
+            start: 0.0,
+            end: 0.2,
+            text: "tone".to_string(),
+            confidence: None,
+            likely_new_speaker: None,
+        }];
+        offset_segment_timestamps(&mut segments, trim.leading_ms());
+        round_segment_timestamps(&mut segments, 3);
+        assert_eq!(segments[0].start, 0.1);
+        assert_eq!(segments[0].end, 0.3);
+    }
+
+    #[test]
+    fn test_trim_silence_keeps_all_silent_audio() {
+        let mut samples = vec![0.0; 1600];
+        let trim = trim_silence(&mut samples, DEFAULT_SILENCE_THRESHOLD);
+
+        assert_eq!(trim, SilenceTrim::default());
+        assert_eq!(samples.len(), 1600);
+    }
+
     // JSON audio processing tests - these would require mocking stdin which is complex
     // The actual functionality is tested through the transcription module tests
+
+    #[test]
+    fn test_audio_buffer_flushes_at_max_buffer_ms() {
+        // 100 ms pieces of a 250 ms stream
+        let mut buffer = AudioBuffer::with_max_buffer_ms(100);
+        let audio_data = AudioData {
+            data: vec![0; ms_to_samples(250) * 2],
+            timestamp: std::time::Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
+        };
+        buffer.process_audio(&audio_data).unwrap();
+
+        let first = buffer.take_audio_data().unwrap();
+        assert_eq!(first.data.len(), ms_to_samples(100) * 2);
+        // The rest is still waiting mid-stream
+        assert!(buffer.is_ready());
+        assert_eq!(buffer.total_bytes_received(), ms_to_samples(150) as u64 * 2);
+
+        let mut pieces = vec![(first.offset_ms, first.data.len())];
+        while let Some(piece) = buffer.take_audio_data() {
+            pieces.push((piece.offset_ms, piece.data.len()));
+        }
+        // Each piece knows where it starts in the request
+        assert_eq!(pieces, vec![(0, 3200), (100, 3200), (200, 1600)]);
+        assert!(!buffer.is_ready());
+        assert_eq!(buffer.total_bytes_received(), 0);
+    }
+
+    #[test]
+    fn test_audio_buffer_status() {
+        let mut buffer = AudioBuffer::new();
+        let status = buffer.status();
+        assert_eq!(status.buffered_bytes, 0);
+        assert!(!status.is_ready);
+
+        // Half a second of 16 kHz 16-bit audio
+        let audio_data = AudioData {
+            data: vec![0; 16000],
+            timestamp: std::time::Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
+        };
+        buffer.process_audio(&audio_data).unwrap();
+
+        let status = buffer.status();
+        assert_eq!(status.total_bytes_received, 16000);
+        assert_eq!(status.buffered_bytes, 16000);
+        assert!(status.is_ready);
+        assert_eq!(status.estimated_seconds, 0.5);
+    }
+
+    fn sequenced_chunk(seq: u64, data: Vec<u8>, timestamp: Instant) -> AudioData {
+        AudioData {
+            data,
+            timestamp,
+            seq: Some(seq),
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
+        }
+    }
+
+    #[test]
+    fn test_reorder_buffer_reassembles_out_of_order_chunks() {
+        let now = Instant::now();
+        let mut reorder = ReorderBuffer::new(Duration::from_secs(2));
+
+        // Chunks 2 and 1 arrive before 0 and are held
+        assert!(
+            reorder
+                .push(2, sequenced_chunk(2, vec![5, 6], now), now)
+                .is_none()
+        );
+        assert!(
+            reorder
+                .push(1, sequenced_chunk(1, vec![3, 4], now), now)
+                .is_none()
+        );
+        assert_eq!(reorder.pending_count(), 2);
+
+        let assembled = reorder
+            .push(0, sequenced_chunk(0, vec![1, 2], now), now)
+            .unwrap();
+        assert_eq!(assembled.data, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(assembled.seq, None);
+        assert_eq!(reorder.pending_count(), 0);
+
+        // The next in-order chunk is released immediately
+        let next = reorder
+            .push(3, sequenced_chunk(3, vec![7, 8], now), now)
+            .unwrap();
+        assert_eq!(next.data, vec![7, 8]);
+
+        // Duplicates of released chunks are dropped
+        assert!(
+            reorder
+                .push(1, sequenced_chunk(1, vec![3, 4], now), now)
+                .is_none()
+        );
+        assert_eq!(reorder.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_reorder_buffer_skips_missing_chunk_after_timeout() {
+        let start = Instant::now();
+        let mut reorder = ReorderBuffer::new(Duration::from_secs(2));
+        assert!(
+            reorder
+                .push(0, sequenced_chunk(0, vec![1], start), start)
+                .is_some()
+        );
+
+        // Chunk 1 never arrives; chunk 2 is held until the timeout passes
+        assert!(
+            reorder
+                .push(2, sequenced_chunk(2, vec![3], start), start)
+                .is_none()
+        );
+        let later = start + Duration::from_secs(3);
+        let assembled = reorder
+            .push(3, sequenced_chunk(3, vec![4], later), later)
+            .unwrap();
+        assert_eq!(assembled.data, vec![3, 4]);
+
+        // A straggler from before the skipped gap is dropped
+        assert!(
+            reorder
+                .push(1, sequenced_chunk(1, vec![2], later), later)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_reorder_buffer_expires_without_another_chunk() {
+        let start = Instant::now();
+        let mut reorder = ReorderBuffer::new(Duration::from_secs(2));
+        assert!(reorder.deadline().is_none());
+
+        // Chunk 0 never arrives and nothing follows chunks 1 and 2
+        reorder.push(1, sequenced_chunk(1, vec![2], start), start);
+        let later = start + Duration::from_secs(1);
+        reorder.push(2, sequenced_chunk(2, vec![3], later), later);
+        assert_eq!(reorder.deadline(), Some(start + Duration::from_secs(2)));
+
+        // Nothing is released before the deadline
+        assert!(reorder.expire(later).is_none());
+        assert_eq!(reorder.pending_count(), 2);
+
+        let assembled = reorder.expire(reorder.deadline().unwrap()).unwrap();
+        assert_eq!(assembled.data, vec![2, 3]);
+        assert_eq!(assembled.seq, None);
+        assert!(reorder.deadline().is_none());
+        assert!(reorder.is_duplicate(0));
+    }
+
+    #[test]
+    fn test_reorder_buffer_drain_releases_held_chunks_in_order() {
+        let now = Instant::now();
+        let mut reorder = ReorderBuffer::new(Duration::from_secs(2));
+        assert!(reorder.drain().is_none());
+
+        reorder.push(3, sequenced_chunk(3, vec![4], now), now);
+        reorder.push(1, sequenced_chunk(1, vec![2], now), now);
+        let drained = reorder.drain().unwrap();
+        assert_eq!(drained.data, vec![2, 4]);
+        assert_eq!(drained.seq, None);
+        assert_eq!(reorder.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_reorder_buffer_ignores_retried_chunks() {
+        let now = Instant::now();
+        let mut reorder = ReorderBuffer::new(Duration::from_secs(2));
+
+        // A retry of a chunk that was already released
+        let first = reorder
+            .push(0, sequenced_chunk(0, vec![1, 2], now), now)
+            .unwrap();
+        assert_eq!(first.data, vec![1, 2]);
+        assert!(reorder.is_duplicate(0));
+        assert!(
+            reorder
+                .push(0, sequenced_chunk(0, vec![1, 2], now), now)
+                .is_none()
+        );
+
+        // A retry of a chunk that is still held
+        assert!(
+            reorder
+                .push(2, sequenced_chunk(2, vec![5, 6], now), now)
+                .is_none()
+        );
+        assert!(reorder.is_duplicate(2));
+        assert!(
+            reorder
+                .push(2, sequenced_chunk(2, vec![5, 6], now), now)
+                .is_none()
+        );
+        assert_eq!(reorder.pending_count(), 1);
+
+        let assembled = reorder
+            .push(1, sequenced_chunk(1, vec![3, 4], now), now)
+            .unwrap();
+        assert_eq!(assembled.data, vec![3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_pipeline_applies_steps_in_configured_order() {
+        // Silence followed by a constant tone: pre-emphasis turns the tone
+        // into near-silence, so filtering first changes what gets trimmed
+        let mut input = vec![0.0; ENERGY_FRAME_SAMPLES * 2];
+        input.extend(vec![0.2; ENERGY_FRAME_SAMPLES * 4]);
+
+        let mut expected = input.clone();
+        apply_pre_emphasis(&mut expected, 0.97);
+        let expected_trim = trim_silence(&mut expected, DEFAULT_SILENCE_THRESHOLD);
+
+        let pipeline =
+            AudioPipeline::new(&[PreprocessStep::PreEmphasis, PreprocessStep::TrimSilence])
+                .with_trim_silence(DEFAULT_SILENCE_THRESHOLD)
+                .with_pre_emphasis(0.97);
+        let mut samples = input.clone();
+        assert_eq!(pipeline.run(&mut samples), Some(expected_trim));
+        assert_eq!(samples, expected);
+
+        let default_pipeline = AudioPipeline::new(&PreprocessStep::DEFAULT_ORDER)
+            .with_trim_silence(DEFAULT_SILENCE_THRESHOLD)
+            .with_pre_emphasis(0.97);
+        let mut default_samples = input;
+        default_pipeline.run(&mut default_samples);
+        assert_ne!(default_samples, samples);
+    }
+
+    #[test]
+    fn test_pipeline_skips_disabled_steps() {
+        let mut samples = vec![0.0, 0.5, 0.5];
+        let pipeline = AudioPipeline::new(&PreprocessStep::DEFAULT_ORDER);
+        assert_eq!(pipeline.run(&mut samples), None);
+        assert_eq!(samples, vec![0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_agc_raises_quiet_buffer_towards_target() {
+        let rms = |samples: &[f32]| {
+            (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+        };
+        let quiet = tone(16000, 0.03);
+        let before = rms(&quiet);
+
+        let mut samples = quiet.clone();
+        AudioPipeline::new(&PreprocessStep::DEFAULT_ORDER)
+            .with_agc(AgcSettings::default())
+            .run(&mut samples);
+        assert!((rms(&samples) - DEFAULT_AGC_TARGET_RMS).abs() < 0.001);
+
+        // The gain cap stops short of the target for very quiet input
+        let mut capped = quiet;
+        let gain = apply_agc(
+            &mut capped,
+            AgcSettings {
+                target_rms: 0.1,
+                max_gain: 2.0,
+            },
+        );
+        assert_eq!(gain, 2.0);
+        assert!((rms(&capped) - before * 2.0).abs() < 1e-4);
+
+        // Loud input is attenuated and silence is left alone
+        let mut loud = tone(16000, 0.9);
+        assert!(apply_agc(&mut loud, AgcSettings::default()) < 1.0);
+        let mut silence = vec![0.0; 160];
+        assert_eq!(apply_agc(&mut silence, AgcSettings::default()), 1.0);
+        assert_eq!(silence, vec![0.0; 160]);
+    }
+
+    #[test]
+    fn test_contains_speech() {
+        let mut samples = vec![0.001; ENERGY_FRAME_SAMPLES * 10];
+        assert!(!contains_speech(
+            samples.iter().copied(),
+            DEFAULT_SILENCE_THRESHOLD
+        ));
+
+        // A short loud tail in the final, partial frame still counts
+        samples.extend([0.5; 10]);
+        assert!(contains_speech(
+            samples.iter().copied(),
+            DEFAULT_SILENCE_THRESHOLD
+        ));
+        assert!(!contains_speech([], DEFAULT_SILENCE_THRESHOLD));
+    }
+
+    #[test]
+    fn test_pcm16_samples_decodes_little_endian() {
+        let samples: Vec<f32> =
+            pcm16_samples(&[0xFF, 0x7F, 0x00, 0x80, 0x00, 0x00, 0xFF, 0xFF]).collect();
+        assert_eq!(samples, vec![32767.0 / 32768.0, -1.0, 0.0, -1.0 / 32768.0]);
+        assert!((samples[0] - 0.999).abs() < 0.001);
+
+        // A trailing odd byte is dropped
+        assert_eq!(pcm16_samples(&[0x00, 0x80, 0x7F]).count(), 1);
+    }
+
+    #[test]
+    fn test_duration_mismatch_with_declared_duration() {
+        // One second of 16 kHz 16-bit audio
+        let mut audio = AudioData {
+            data: vec![0u8; 32000],
+            timestamp: Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: InputAudioInfo::default(),
+        };
+        assert_eq!(audio.computed_duration_ms(), 1000);
+        assert_eq!(audio.duration_mismatch(0), None);
+
+        audio.duration_ms = Some(1050);
+        assert_eq!(audio.duration_mismatch(DEFAULT_DURATION_TOLERANCE_MS), None);
+        assert!(audio.duration_mismatch(0).is_some());
+
+        audio.duration_ms = Some(2500);
+        assert_eq!(
+            audio.duration_mismatch(DEFAULT_DURATION_TOLERANCE_MS),
+            Some(DurationMismatch {
+                declared_ms: 2500,
+                computed_ms: 1000,
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_input_reader_reopens_fifo_for_each_writer() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("whisper-fifo-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("input.fifo");
+        let _ = std::fs::remove_file(&path);
+        let status = std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // Two clients connect one after the other
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            for lines in [
+                "{\"command\":\"buffer_status\"}\n{\"audio_data\":{\"data\":\"AAA=\"}}\n",
+                "{\"audio_data\":{\"data\":[1,0,2,0]}}\n",
+            ] {
+                let mut fifo = std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&writer_path)
+                    .unwrap();
+                fifo.write_all(lines.as_bytes()).unwrap();
+            }
+        });
+
+        let mut input = InputReader::fifo(&path).await.unwrap();
+        let Some(InputMessage::Command(command)) = read_json_audio(&mut input).await.unwrap()
+        else {
+            panic!("expected a command");
+        };
+        assert_eq!(command.command, "buffer_status");
+        let Some(InputMessage::Audio(first)) = read_json_audio(&mut input).await.unwrap() else {
+            panic!("expected audio");
+        };
+        assert_eq!(first.data, vec![0, 0]);
+        let Some(InputMessage::Audio(second)) = read_json_audio(&mut input).await.unwrap() else {
+            panic!("expected audio from the second writer");
+        };
+        assert_eq!(second.data, vec![1, 0, 2, 0]);
+
+        writer.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_input_reader_rejects_regular_file() {
+        let path = std::env::temp_dir().join(format!("whisper-not-fifo-{}", std::process::id()));
+        std::fs::write(&path, "{}\n").unwrap();
+        assert!(InputReader::fifo(&path).await.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Minimal WAV file around `data`
+    fn wav_file(sample_rate: u32, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        bytes.extend_from_slice(&block_align.to_le_bytes());
+        bytes.extend_from_slice(&bits.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_wav() {
+        let data = [1, 0, 2, 0, 3, 0];
+        let (format, pcm) = parse_wav(&wav_file(44100, 2, 16, &data)).unwrap();
+        assert_eq!(
+            format,
+            AudioFormatInfo {
+                sample_rate: 44100,
+                channels: 2,
+                bits_per_sample: 16,
+            }
+        );
+        assert_eq!(pcm, data);
+
+        assert!(parse_wav(&data).is_err());
+        assert!(parse_wav(&wav_file(16000, 1, 16, &data)[..36]).is_err());
+    }
+
+    #[test]
+    fn test_parse_wav_skips_unknown_chunks() {
+        let mut bytes = wav_file(16000, 1, 16, &[5, 0]);
+        // Insert an odd-sized LIST chunk (with its pad byte) before the fmt chunk
+        let list = [b"LIST".as_slice(), &3u32.to_le_bytes(), &[9, 9, 9, 0]].concat();
+        bytes.splice(12..12, list);
+        assert_eq!(parse_wav(&bytes).unwrap().1, vec![5, 0]);
+    }
+
+    #[test]
+    fn test_strip_wav_container() {
+        let data = vec![1, 0, 2, 0];
+        let wav = wav_file(16000, 1, 16, &data);
+
+        // Detected by magic or by hint; raw PCM is left alone
+        assert_eq!(
+            strip_wav_container(None, wav.clone()).unwrap(),
+            (Some(AudioFormatInfo::default()), data.clone())
+        );
+        assert_eq!(strip_wav_container(Some("WAV"), wav).unwrap().1, data);
+        assert_eq!(
+            strip_wav_container(Some("pcm"), data.clone()).unwrap(),
+            (None, data.clone())
+        );
+        assert!(matches!(
+            strip_wav_container(Some("wav"), data),
+            Err(JsonError::AudioDataError(_))
+        ));
+
+        let stereo = wav_file(16000, 2, 16, &[0; 8]);
+        let (input_audio, pcm) = normalize_audio(None, AudioFormatInfo::default(), stereo).unwrap();
+        assert_eq!(pcm.len(), 4);
+        assert_eq!(input_audio.channels, 2);
+        assert!(input_audio.downmixed);
+        assert!(!input_audio.resampled);
+        let eight_bit = wav_file(16000, 1, 8, &[0; 4]);
+        let error = normalize_audio(None, AudioFormatInfo::default(), eight_bit).unwrap_err();
+        assert!(error.to_string().contains("8 bits"));
+    }
+
+    /// Sine tone of `freq` Hz lasting one second at `rate`
+    fn sine_second(freq: f32, rate: u32) -> Vec<f32> {
+        (0..rate)
+            .map(|n| 0.5 * (2.0 * std::f32::consts::PI * freq * n as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    /// Number of sign changes from negative to non-negative
+    fn rising_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn test_resample_to_16k_preserves_tone() {
+        let resampled = resample_to_16k(&sine_second(440.0, 48000), 48000);
+        assert_eq!(resampled.len(), 16000);
+        // One rising crossing per cycle
+        assert!(rising_crossings(&resampled).abs_diff(440) <= 1);
+        // The amplitude survives away from the edges
+        let peak = resampled[1000..15000]
+            .iter()
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.5).abs() < 0.02, "peak {}", peak);
+
+        let upsampled = resample_to_16k(&sine_second(440.0, 8000), 8000);
+        assert_eq!(upsampled.len(), 16000);
+        assert!(rising_crossings(&upsampled).abs_diff(440) <= 1);
+    }
+
+    #[test]
+    fn test_resample_to_16k_filters_content_above_nyquist() {
+        // 12 kHz cannot be represented at 16 kHz and must not alias to 4 kHz
+        let resampled = resample_to_16k(&sine_second(12000.0, 48000), 48000);
+        let rms = (resampled[1000..15000].iter().map(|s| s * s).sum::<f32>() / 14000.0).sqrt();
+        assert!(rms < 0.01, "rms {}", rms);
+    }
+
+    #[test]
+    fn test_normalize_audio_resamples() {
+        let bytes = pcm16_bytes(&sine_second(440.0, 48000));
+        let raw = |sample_rate| AudioFormatInfo {
+            sample_rate,
+            ..Default::default()
+        };
+        let (input_audio, pcm) = normalize_audio(None, raw(16000), bytes.clone()).unwrap();
+        assert_eq!(pcm, bytes);
+        assert_eq!(input_audio, InputAudioInfo::default());
+
+        let (input_audio, pcm) = normalize_audio(None, raw(48000), bytes.clone()).unwrap();
+        assert_eq!(pcm.len(), 32000);
+        assert_eq!(input_audio.sample_rate, 48000);
+        assert!(input_audio.resampled);
+        assert!(!input_audio.downmixed);
+
+        let wav = wav_file(48000, 1, 16, &bytes);
+        let (input_audio, pcm) = normalize_audio(None, raw(16000), wav).unwrap();
+        assert_eq!(pcm.len(), 32000);
+        assert_eq!(input_audio.sample_rate, 48000);
+        assert!(input_audio.resampled);
+
+        // Rates outside the supported range are rejected before resampling
+        for rate in [0, 1, 7999, 192001] {
+            assert!(matches!(
+                normalize_audio(None, raw(rate), bytes.clone()),
+                Err(JsonError::InvalidFieldValue(ref field, _)) if field == "sample_rate"
+            ));
+        }
+        assert!(matches!(
+            normalize_audio(None, raw(48000), wav_file(1, 1, 16, &bytes)),
+            Err(JsonError::AudioDataError(_))
+        ));
+    }
+
+    #[test]
+    fn test_downmix_to_mono() {
+        let stereo = [0.2, 0.4, -0.5, 0.5, 1.0, 0.0, 0.3, 0.1];
+        let mono = downmix_to_mono(&stereo, 2);
+        assert_eq!(mono.len(), stereo.len() / 2);
+        for (sample, pair) in mono.iter().zip(stereo.chunks(2)) {
+            assert!((sample - (pair[0] + pair[1]) / 2.0).abs() < 1e-6);
+        }
+
+        // A trailing incomplete frame is dropped
+        assert_eq!(downmix_to_mono(&[0.1, 0.3, 0.5], 2).len(), 1);
+        assert_eq!(downmix_to_mono(&[0.1, 0.3], 1), vec![0.1, 0.3]);
+    }
+
+    #[test]
+    fn test_normalize_audio_downmixes_declared_channels() {
+        let options = transcription::TranscriptionOptions {
+            channels: Some(2),
+            ..Default::default()
+        };
+        let format = raw_audio_format(Some(&options));
+        assert_eq!(format.channels, 2);
+        assert_eq!(format.sample_rate, SAMPLE_RATE);
+
+        let stereo = pcm16_bytes(&[0.5, 0.25, -0.5, 0.0]);
+        assert_eq!(
+            normalize_audio(None, format, stereo).unwrap().1,
+            pcm16_bytes(&[0.375, -0.25])
+        );
+    }
+
+    #[test]
+    fn test_pcm16_bytes_round_trip() {
+        let bytes = vec![0xFF, 0x7F, 0x00, 0x80, 0x00, 0x00, 0x34, 0x12];
+        let samples: Vec<f32> = pcm16_samples(&bytes).collect();
+        assert_eq!(pcm16_bytes(&samples), bytes);
+        assert_eq!(pcm16_bytes(&[2.0, -2.0]), vec![0xFF, 0x7F, 0x00, 0x80]);
+    }
 }
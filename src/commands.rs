@@ -0,0 +1,122 @@
+//! Control commands sent on stdin alongside audio requests
+//!
+//! A line whose JSON object carries a string `command` field is treated as a
+//! control command (e.g. `{"command":"list_output_formats"}`) rather than a
+//! transcription request.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Return the identifiers of the supported output formats
+pub const LIST_OUTPUT_FORMATS: &str = "list_output_formats";
+
+/// Return the state of the audio buffer
+pub const BUFFER_STATUS: &str = "buffer_status";
+
+/// Replace the loaded model with the one at `path`
+pub const LOAD_MODEL: &str = "load_model";
+
+/// Return the most recent transcription results (with `--keep-recent`)
+pub const RECENT_RESULTS: &str = "recent_results";
+
+/// Every command the server understands
+pub const KNOWN_COMMANDS: &[&str] = &[
+    LIST_OUTPUT_FORMATS,
+    BUFFER_STATUS,
+    LOAD_MODEL,
+    RECENT_RESULTS,
+];
+
+/// Whether `name` is a command the server understands
+pub fn is_known(name: &str) -> bool {
+    KNOWN_COMMANDS.contains(&name)
+}
+
+/// A control command read from stdin
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandRequest {
+    /// Command name
+    pub command: String,
+    /// Remaining fields of the command object
+    #[serde(flatten)]
+    pub args: Map<String, Value>,
+}
+
+impl CommandRequest {
+    /// Get a string argument of the command
+    ///
+    /// # Arguments
+    /// * `name` - Argument name
+    ///
+    /// # Returns
+    /// * `Option<&str>` - The argument if present and a string
+    pub fn string_arg(&self, name: &str) -> Option<&str> {
+        self.args.get(name).and_then(Value::as_str)
+    }
+
+    /// Get a non-negative integer argument of the command
+    ///
+    /// # Arguments
+    /// * `name` - Argument name
+    ///
+    /// # Returns
+    /// * `Option<usize>` - The argument if present and a non-negative integer
+    pub fn usize_arg(&self, name: &str) -> Option<usize> {
+        self.args
+            .get(name)
+            .and_then(Value::as_u64)
+            .map(|value| value as usize)
+    }
+}
+
+/// Parse a stdin line as a control command
+///
+/// # Arguments
+/// * `line` - Raw JSON line
+///
+/// # Returns
+/// * `Option<CommandRequest>` - The command if the line is an object with a string `command` field
+pub fn parse_command(line: &str) -> Option<CommandRequest> {
+    serde_json::from_str(line).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let command = parse_command(r#"{"command":"list_output_formats"}"#).unwrap();
+        assert_eq!(command.command, LIST_OUTPUT_FORMATS);
+    }
+
+    #[test]
+    fn test_parse_command_ignores_audio_requests() {
+        assert!(parse_command(r#"{"audio_data":{"data":"AAAA"}}"#).is_none());
+        assert!(parse_command(r#"{"command":5}"#).is_none());
+        assert!(parse_command("not json").is_none());
+    }
+
+    #[test]
+    fn test_parse_command_arguments() {
+        let command =
+            parse_command(r#"{"command":"load_model","path":"/models/ggml-small.bin"}"#).unwrap();
+        assert_eq!(command.command, LOAD_MODEL);
+        assert_eq!(command.string_arg("path"), Some("/models/ggml-small.bin"));
+        assert_eq!(command.string_arg("missing"), None);
+    }
+
+    #[test]
+    fn test_is_known() {
+        assert!(is_known(LOAD_MODEL));
+        assert!(!is_known("foo"));
+    }
+
+    #[test]
+    fn test_usize_arg() {
+        let command = parse_command(r#"{"command":"recent_results","limit":3,"bad":-1}"#).unwrap();
+        assert_eq!(command.usize_arg("limit"), Some(3));
+        assert_eq!(command.usize_arg("bad"), None);
+        assert_eq!(command.usize_arg("missing"), None);
+    }
+}
@@ -1,7 +1,13 @@
 use std::path::Path;
+use std::str::FromStr;
+
+use log::info;
+
+use crate::audio::PreprocessStep;
+use crate::logging::LogFormat;
 
 /// Configuration structure for the Whisper Background Server
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Config {
     /// Path to the model file (required)
     pub model_path: String,
@@ -9,6 +15,311 @@ pub struct Config {
     pub threads: Option<usize>,
     /// Whether to enforce CPU-only mode (optional, defaults to false)
     pub cpu_only: bool,
+    /// Process nice value applied at startup (optional, -20 to 19)
+    pub nice: Option<i32>,
+    /// Scheduling priority preset applied at startup when `nice` is not given
+    pub thread_priority: Option<ThreadPriority>,
+    /// Pre-emphasis filter coefficient applied before inference (0.0 to <1.0)
+    pub pre_emphasis: Option<f32>,
+    /// Whether to trim leading/trailing silence before inference
+    pub trim_silence: bool,
+    /// Whether to apply automatic gain control before inference
+    pub agc: bool,
+    /// RMS level automatic gain control aims for (default when None)
+    pub agc_target_rms: Option<f32>,
+    /// Largest gain automatic gain control applies (default when None)
+    pub agc_max_gain: Option<f32>,
+    /// Window length in seconds for transcribing long audio in chunks
+    pub chunk_secs: Option<u64>,
+    /// Overlap shared by consecutive chunks in milliseconds
+    pub chunk_overlap_ms: Option<u64>,
+    /// Interval in seconds between heartbeat events while idle
+    pub heartbeat_secs: Option<u64>,
+    /// Format used to write transcription results to stdout
+    pub output_format: OutputFormat,
+    /// JSON file with per-language configuration overrides
+    pub language_overrides_path: Option<String>,
+    /// Streams that error results and diagnostics are written to
+    pub errors_to: ErrorStream,
+    /// Stream non-result events are written to
+    pub events_to: EventStream,
+    /// FIFO requests are read from instead of stdin
+    pub input_fifo: Option<String>,
+    /// Unix socket whose connections requests are read from instead of stdin
+    pub socket_path: Option<String>,
+    /// TCP address whose connections requests are read from instead of stdin
+    pub listen_addr: Option<String>,
+    /// Most socket connections open at once (default when None)
+    pub max_connections: Option<usize>,
+    /// Accept `load_model` from socket connections
+    pub allow_remote_load_model: bool,
+    /// Number of recent results kept for `recent_results` (disabled when None)
+    pub keep_recent: Option<usize>,
+    /// Fields kept in JSON results (all fields when None)
+    pub output_fields: Option<Vec<String>>,
+    /// Decimal places segment timestamps are rounded to
+    pub timestamp_decimals: Option<u32>,
+    /// Number of idle Whisper states kept for reuse
+    pub state_pool_size: Option<usize>,
+    /// Enable flash attention on GPU builds
+    pub flash_attn: bool,
+    /// Alignment-head preset for DTW token timestamps
+    pub dtw_preset: Option<String>,
+    /// Longest piece of audio transcribed at once, in milliseconds
+    pub max_buffer_ms: Option<u64>,
+    /// Report an English translation alongside the original text
+    pub with_translation: bool,
+    /// Segments shorter than this many milliseconds are filtered out
+    pub min_segment_ms: Option<u64>,
+    /// Most segments returned per result
+    pub max_segments: Option<usize>,
+    /// Merge filtered segments' text into their neighbours
+    pub merge_short_segments: bool,
+    /// Produce byte-identical output for identical input
+    pub deterministic: bool,
+    /// Mark segments that follow a long pause as likely speaker changes
+    pub speaker_hints: bool,
+    /// Pause in milliseconds treated as a likely speaker change
+    pub speaker_gap_ms: Option<u64>,
+    /// Idle interval in seconds between keepalive inferences
+    pub keepalive_secs: Option<u64>,
+    /// Include per-request timings in each result
+    pub include_metrics: bool,
+    /// Retry greedily when beam search produces no text
+    pub greedy_fallback: bool,
+    /// Results with a lower average log probability are marked low-confidence
+    pub min_avg_logprob: Option<f32>,
+    /// Maximum number of audio requests admitted per second
+    pub max_rps: Option<f64>,
+    /// Whether requests over the rate wait or are rejected
+    pub rate_limit_policy: RateLimitPolicy,
+    /// How long sequenced chunks wait for a missing earlier chunk in milliseconds
+    pub reorder_timeout_ms: Option<u64>,
+    /// Re-group words into punctuation- and pause-delimited phrases
+    pub phrase_segments: bool,
+    /// Pause in milliseconds that ends a phrase
+    pub phrase_gap_ms: Option<u64>,
+    /// Number of leading transcriptions left out of the session average
+    pub exclude_cold_start: Option<u64>,
+    /// Upper bounds in milliseconds of the transcription time histogram
+    pub latency_buckets_ms: Option<Vec<u64>>,
+    /// Include the effective sampling setup in each result
+    pub debug_decoding: bool,
+    /// Order the preprocessing steps run in (default order when None)
+    pub preprocess_order: Option<Vec<PreprocessStep>>,
+    /// Emit each segment as soon as it is decoded
+    pub stream_segments: bool,
+    /// Convert long audio one window at a time to bound peak memory
+    pub low_memory: bool,
+    /// Skip inference on audio that is silent throughout
+    pub skip_silent: bool,
+    /// Largest declared vs computed duration difference in milliseconds
+    pub duration_tolerance_ms: Option<u64>,
+    /// Reject requests whose declared duration disagrees with their audio
+    pub strict_duration: bool,
+    /// Format log records are written to stderr in
+    pub log_format: LogFormat,
+}
+
+/// Alignment-head presets accepted by `--dtw`, named after the models they fit
+pub const DTW_PRESETS: &[&str] = &[
+    "tiny",
+    "tiny.en",
+    "base",
+    "base.en",
+    "small",
+    "small.en",
+    "medium",
+    "medium.en",
+    "large.v1",
+    "large.v2",
+    "large.v3",
+    "large.v3.turbo",
+];
+
+/// Streams error results and diagnostics can be routed to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorStream {
+    /// Error results are written to stdout alongside successful results
+    #[default]
+    Stdout,
+    /// Error results are written to stderr only
+    Stderr,
+    /// Error results are written to both streams
+    Both,
+}
+
+impl ErrorStream {
+    /// Whether errors are written to stdout
+    pub fn to_stdout(self) -> bool {
+        matches!(self, ErrorStream::Stdout | ErrorStream::Both)
+    }
+
+    /// Whether errors are written to stderr
+    pub fn to_stderr(self) -> bool {
+        matches!(self, ErrorStream::Stderr | ErrorStream::Both)
+    }
+}
+
+impl FromStr for ErrorStream {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(ErrorStream::Stdout),
+            "stderr" => Ok(ErrorStream::Stderr),
+            "both" => Ok(ErrorStream::Both),
+            _ => Err(format!(
+                "Invalid error stream: {} (expected stdout, stderr or both)",
+                s
+            )),
+        }
+    }
+}
+
+/// Streams non-result events (server info, heartbeats, warnings, command
+/// responses and the session summary) can be routed to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventStream {
+    /// Events are written to stdout alongside results
+    #[default]
+    Stdout,
+    /// Events are written to stderr
+    Stderr,
+    /// Events are written to file descriptor 3, which the parent must open
+    Fd3,
+}
+
+impl FromStr for EventStream {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(EventStream::Stdout),
+            "stderr" => Ok(EventStream::Stderr),
+            "fd3" => Ok(EventStream::Fd3),
+            _ => Err(format!(
+                "Invalid event stream: {} (expected stdout, stderr or fd3)",
+                s
+            )),
+        }
+    }
+}
+
+/// Formats transcription results can be written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One JSON object per result
+    #[default]
+    Json,
+    /// Plain transcribed text, one line per result
+    Text,
+    /// OpenAI verbose transcription response, one JSON object per result
+    OpenAi,
+    /// whisper.cpp `--output-json` structure, one JSON object per result
+    WhisperCpp,
+}
+
+impl OutputFormat {
+    /// Every supported format, in the order they are listed to clients
+    pub const ALL: &'static [OutputFormat] = &[
+        OutputFormat::Json,
+        OutputFormat::Text,
+        OutputFormat::OpenAi,
+        OutputFormat::WhisperCpp,
+    ];
+
+    /// Identifier used on the command line and in `list_output_formats`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Text => "text",
+            OutputFormat::OpenAi => "openai",
+            OutputFormat::WhisperCpp => "whisper-cpp",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| {
+                let ids: Vec<&str> = OutputFormat::ALL.iter().map(|f| f.as_str()).collect();
+                format!(
+                    "Invalid output format: {} (expected one of {})",
+                    s,
+                    ids.join(", ")
+                )
+            })
+    }
+}
+
+/// Scheduling priority presets for the transcription threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadPriority {
+    /// Yield to other work on the machine
+    Low,
+    /// Leave the scheduler defaults untouched
+    Normal,
+    /// Favour transcription latency (usually requires elevated privileges)
+    High,
+}
+
+impl ThreadPriority {
+    /// Nice value the preset maps to
+    pub fn nice_value(self) -> i32 {
+        match self {
+            ThreadPriority::Low => 10,
+            ThreadPriority::Normal => 0,
+            ThreadPriority::High => -10,
+        }
+    }
+}
+
+impl FromStr for ThreadPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(ThreadPriority::Low),
+            "normal" => Ok(ThreadPriority::Normal),
+            "high" => Ok(ThreadPriority::High),
+            _ => Err(format!(
+                "Invalid thread priority: {} (expected low, normal or high)",
+                s
+            )),
+        }
+    }
+}
+
+/// What happens to requests that arrive faster than `--max-rps`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitPolicy {
+    /// Wait until the request can be admitted
+    #[default]
+    Block,
+    /// Reject the request with a `RATE_LIMITED` error
+    Reject,
+}
+
+impl FromStr for RateLimitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(RateLimitPolicy::Block),
+            "reject" => Ok(RateLimitPolicy::Reject),
+            _ => Err(format!(
+                "Invalid rate limit policy: {} (expected block or reject)",
+                s
+            )),
+        }
+    }
 }
 
 /// Parse command line arguments and return configuration
@@ -19,6 +330,28 @@ pub struct Config {
 /// # Returns
 /// * `Result<Config, String>` - Configuration on success, error message on failure
 pub fn parse_arguments<I, S>(args: I) -> Result<Config, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let config = parse_argument_values(args)?;
+
+    // Validate that the model path exists
+    if !Path::new(&config.model_path).exists() {
+        return Err(format!("Model path does not exist: {}", config.model_path));
+    }
+
+    Ok(config)
+}
+
+/// Parse command line arguments without checking the model path on disk
+///
+/// # Arguments
+/// * `args` - Iterator over command line arguments
+///
+/// # Returns
+/// * `Result<Config, String>` - Configuration on success, error message on failure
+fn parse_argument_values<I, S>(args: I) -> Result<Config, String>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<str>,
@@ -36,11 +369,7 @@ where
         return Err("Model path is required. Usage: whisper-background-server <model-path> [--threads <number>] [--cpu-only]".to_string());
     }
 
-    let mut config = Config {
-        model_path: String::new(),
-        threads: None,
-        cpu_only: false,
-    };
+    let mut config = Config::default();
 
     let mut i = 0;
     while i < args.len() {
@@ -50,12 +379,6 @@ where
             // Model path (positional argument, first argument)
             _ if i == 0 => {
                 config.model_path = arg.clone();
-
-                // Validate that the model path exists
-                if !Path::new(&config.model_path).exists() {
-                    return Err(format!("Model path does not exist: {}", config.model_path));
-                }
-
                 i += 1;
             }
 
@@ -86,245 +409,2026 @@ where
                 i += 1;
             }
 
-            // Unknown argument
-            _ => {
-                return Err(format!("Unknown argument: {}", arg));
+            // Flash attention flag
+            "--flash-attn" => {
+                config.flash_attn = true;
+                i += 1;
             }
-        }
-    }
 
-    // Validate that we have a model path
-    if config.model_path.is_empty() {
-        return Err("Model path is required".to_string());
-    }
+            // Bilingual output flag
+            "--with-translation" => {
+                config.with_translation = true;
+                i += 1;
+            }
 
-    Ok(config)
-}
+            // Short segment filter options
+            "--min-segment-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--min-segment-ms option requires a value".to_string());
+                }
 
-/// Validate that the model path exists and has the correct extension
-///
-/// # Arguments
-/// * `model_path` - Path to the model file
-///
-/// # Returns
-/// * `Result<(), String>` - Ok if valid, error message if invalid
-pub fn validate_model_path(model_path: &str) -> Result<(), String> {
-    let path = Path::new(model_path);
+                let ms_str = &args[i + 1];
+                match ms_str.parse::<u64>() {
+                    Ok(ms) if ms > 0 => {
+                        config.min_segment_ms = Some(ms);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid minimum segment length: {}", ms_str));
+                    }
+                }
+            }
 
-    // Check if file exists
-    if !path.exists() {
-        return Err(format!("Model file does not exist: {}", model_path));
-    }
+            // Segment count limit option
+            "--max-segments" => {
+                if i + 1 >= args.len() {
+                    return Err("--max-segments option requires a value".to_string());
+                }
 
-    // Check if it's a file (not a directory)
-    if !path.is_file() {
-        return Err(format!("Model path is not a file: {}", model_path));
-    }
+                let count_str = &args[i + 1];
+                match count_str.parse::<usize>() {
+                    Ok(count) if count > 0 => {
+                        config.max_segments = Some(count);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid segment limit: {}", count_str));
+                    }
+                }
+            }
 
-    // Check file extension
-    if let Some(extension) = path.extension() {
-        if extension != "bin" {
-            return Err(format!(
-                "Model file must have .bin extension, got: {:?}",
-                extension
-            ));
-        }
-    } else {
-        return Err(format!("Model file has no extension: {}", model_path));
-    }
+            "--merge-short-segments" => {
+                config.merge_short_segments = true;
+                i += 1;
+            }
 
-    // Get file size
-    if let Ok(metadata) = path.metadata() {
-        if metadata.len() == 0 {
-            return Err(format!("Model file is empty: {}", model_path));
-        }
-    } else {
-        return Err(format!("Cannot read model file metadata: {}", model_path));
-    }
+            // Reproducible output flag
+            "--deterministic" => {
+                config.deterministic = true;
+                i += 1;
+            }
 
-    Ok(())
-}
+            // Empty beam search fallback option
+            "--greedy-fallback" => {
+                config.greedy_fallback = true;
+                i += 1;
+            }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+            // Incremental segment output option
+            "--stream-segments" => {
+                config.stream_segments = true;
+                i += 1;
+            }
 
-    // Mock the path existence check for tests
-    fn mock_parse_arguments<I, S>(args: I) -> Result<Config, String>
-    where
-        I: IntoIterator<Item = S>,
-        S: AsRef<str>,
-    {
-        let mut args: Vec<String> = args.into_iter().map(|s| s.as_ref().to_string()).collect();
+            // Effective sampling setup option
+            "--debug-decoding" => {
+                config.debug_decoding = true;
+                i += 1;
+            }
 
-        // Remove the program name from arguments
-        if args.is_empty() {
-            return Err("No arguments provided. Usage: whisper-background-server <model-path> [--threads <number>] [--cpu-only]".to_string());
-        }
+            // Per-request metrics option
+            "--include-metrics" => {
+                config.include_metrics = true;
+                i += 1;
+            }
 
-        args.remove(0); // Remove program name
+            // Speaker-turn hint options
+            "--speaker-hints" => {
+                config.speaker_hints = true;
+                i += 1;
+            }
 
-        if args.is_empty() {
-            return Err("Model path is required. Usage: whisper-background-server <model-path> [--threads <number>] [--cpu-only]".to_string());
-        }
+            "--speaker-gap-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--speaker-gap-ms option requires a value".to_string());
+                }
 
-        let mut config = Config {
-            model_path: String::new(),
-            threads: None,
-            cpu_only: false,
-        };
-
-        let mut i = 0;
-        while i < args.len() {
-            let arg = &args[i];
-
-            match arg.as_str() {
-                // Model path (positional argument, first argument)
-                _ if i == 0 => {
-                    config.model_path = arg.clone();
-                    i += 1;
-                }
-
-                // Threads option
-                "--threads" => {
-                    if i + 1 >= args.len() {
-                        return Err("--threads option requires a value".to_string());
-                    }
-
-                    let threads_str = &args[i + 1];
-                    match threads_str.parse::<usize>() {
-                        Ok(threads) => {
-                            if threads == 0 {
-                                return Err("Number of threads must be greater than 0".to_string());
-                            }
-                            config.threads = Some(threads);
-                            i += 2; // Skip the next argument (the value)
-                        }
-                        Err(_) => {
-                            return Err(format!("Invalid number of threads: {}", threads_str));
-                        }
+                let ms_str = &args[i + 1];
+                match ms_str.parse::<u64>() {
+                    Ok(ms) if ms > 0 => {
+                        config.speaker_gap_ms = Some(ms);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid speaker gap: {}", ms_str));
                     }
                 }
+            }
+
+            // Phrase grouping options
+            "--phrase-segments" => {
+                config.phrase_segments = true;
+                i += 1;
+            }
 
-                // CPU-only flag
-                "--cpu-only" => {
-                    config.cpu_only = true;
-                    i += 1;
+            "--phrase-gap-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--phrase-gap-ms option requires a value".to_string());
                 }
 
-                // Unknown argument
-                _ => {
-                    return Err(format!("Unknown argument: {}", arg));
+                let ms_str = &args[i + 1];
+                match ms_str.parse::<u64>() {
+                    Ok(ms) if ms > 0 => {
+                        config.phrase_gap_ms = Some(ms);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid phrase gap: {}", ms_str));
+                    }
                 }
             }
-        }
 
-        // Validate that we have a model path
-        if config.model_path.is_empty() {
-            return Err("Model path is required".to_string());
-        }
+            // DTW token timestamps option
+            "--dtw" => {
+                if i + 1 >= args.len() {
+                    return Err("--dtw option requires a value".to_string());
+                }
 
-        Ok(config)
-    }
+                let preset = &args[i + 1];
+                if !DTW_PRESETS.contains(&preset.as_str()) {
+                    return Err(format!(
+                        "Invalid DTW preset: {} (expected one of: {})",
+                        preset,
+                        DTW_PRESETS.join(", ")
+                    ));
+                }
+                config.dtw_preset = Some(preset.clone());
+                i += 2;
+            }
 
-    #[test]
-    fn test_parse_arguments_minimal() {
-        let args = vec!["program_name".to_string(), "/path/to/model.bin".to_string()];
+            // Nice value option
+            "--nice" => {
+                if i + 1 >= args.len() {
+                    return Err("--nice option requires a value".to_string());
+                }
 
-        let config = mock_parse_arguments(args).unwrap();
-        assert_eq!(config.model_path, "/path/to/model.bin");
-        assert_eq!(config.threads, None);
-        assert_eq!(config.cpu_only, false);
-    }
+                let nice_str = &args[i + 1];
+                match nice_str.parse::<i32>() {
+                    Ok(nice) => {
+                        if !(-20..=19).contains(&nice) {
+                            return Err("Nice value must be between -20 and 19".to_string());
+                        }
+                        config.nice = Some(nice);
+                        i += 2;
+                    }
+                    Err(_) => {
+                        return Err(format!("Invalid nice value: {}", nice_str));
+                    }
+                }
+            }
 
-    #[test]
-    fn test_parse_arguments_with_threads() {
-        let args = vec![
-            "program_name".to_string(),
-            "/path/to/model.bin".to_string(),
-            "--threads".to_string(),
-            "4".to_string(),
-        ];
+            // Thread priority option
+            "--thread-priority" => {
+                if i + 1 >= args.len() {
+                    return Err("--thread-priority option requires a value".to_string());
+                }
 
-        let config = mock_parse_arguments(args).unwrap();
-        assert_eq!(config.model_path, "/path/to/model.bin");
-        assert_eq!(config.threads, Some(4));
-        assert_eq!(config.cpu_only, false);
-    }
+                config.thread_priority = Some(args[i + 1].parse()?);
+                i += 2;
+            }
 
-    #[test]
-    fn test_parse_arguments_with_cpu_only() {
-        let args = vec![
-            "program_name".to_string(),
-            "/path/to/model.bin".to_string(),
-            "--cpu-only".to_string(),
-        ];
+            // Request rate limit options
+            "--max-rps" => {
+                if i + 1 >= args.len() {
+                    return Err("--max-rps option requires a value".to_string());
+                }
 
-        let config = mock_parse_arguments(args).unwrap();
-        assert_eq!(config.model_path, "/path/to/model.bin");
-        assert_eq!(config.threads, None);
-        assert_eq!(config.cpu_only, true);
-    }
+                let rps_str = &args[i + 1];
+                match rps_str.parse::<f64>() {
+                    Ok(rps) if rps.is_finite() && rps > 0.0 => {
+                        config.max_rps = Some(rps);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid request rate: {}", rps_str));
+                    }
+                }
+            }
 
-    #[test]
-    fn test_parse_arguments_with_both_options() {
-        let args = vec![
-            "program_name".to_string(),
-            "/path/to/model.bin".to_string(),
-            "--threads".to_string(),
-            "8".to_string(),
-            "--cpu-only".to_string(),
-        ];
+            "--rate-limit-policy" => {
+                if i + 1 >= args.len() {
+                    return Err("--rate-limit-policy option requires a value".to_string());
+                }
 
-        let config = mock_parse_arguments(args).unwrap();
-        assert_eq!(config.model_path, "/path/to/model.bin");
-        assert_eq!(config.threads, Some(8));
-        assert_eq!(config.cpu_only, true);
-    }
+                config.rate_limit_policy = args[i + 1].parse()?;
+                i += 2;
+            }
 
-    #[test]
-    fn test_parse_arguments_no_model_path() {
-        let args = vec!["program_name".to_string()];
+            // Output format option
+            "--output-format" => {
+                if i + 1 >= args.len() {
+                    return Err("--output-format option requires a value".to_string());
+                }
 
-        let result = mock_parse_arguments(args);
-        assert!(result.is_err());
-    }
+                config.output_format = args[i + 1].parse()?;
+                i += 2;
+            }
 
-    #[test]
+            // Output field allowlist option
+            "--output-fields" => {
+                if i + 1 >= args.len() {
+                    return Err("--output-fields option requires a value".to_string());
+                }
+
+                let fields: Vec<String> = args[i + 1]
+                    .split(',')
+                    .map(|field| field.trim().to_string())
+                    .filter(|field| !field.is_empty())
+                    .collect();
+                if fields.is_empty() {
+                    return Err("--output-fields requires at least one field".to_string());
+                }
+                config.output_fields = Some(fields);
+                i += 2;
+            }
+
+            // Timestamp rounding option
+            "--timestamp-decimals" => {
+                if i + 1 >= args.len() {
+                    return Err("--timestamp-decimals option requires a value".to_string());
+                }
+
+                let decimals_str = &args[i + 1];
+                match decimals_str.parse::<u32>() {
+                    Ok(decimals) if decimals <= 6 => {
+                        config.timestamp_decimals = Some(decimals);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Invalid timestamp decimals: {} (expected 0 to 6)",
+                            decimals_str
+                        ));
+                    }
+                }
+            }
+
+            // State pool option
+            "--state-pool-size" => {
+                if i + 1 >= args.len() {
+                    return Err("--state-pool-size option requires a value".to_string());
+                }
+
+                let size_str = &args[i + 1];
+                match size_str.parse::<usize>() {
+                    Ok(size) => {
+                        config.state_pool_size = Some(size);
+                        i += 2;
+                    }
+                    Err(_) => {
+                        return Err(format!("Invalid state pool size: {}", size_str));
+                    }
+                }
+            }
+
+            // Error routing option
+            "--errors-to" => {
+                if i + 1 >= args.len() {
+                    return Err("--errors-to option requires a value".to_string());
+                }
+
+                config.errors_to = args[i + 1].parse()?;
+                i += 2;
+            }
+
+            // Log format option
+            "--log-format" => {
+                if i + 1 >= args.len() {
+                    return Err("--log-format option requires a value".to_string());
+                }
+
+                config.log_format = args[i + 1].parse()?;
+                i += 2;
+            }
+
+            // Event routing option
+            "--events-to" => {
+                if i + 1 >= args.len() {
+                    return Err("--events-to option requires a value".to_string());
+                }
+
+                config.events_to = args[i + 1].parse()?;
+                i += 2;
+            }
+
+            // Request input option
+            "--input-fifo" => {
+                if i + 1 >= args.len() {
+                    return Err("--input-fifo option requires a value".to_string());
+                }
+
+                config.input_fifo = Some(args[i + 1].clone());
+                i += 2;
+            }
+
+            "--socket" => {
+                if !cfg!(all(unix, feature = "socket")) {
+                    return Err(
+                        "--socket requires the server to be built for Unix with the socket feature"
+                            .to_string(),
+                    );
+                }
+                if i + 1 >= args.len() {
+                    return Err("--socket option requires a value".to_string());
+                }
+
+                config.socket_path = Some(args[i + 1].clone());
+                i += 2;
+            }
+
+            "--listen" => {
+                if !cfg!(all(unix, feature = "socket")) {
+                    return Err(
+                        "--listen requires the server to be built for Unix with the socket feature"
+                            .to_string(),
+                    );
+                }
+                if i + 1 >= args.len() {
+                    return Err("--listen option requires a value".to_string());
+                }
+
+                config.listen_addr = Some(args[i + 1].clone());
+                i += 2;
+            }
+
+            "--max-connections" => {
+                if i + 1 >= args.len() {
+                    return Err("--max-connections option requires a value".to_string());
+                }
+
+                let count_str = &args[i + 1];
+                match count_str.parse::<usize>() {
+                    Ok(count) if count > 0 => {
+                        config.max_connections = Some(count);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid connection limit: {}", count_str));
+                    }
+                }
+            }
+
+            "--allow-remote-load-model" => {
+                config.allow_remote_load_model = true;
+                i += 1;
+            }
+
+            "--keep-recent" => {
+                if i + 1 >= args.len() {
+                    return Err("--keep-recent option requires a value".to_string());
+                }
+
+                let count_str = &args[i + 1];
+                match count_str.parse::<usize>() {
+                    Ok(count) if count > 0 => {
+                        config.keep_recent = Some(count);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid recent result count: {}", count_str));
+                    }
+                }
+            }
+
+            // Per-language overrides file option
+            "--language-overrides" => {
+                if i + 1 >= args.len() {
+                    return Err("--language-overrides option requires a value".to_string());
+                }
+
+                config.language_overrides_path = Some(args[i + 1].clone());
+                i += 2;
+            }
+
+            // Pre-emphasis filter option
+            "--pre-emphasis" => {
+                if i + 1 >= args.len() {
+                    return Err("--pre-emphasis option requires a value".to_string());
+                }
+
+                let coef_str = &args[i + 1];
+                match coef_str.parse::<f32>() {
+                    Ok(coef) => {
+                        if !(0.0..1.0).contains(&coef) {
+                            return Err("Pre-emphasis coefficient must be in the range [0.0, 1.0)"
+                                .to_string());
+                        }
+                        config.pre_emphasis = Some(coef);
+                        i += 2;
+                    }
+                    Err(_) => {
+                        return Err(format!("Invalid pre-emphasis coefficient: {}", coef_str));
+                    }
+                }
+            }
+
+            // Low-confidence threshold option
+            "--min-avg-logprob" => {
+                if i + 1 >= args.len() {
+                    return Err("--min-avg-logprob option requires a value".to_string());
+                }
+
+                let logprob_str = &args[i + 1];
+                match logprob_str.parse::<f32>() {
+                    Ok(logprob) if logprob.is_finite() && logprob <= 0.0 => {
+                        config.min_avg_logprob = Some(logprob);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid average log probability: {}", logprob_str));
+                    }
+                }
+            }
+
+            // Preprocessing order option
+            "--preprocess-order" => {
+                if i + 1 >= args.len() {
+                    return Err("--preprocess-order option requires a value".to_string());
+                }
+
+                let order = args[i + 1]
+                    .split(',')
+                    .map(|step| step.trim().parse())
+                    .collect::<Result<Vec<PreprocessStep>, String>>()?;
+                let complete = order.len() == PreprocessStep::DEFAULT_ORDER.len()
+                    && PreprocessStep::DEFAULT_ORDER
+                        .iter()
+                        .all(|step| order.contains(step));
+                if !complete {
+                    let ids: Vec<&str> = PreprocessStep::DEFAULT_ORDER
+                        .iter()
+                        .map(|step| step.as_str())
+                        .collect();
+                    return Err(format!(
+                        "--preprocess-order must list each step exactly once ({})",
+                        ids.join(", ")
+                    ));
+                }
+                config.preprocess_order = Some(order);
+                i += 2;
+            }
+
+            // Windowed conversion flag
+            "--low-memory" => {
+                config.low_memory = true;
+                i += 1;
+            }
+
+            // Silent audio short-circuit flag
+            "--skip-silent" => {
+                config.skip_silent = true;
+                i += 1;
+            }
+
+            // Declared duration validation options
+            "--duration-tolerance-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--duration-tolerance-ms option requires a value".to_string());
+                }
+
+                let tolerance_str = &args[i + 1];
+                match tolerance_str.parse::<u64>() {
+                    Ok(tolerance) => {
+                        config.duration_tolerance_ms = Some(tolerance);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid duration tolerance: {}", tolerance_str));
+                    }
+                }
+            }
+
+            "--strict-duration" => {
+                config.strict_duration = true;
+                i += 1;
+            }
+
+            // Silence trimming flag
+            "--trim-silence" => {
+                config.trim_silence = true;
+                i += 1;
+            }
+
+            // Automatic gain control options
+            "--agc" => {
+                config.agc = true;
+                i += 1;
+            }
+
+            "--agc-target-rms" => {
+                if i + 1 >= args.len() {
+                    return Err("--agc-target-rms option requires a value".to_string());
+                }
+
+                let level_str = &args[i + 1];
+                match level_str.parse::<f32>() {
+                    Ok(level) if level > 0.0 && level <= 1.0 => {
+                        config.agc_target_rms = Some(level);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid AGC target RMS: {}", level_str));
+                    }
+                }
+            }
+
+            "--agc-max-gain" => {
+                if i + 1 >= args.len() {
+                    return Err("--agc-max-gain option requires a value".to_string());
+                }
+
+                let gain_str = &args[i + 1];
+                match gain_str.parse::<f32>() {
+                    Ok(gain) if gain >= 1.0 && gain.is_finite() => {
+                        config.agc_max_gain = Some(gain);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid AGC max gain: {}", gain_str));
+                    }
+                }
+            }
+
+            // Chunk length option
+            "--chunk-secs" => {
+                if i + 1 >= args.len() {
+                    return Err("--chunk-secs option requires a value".to_string());
+                }
+
+                let secs_str = &args[i + 1];
+                match secs_str.parse::<u64>() {
+                    Ok(secs) if secs > 0 => {
+                        config.chunk_secs = Some(secs);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid chunk length: {}", secs_str));
+                    }
+                }
+            }
+
+            // Chunk overlap option
+            "--chunk-overlap-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--chunk-overlap-ms option requires a value".to_string());
+                }
+
+                let overlap_str = &args[i + 1];
+                match overlap_str.parse::<u64>() {
+                    Ok(overlap) => {
+                        config.chunk_overlap_ms = Some(overlap);
+                        i += 2;
+                    }
+                    Err(_) => {
+                        return Err(format!("Invalid chunk overlap: {}", overlap_str));
+                    }
+                }
+            }
+
+            // Heartbeat interval option
+            "--heartbeat-secs" => {
+                if i + 1 >= args.len() {
+                    return Err("--heartbeat-secs option requires a value".to_string());
+                }
+
+                let secs_str = &args[i + 1];
+                match secs_str.parse::<u64>() {
+                    Ok(secs) if secs > 0 => {
+                        config.heartbeat_secs = Some(secs);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid heartbeat interval: {}", secs_str));
+                    }
+                }
+            }
+
+            // Sequenced chunk reordering option
+            "--reorder-timeout-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--reorder-timeout-ms option requires a value".to_string());
+                }
+
+                let timeout_str = &args[i + 1];
+                match timeout_str.parse::<u64>() {
+                    Ok(timeout) if timeout > 0 => {
+                        config.reorder_timeout_ms = Some(timeout);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid reorder timeout: {}", timeout_str));
+                    }
+                }
+            }
+
+            // Transcription time histogram option
+            "--latency-buckets" => {
+                if i + 1 >= args.len() {
+                    return Err("--latency-buckets option requires a value".to_string());
+                }
+
+                let buckets = args[i + 1]
+                    .split(',')
+                    .map(|bound| match bound.trim().parse::<u64>() {
+                        Ok(bound) if bound > 0 => Ok(bound),
+                        _ => Err(format!("Invalid latency bucket: {}", bound.trim())),
+                    })
+                    .collect::<Result<Vec<u64>, String>>()?;
+                config.latency_buckets_ms = Some(buckets);
+                i += 2;
+            }
+
+            // Cold-start exclusion option
+            "--exclude-cold-start" => {
+                if i + 1 >= args.len() {
+                    return Err("--exclude-cold-start option requires a value".to_string());
+                }
+
+                let count_str = &args[i + 1];
+                match count_str.parse::<u64>() {
+                    Ok(count) if count > 0 => {
+                        config.exclude_cold_start = Some(count);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid cold-start request count: {}", count_str));
+                    }
+                }
+            }
+
+            // Keepalive interval option
+            "--keepalive-secs" => {
+                if i + 1 >= args.len() {
+                    return Err("--keepalive-secs option requires a value".to_string());
+                }
+
+                let secs_str = &args[i + 1];
+                match secs_str.parse::<u64>() {
+                    Ok(secs) if secs > 0 => {
+                        config.keepalive_secs = Some(secs);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid keepalive interval: {}", secs_str));
+                    }
+                }
+            }
+
+            // Buffer flush threshold option
+            "--max-buffer-ms" => {
+                if i + 1 >= args.len() {
+                    return Err("--max-buffer-ms option requires a value".to_string());
+                }
+
+                let ms_str = &args[i + 1];
+                match ms_str.parse::<u64>() {
+                    Ok(ms) if ms > 0 => {
+                        config.max_buffer_ms = Some(ms);
+                        i += 2;
+                    }
+                    _ => {
+                        return Err(format!("Invalid maximum buffer length: {}", ms_str));
+                    }
+                }
+            }
+
+            // Unknown argument
+            _ => {
+                return Err(format!("Unknown argument: {}", arg));
+            }
+        }
+    }
+
+    // Validate that we have a model path
+    if config.model_path.is_empty() {
+        return Err("Model path is required".to_string());
+    }
+
+    // Validate chunk overlap against the chunk length
+    if let Some(overlap_ms) = config.chunk_overlap_ms {
+        match config.chunk_secs {
+            None => return Err("--chunk-overlap-ms requires --chunk-secs".to_string()),
+            Some(secs) if overlap_ms >= secs * 1000 => {
+                return Err("Chunk overlap must be shorter than the chunk length".to_string());
+            }
+            Some(_) => {}
+        }
+    }
+
+    if config.low_memory && config.chunk_secs.is_none() {
+        return Err("--low-memory requires --chunk-secs".to_string());
+    }
+
+    if config.speaker_gap_ms.is_some() && !config.speaker_hints {
+        return Err("--speaker-gap-ms requires --speaker-hints".to_string());
+    }
+
+    if config.phrase_gap_ms.is_some() && !config.phrase_segments {
+        return Err("--phrase-gap-ms requires --phrase-segments".to_string());
+    }
+
+    if (config.agc_target_rms.is_some() || config.agc_max_gain.is_some()) && !config.agc {
+        return Err("--agc-target-rms and --agc-max-gain require --agc".to_string());
+    }
+
+    if config.merge_short_segments && config.min_segment_ms.is_none() {
+        return Err("--merge-short-segments requires --min-segment-ms".to_string());
+    }
+
+    if config.allow_remote_load_model
+        && config.socket_path.is_none()
+        && config.listen_addr.is_none()
+    {
+        return Err("--allow-remote-load-model requires --socket or --listen".to_string());
+    }
+
+    check_flag_conflicts(&config)?;
+
+    Ok(config)
+}
+
+/// Reject combinations of flags that contradict each other
+///
+/// # Arguments
+/// * `config` - Parsed configuration
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if consistent, otherwise an error naming both flags
+fn check_flag_conflicts(config: &Config) -> Result<(), String> {
+    let conflicts = [
+        // whisper.cpp silently disables DTW when flash attention is on
+        (
+            "--dtw",
+            config.dtw_preset.is_some(),
+            "--flash-attn",
+            config.flash_attn,
+        ),
+        // whisper's segment callback fires inconsistently with DTW enabled
+        (
+            "--dtw",
+            config.dtw_preset.is_some(),
+            "--stream-segments",
+            config.stream_segments,
+        ),
+        // Requests come from exactly one input
+        (
+            "--input-fifo",
+            config.input_fifo.is_some(),
+            "--socket",
+            config.socket_path.is_some(),
+        ),
+        (
+            "--input-fifo",
+            config.input_fifo.is_some(),
+            "--listen",
+            config.listen_addr.is_some(),
+        ),
+        (
+            "--socket",
+            config.socket_path.is_some(),
+            "--listen",
+            config.listen_addr.is_some(),
+        ),
+        // Trimming needs the whole clip, which low-memory mode never converts
+        (
+            "--low-memory",
+            config.low_memory,
+            "--trim-silence",
+            config.trim_silence,
+        ),
+        // The gain is measured over the whole clip too
+        ("--low-memory", config.low_memory, "--agc", config.agc),
+        // Flash attention only applies to GPU inference
+        (
+            "--cpu-only",
+            config.cpu_only,
+            "--flash-attn",
+            config.flash_attn,
+        ),
+        // Field selection only applies to JSON output
+        (
+            "--output-format text",
+            config.output_format == OutputFormat::Text,
+            "--output-fields",
+            config.output_fields.is_some(),
+        ),
+        (
+            "--output-format openai",
+            config.output_format == OutputFormat::OpenAi,
+            "--output-fields",
+            config.output_fields.is_some(),
+        ),
+        (
+            "--output-format whisper-cpp",
+            config.output_format == OutputFormat::WhisperCpp,
+            "--output-fields",
+            config.output_fields.is_some(),
+        ),
+        // Timings differ between runs, so output could not be byte-identical
+        (
+            "--deterministic",
+            config.deterministic,
+            "--include-metrics",
+            config.include_metrics,
+        ),
+    ];
+
+    for (first, first_set, second, second_set) in conflicts {
+        if first_set && second_set {
+            return Err(format!("{} cannot be combined with {}", first, second));
+        }
+    }
+    Ok(())
+}
+
+/// Nice value to apply at startup, if any
+///
+/// An explicit `--nice` takes precedence over the `--thread-priority` preset.
+pub fn effective_nice_value(config: &Config) -> Option<i32> {
+    config
+        .nice
+        .or_else(|| config.thread_priority.map(ThreadPriority::nice_value))
+}
+
+/// Apply the configured scheduling priority with `setpriority(PRIO_PROCESS, 0, nice)`
+///
+/// On Linux the nice value belongs to a thread: it is set for the calling
+/// thread and inherited by threads that thread spawns afterwards, such as the
+/// transcription worker and whisper.cpp's compute threads, but not by threads
+/// that are already running, such as the async runtime's. It must therefore be
+/// called from the main thread before the worker is started. Other Unix
+/// systems apply it to the whole process. Pinning threads to CPUs is out of
+/// scope.
+///
+/// # Arguments
+/// * `config` - Server configuration
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if applied (or nothing to apply), error message if failed
+#[cfg(unix)]
+pub fn apply_process_priority(config: &Config) -> Result<(), String> {
+    use std::os::raw::{c_int, c_uint};
+
+    // Provided by the C library std already links against
+    unsafe extern "C" {
+        fn setpriority(which: c_int, who: c_uint, prio: c_int) -> c_int;
+    }
+    const PRIO_PROCESS: c_int = 0;
+
+    let Some(nice) = effective_nice_value(config) else {
+        return Ok(());
+    };
+
+    info!("Setting nice value to {}", nice);
+    // SAFETY: setpriority only reads its integer arguments
+    if unsafe { setpriority(PRIO_PROCESS, 0, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "setpriority failed: {}",
+            std::io::Error::last_os_error()
+        ))
+    }
+}
+
+/// Apply the configured scheduling priority to the current process
+///
+/// Not supported on this platform; the setting is ignored with a warning.
+#[cfg(not(unix))]
+pub fn apply_process_priority(config: &Config) -> Result<(), String> {
+    if effective_nice_value(config).is_some() {
+        log::warn!("Process priority configuration is not supported on this platform, ignoring");
+    }
+    Ok(())
+}
+
+/// Validate that the model path exists and has the correct extension
+///
+/// # Arguments
+/// * `model_path` - Path to the model file
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if valid, error message if invalid
+pub fn validate_model_path(model_path: &str) -> Result<(), String> {
+    let path = Path::new(model_path);
+
+    // Check if file exists
+    if !path.exists() {
+        return Err(format!("Model file does not exist: {}", model_path));
+    }
+
+    // Check if it's a file (not a directory)
+    if !path.is_file() {
+        return Err(format!("Model path is not a file: {}", model_path));
+    }
+
+    // Check file extension
+    if let Some(extension) = path.extension() {
+        if extension != "bin" {
+            return Err(format!(
+                "Model file must have .bin extension, got: {:?}",
+                extension
+            ));
+        }
+    } else {
+        return Err(format!("Model file has no extension: {}", model_path));
+    }
+
+    // Get file size
+    if let Ok(metadata) = path.metadata() {
+        if metadata.len() == 0 {
+            return Err(format!("Model file is empty: {}", model_path));
+        }
+    } else {
+        return Err(format!("Cannot read model file metadata: {}", model_path));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mock the path existence check for tests
+    fn mock_parse_arguments<I, S>(args: I) -> Result<Config, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        parse_argument_values(args)
+    }
+
+    #[test]
+    fn test_parse_arguments_minimal() {
+        let args = vec!["program_name".to_string(), "/path/to/model.bin".to_string()];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.model_path, "/path/to/model.bin");
+        assert_eq!(config.threads, None);
+        assert_eq!(config.cpu_only, false);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_threads() {
+        let args = vec![
+            "program_name".to_string(),
+            "/path/to/model.bin".to_string(),
+            "--threads".to_string(),
+            "4".to_string(),
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.model_path, "/path/to/model.bin");
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(config.cpu_only, false);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_cpu_only() {
+        let args = vec![
+            "program_name".to_string(),
+            "/path/to/model.bin".to_string(),
+            "--cpu-only".to_string(),
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.model_path, "/path/to/model.bin");
+        assert_eq!(config.threads, None);
+        assert_eq!(config.cpu_only, true);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_both_options() {
+        let args = vec![
+            "program_name".to_string(),
+            "/path/to/model.bin".to_string(),
+            "--threads".to_string(),
+            "8".to_string(),
+            "--cpu-only".to_string(),
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.model_path, "/path/to/model.bin");
+        assert_eq!(config.threads, Some(8));
+        assert_eq!(config.cpu_only, true);
+    }
+
+    #[test]
+    fn test_parse_arguments_no_model_path() {
+        let args = vec!["program_name".to_string()];
+
+        let result = mock_parse_arguments(args);
+        assert!(result.is_err());
+    }
+
+    #[test]
     fn test_parse_arguments_invalid_threads() {
         let args = vec![
-            "program_name".to_string(),
-            "/path/to/model.bin".to_string(),
-            "--threads".to_string(),
-            "invalid".to_string(),
+            "program_name".to_string(),
+            "/path/to/model.bin".to_string(),
+            "--threads".to_string(),
+            "invalid".to_string(),
+        ];
+
+        let result = mock_parse_arguments(args);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_zero_threads() {
+        let args = vec![
+            "program_name".to_string(),
+            "/path/to/model.bin".to_string(),
+            "--threads".to_string(),
+            "0".to_string(),
+        ];
+
+        let result = mock_parse_arguments(args);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_unknown_argument() {
+        let args = vec![
+            "program_name".to_string(),
+            "/path/to/model.bin".to_string(),
+            "--unknown".to_string(),
+        ];
+
+        let result = mock_parse_arguments(args);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_nice() {
+        let args = vec!["program_name", "/path/to/model.bin", "--nice", "5"];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.nice, Some(5));
+        assert_eq!(effective_nice_value(&config), Some(5));
+    }
+
+    #[test]
+    fn test_parse_arguments_nice_out_of_range() {
+        let args = vec!["program_name", "/path/to/model.bin", "--nice", "20"];
+        assert!(mock_parse_arguments(args).is_err());
+
+        let args = vec!["program_name", "/path/to/model.bin", "--nice", "-21"];
+        assert!(mock_parse_arguments(args).is_err());
+
+        let args = vec!["program_name", "/path/to/model.bin", "--nice"];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_thread_priority() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--thread-priority",
+            "low",
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.thread_priority, Some(ThreadPriority::Low));
+        assert_eq!(effective_nice_value(&config), Some(10));
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--thread-priority",
+            "realtime",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_nice_value_is_inherited_by_threads_spawned_afterwards() {
+        /// Nice value of the calling thread, from /proc
+        fn current_nice() -> i32 {
+            let stat = std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+            // Fields after the command name, starting with the state (field 3)
+            let fields: Vec<&str> = stat.rsplit_once(") ").unwrap().1.split(' ').collect();
+            fields[16].parse().unwrap()
+        }
+
+        // Run on a thread of its own, since the value is per thread
+        std::thread::spawn(|| {
+            let config = Config {
+                nice: Some(19),
+                ..Default::default()
+            };
+            apply_process_priority(&config).unwrap();
+            assert_eq!(current_nice(), 19);
+            let inherited = std::thread::spawn(current_nice).join().unwrap();
+            assert_eq!(inherited, 19);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_explicit_nice_overrides_thread_priority() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--thread-priority",
+            "high",
+            "--nice",
+            "3",
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(effective_nice_value(&config), Some(3));
+    }
+
+    #[test]
+    fn test_parse_arguments_with_pre_emphasis() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--pre-emphasis",
+            "0.97",
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.pre_emphasis, Some(0.97));
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid_pre_emphasis() {
+        for value in ["1.0", "-0.1", "abc"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--pre-emphasis",
+                value,
+            ];
+            assert!(mock_parse_arguments(args).is_err(), "accepted {}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_trim_silence() {
+        let args = vec!["program_name", "/path/to/model.bin", "--trim-silence"];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert!(config.trim_silence);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_chunking() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--chunk-secs",
+            "30",
+            "--chunk-overlap-ms",
+            "1000",
+        ];
+
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.chunk_secs, Some(30));
+        assert_eq!(config.chunk_overlap_ms, Some(1000));
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid_chunk_overlap() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--chunk-overlap-ms",
+            "500",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--chunk-secs",
+            "1",
+            "--chunk-overlap-ms",
+            "1000",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_heartbeat() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--heartbeat-secs",
+            "15",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.heartbeat_secs, Some(15));
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--heartbeat-secs",
+            "0",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_output_format() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.output_format, OutputFormat::Json);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--output-format",
+            "text",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.output_format, OutputFormat::Text);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--output-format",
+            "xml",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_output_format_ids_round_trip() {
+        for format in OutputFormat::ALL {
+            assert_eq!(format.as_str().parse::<OutputFormat>(), Ok(*format));
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_language_overrides() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--language-overrides",
+            "/etc/whisper/languages.json",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(
+            config.language_overrides_path.as_deref(),
+            Some("/etc/whisper/languages.json")
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_with_errors_to() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().errors_to,
+            ErrorStream::Stdout
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--errors-to", "both"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().errors_to,
+            ErrorStream::Both
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--errors-to", "file"];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_output_fields() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--output-fields",
+            "text, language,segments",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(
+            config.output_fields,
+            Some(vec![
+                "text".to_string(),
+                "language".to_string(),
+                "segments".to_string()
+            ])
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--output-fields", ","];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_timestamp_decimals() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--timestamp-decimals",
+            "2",
         ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.timestamp_decimals, Some(2));
 
-        let result = mock_parse_arguments(args);
-        assert!(result.is_err());
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--timestamp-decimals",
+            "9",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
     }
 
     #[test]
-    fn test_parse_arguments_zero_threads() {
+    fn test_parse_arguments_with_state_pool_size() {
         let args = vec![
-            "program_name".to_string(),
-            "/path/to/model.bin".to_string(),
-            "--threads".to_string(),
-            "0".to_string(),
+            "program_name",
+            "/path/to/model.bin",
+            "--state-pool-size",
+            "0",
         ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.state_pool_size, Some(0));
 
-        let result = mock_parse_arguments(args);
-        assert!(result.is_err());
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--state-pool-size",
+            "-1",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
     }
 
     #[test]
-    fn test_parse_arguments_unknown_argument() {
+    fn test_parse_arguments_with_flash_attn() {
+        let args = vec!["program_name", "/path/to/model.bin", "--flash-attn"];
+        let config = mock_parse_arguments(args).unwrap();
+        assert!(config.flash_attn);
+        assert!(!config.cpu_only);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_dtw() {
+        let args = vec!["program_name", "/path/to/model.bin", "--dtw", "base.en"];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.dtw_preset, Some("base.en".to_string()));
+
+        let args = vec!["program_name", "/path/to/model.bin", "--dtw", "huge"];
+        assert!(mock_parse_arguments(args).is_err());
+
         let args = vec![
-            "program_name".to_string(),
-            "/path/to/model.bin".to_string(),
-            "--unknown".to_string(),
+            "program_name",
+            "/path/to/model.bin",
+            "--dtw",
+            "base",
+            "--flash-attn",
         ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
 
-        let result = mock_parse_arguments(args);
-        assert!(result.is_err());
+    #[test]
+    fn test_parse_arguments_with_max_buffer_ms() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--max-buffer-ms",
+            "30000",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.max_buffer_ms, Some(30000));
+
+        let args = vec!["program_name", "/path/to/model.bin", "--max-buffer-ms", "0"];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_translation() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert!(!mock_parse_arguments(args).unwrap().with_translation);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--with-translation"];
+        assert!(mock_parse_arguments(args).unwrap().with_translation);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_min_segment_ms() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--min-segment-ms",
+            "100",
+            "--merge-short-segments",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.min_segment_ms, Some(100));
+        assert!(config.merge_short_segments);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--merge-short-segments",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--min-segment-ms",
+            "0",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_deterministic() {
+        let args = vec!["program_name", "/path/to/model.bin", "--deterministic"];
+        assert!(mock_parse_arguments(args).unwrap().deterministic);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_speaker_hints() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--speaker-hints",
+            "--speaker-gap-ms",
+            "2000",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert!(config.speaker_hints);
+        assert_eq!(config.speaker_gap_ms, Some(2000));
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--speaker-gap-ms",
+            "2000",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_keepalive_secs() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert_eq!(mock_parse_arguments(args).unwrap().keepalive_secs, None);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--keepalive-secs",
+            "300",
+        ];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().keepalive_secs,
+            Some(300)
+        );
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--keepalive-secs",
+            "0",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_include_metrics() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert!(!mock_parse_arguments(args).unwrap().include_metrics);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--include-metrics"];
+        assert!(mock_parse_arguments(args).unwrap().include_metrics);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_greedy_fallback() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert!(!mock_parse_arguments(args).unwrap().greedy_fallback);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--greedy-fallback"];
+        assert!(mock_parse_arguments(args).unwrap().greedy_fallback);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_min_avg_logprob() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--min-avg-logprob",
+            "-1.0",
+        ];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().min_avg_logprob,
+            Some(-1.0)
+        );
+
+        for value in ["0.5", "abc", "-inf"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--min-avg-logprob",
+                value,
+            ];
+            assert!(mock_parse_arguments(args).is_err(), "accepted {}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_rate_limit() {
+        let args = vec!["program_name", "/path/to/model.bin", "--max-rps", "2.5"];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.max_rps, Some(2.5));
+        assert_eq!(config.rate_limit_policy, RateLimitPolicy::Block);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--max-rps",
+            "10",
+            "--rate-limit-policy",
+            "reject",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.rate_limit_policy, RateLimitPolicy::Reject);
+
+        for (flag, value) in [
+            ("--max-rps", "0"),
+            ("--max-rps", "fast"),
+            ("--rate-limit-policy", "drop"),
+        ] {
+            let args = vec!["program_name", "/path/to/model.bin", flag, value];
+            assert!(mock_parse_arguments(args).is_err(), "accepted {}", value);
+        }
+    }
+
+    #[test]
+    fn test_conflicting_flags_are_rejected() {
+        let conflicts = [
+            (
+                vec!["--cpu-only", "--flash-attn"],
+                "--cpu-only",
+                "--flash-attn",
+            ),
+            (
+                vec!["--output-format", "text", "--output-fields", "text"],
+                "--output-format text",
+                "--output-fields",
+            ),
+            (
+                vec!["--output-format", "openai", "--output-fields", "text"],
+                "--output-format openai",
+                "--output-fields",
+            ),
+            (
+                vec!["--deterministic", "--include-metrics"],
+                "--deterministic",
+                "--include-metrics",
+            ),
+        ];
+
+        for (flags, first, second) in conflicts {
+            let mut args = vec!["program_name", "/path/to/model.bin"];
+            args.extend(flags);
+            let error = mock_parse_arguments(args).unwrap_err();
+            assert!(error.contains(first), "{}", error);
+            assert!(error.contains(second), "{}", error);
+        }
+
+        // Each flag on its own is accepted
+        for flag in ["--cpu-only", "--flash-attn", "--deterministic"] {
+            let args = vec!["program_name", "/path/to/model.bin", flag];
+            assert!(mock_parse_arguments(args).is_ok(), "rejected {}", flag);
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_reorder_timeout() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--reorder-timeout-ms",
+            "500",
+        ];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().reorder_timeout_ms,
+            Some(500)
+        );
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--reorder-timeout-ms",
+            "0",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_phrase_segments() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--phrase-segments",
+            "--phrase-gap-ms",
+            "300",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert!(config.phrase_segments);
+        assert_eq!(config.phrase_gap_ms, Some(300));
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--phrase-gap-ms",
+            "300",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_exclude_cold_start() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--exclude-cold-start",
+            "2",
+        ];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().exclude_cold_start,
+            Some(2)
+        );
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--exclude-cold-start",
+            "0",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_latency_buckets() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--latency-buckets",
+            "100, 500,2000",
+        ];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().latency_buckets_ms,
+            Some(vec![100, 500, 2000])
+        );
+
+        for value in ["", "100,,500", "fast", "0"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--latency-buckets",
+                value,
+            ];
+            assert!(mock_parse_arguments(args).is_err(), "accepted {:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_debug_decoding() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert!(!mock_parse_arguments(args).unwrap().debug_decoding);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--debug-decoding"];
+        assert!(mock_parse_arguments(args).unwrap().debug_decoding);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_preprocess_order() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--preprocess-order",
+            "pre-emphasis,trim",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(
+            config.preprocess_order,
+            Some(vec![
+                PreprocessStep::PreEmphasis,
+                PreprocessStep::TrimSilence
+            ])
+        );
+
+        for order in ["trim", "trim,trim", "trim,normalize"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--preprocess-order",
+                order,
+            ];
+            assert!(mock_parse_arguments(args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_stream_segments() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert!(!mock_parse_arguments(args).unwrap().stream_segments);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--stream-segments"];
+        assert!(mock_parse_arguments(args).unwrap().stream_segments);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--stream-segments",
+            "--dtw",
+            "base",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_low_memory() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--chunk-secs",
+            "30",
+            "--low-memory",
+        ];
+        assert!(mock_parse_arguments(args).unwrap().low_memory);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--low-memory"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap_err(),
+            "--low-memory requires --chunk-secs"
+        );
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--chunk-secs",
+            "30",
+            "--low-memory",
+            "--trim-silence",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_max_segments() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--max-segments",
+            "500",
+        ];
+        assert_eq!(mock_parse_arguments(args).unwrap().max_segments, Some(500));
+
+        for value in ["0", "-1", "many"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--max-segments",
+                value,
+            ];
+            assert!(mock_parse_arguments(args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_skip_silent() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert!(!mock_parse_arguments(args).unwrap().skip_silent);
+
+        let args = vec!["program_name", "/path/to/model.bin", "--skip-silent"];
+        assert!(mock_parse_arguments(args).unwrap().skip_silent);
+    }
+
+    #[test]
+    fn test_parse_arguments_with_duration_validation() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.duration_tolerance_ms, None);
+        assert!(!config.strict_duration);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--duration-tolerance-ms",
+            "250",
+            "--strict-duration",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.duration_tolerance_ms, Some(250));
+        assert!(config.strict_duration);
+
+        for value in ["-1", "soon"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--duration-tolerance-ms",
+                value,
+            ];
+            assert!(mock_parse_arguments(args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_events_to() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().events_to,
+            EventStream::Stdout
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--events-to", "fd3"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().events_to,
+            EventStream::Fd3
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--events-to", "both"];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_input_fifo() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert_eq!(mock_parse_arguments(args).unwrap().input_fifo, None);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--input-fifo",
+            "/tmp/whisper.fifo",
+        ];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().input_fifo.as_deref(),
+            Some("/tmp/whisper.fifo")
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--input-fifo"];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_socket() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--socket",
+            "/tmp/whisper.sock",
+        ];
+        let parsed = mock_parse_arguments(args);
+        if !cfg!(all(unix, feature = "socket")) {
+            assert!(parsed.unwrap_err().contains("socket feature"));
+            return;
+        }
+        assert_eq!(
+            parsed.unwrap().socket_path.as_deref(),
+            Some("/tmp/whisper.sock")
+        );
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--socket",
+            "/tmp/whisper.sock",
+            "--input-fifo",
+            "/tmp/whisper.fifo",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_listen() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--listen",
+            "127.0.0.1:7700",
+            "--max-connections",
+            "2",
+        ];
+        let parsed = mock_parse_arguments(args);
+        if !cfg!(all(unix, feature = "socket")) {
+            assert!(parsed.unwrap_err().contains("socket feature"));
+            return;
+        }
+        let config = parsed.unwrap();
+        assert_eq!(config.listen_addr.as_deref(), Some("127.0.0.1:7700"));
+        assert_eq!(config.max_connections, Some(2));
+
+        for value in ["0", "-1", "many"] {
+            let args = vec![
+                "program_name",
+                "/path/to/model.bin",
+                "--max-connections",
+                value,
+            ];
+            assert!(mock_parse_arguments(args).is_err());
+        }
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--listen",
+            "127.0.0.1:7700",
+            "--socket",
+            "/tmp/whisper.sock",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--listen",
+            "127.0.0.1:7700",
+            "--allow-remote-load-model",
+        ];
+        assert!(mock_parse_arguments(args).unwrap().allow_remote_load_model);
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--allow-remote-load-model",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_log_format() {
+        let args = vec!["program_name", "/path/to/model.bin"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().log_format,
+            LogFormat::Text
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--log-format", "json"];
+        assert_eq!(
+            mock_parse_arguments(args).unwrap().log_format,
+            LogFormat::Json
+        );
+
+        let args = vec!["program_name", "/path/to/model.bin", "--log-format", "yaml"];
+        assert!(mock_parse_arguments(args).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_keep_recent() {
+        let args = vec!["program_name", "/path/to/model.bin", "--keep-recent", "5"];
+        let config = mock_parse_arguments(args).unwrap();
+        assert_eq!(config.keep_recent, Some(5));
+
+        let config = mock_parse_arguments(vec!["program_name", "/path/to/model.bin"]).unwrap();
+        assert_eq!(config.keep_recent, None);
+
+        for value in ["0", "-1", "many"] {
+            let args = vec!["program_name", "/path/to/model.bin", "--keep-recent", value];
+            assert!(mock_parse_arguments(args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments_with_agc() {
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--agc",
+            "--agc-target-rms",
+            "0.2",
+            "--agc-max-gain",
+            "4",
+        ];
+        let config = mock_parse_arguments(args).unwrap();
+        assert!(config.agc);
+        assert_eq!(config.agc_target_rms, Some(0.2));
+        assert_eq!(config.agc_max_gain, Some(4.0));
+
+        for (flag, value) in [
+            ("--agc-target-rms", "0"),
+            ("--agc-target-rms", "1.5"),
+            ("--agc-max-gain", "0.5"),
+            ("--agc-max-gain", "inf"),
+        ] {
+            let args = vec!["program_name", "/path/to/model.bin", "--agc", flag, value];
+            assert!(mock_parse_arguments(args).is_err());
+        }
+
+        let args = vec!["program_name", "/path/to/model.bin", "--agc-max-gain", "4"];
+        assert!(mock_parse_arguments(args).is_err());
+
+        let args = vec![
+            "program_name",
+            "/path/to/model.bin",
+            "--chunk-secs",
+            "30",
+            "--low-memory",
+            "--agc",
+        ];
+        assert!(mock_parse_arguments(args).is_err());
     }
 }
@@ -0,0 +1,379 @@
+//! GBNF grammar parsing for constrained decoding
+//!
+//! Converts a GBNF grammar (the format used by whisper.cpp and llama.cpp) into
+//! the rule elements expected by `FullParams::set_grammar`. Supported syntax:
+//! rule definitions (`name ::= ...`), alternation (`|`), string literals,
+//! character classes (`[a-z]`, `[^0-9]`), rule references, grouping with
+//! parentheses, the `*`, `+` and `?` repetition operators, and `#` comments.
+
+use std::collections::HashMap;
+use whisper_rs::{WhisperGrammarElement, WhisperGrammarElementType};
+
+/// Name of the rule decoding starts from
+pub const ROOT_RULE: &str = "root";
+
+/// A grammar parsed into whisper rule elements
+#[derive(Debug, Clone)]
+pub struct ParsedGrammar {
+    /// Rule elements indexed by rule id, each terminated by an `End` element
+    rules: Vec<Vec<WhisperGrammarElement>>,
+    /// Rule ids keyed by rule name
+    symbol_ids: HashMap<String, u32>,
+}
+
+impl ParsedGrammar {
+    /// Parse a GBNF grammar
+    ///
+    /// # Arguments
+    /// * `source` - Grammar text
+    ///
+    /// # Returns
+    /// * `Result<ParsedGrammar, String>` - Parsed grammar or a description of the syntax error
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+            grammar: ParsedGrammar {
+                rules: Vec::new(),
+                symbol_ids: HashMap::new(),
+            },
+        };
+        parser.parse_grammar()?;
+        let grammar = parser.grammar;
+
+        // Every referenced rule must be defined
+        for (name, &id) in &grammar.symbol_ids {
+            if grammar
+                .rules
+                .get(id as usize)
+                .is_none_or(|rule| rule.is_empty())
+            {
+                return Err(format!("Undefined rule: {}", name));
+            }
+        }
+        if !grammar.symbol_ids.contains_key(ROOT_RULE) {
+            return Err(format!("Grammar must define a '{}' rule", ROOT_RULE));
+        }
+
+        Ok(grammar)
+    }
+
+    /// Index of the rule decoding starts from
+    pub fn start_rule(&self) -> usize {
+        self.symbol_ids[ROOT_RULE] as usize
+    }
+
+    /// Number of rules, including those generated for groups and repetitions
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// All rules flattened in rule id order
+    pub fn elements(&self) -> Vec<WhisperGrammarElement> {
+        self.rules.iter().flatten().copied().collect()
+    }
+}
+
+/// Recursive-descent parser over the grammar source
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    grammar: ParsedGrammar,
+}
+
+/// Shorthand for building a grammar element
+fn element(element_type: WhisperGrammarElementType, value: u32) -> WhisperGrammarElement {
+    WhisperGrammarElement::new(element_type, value)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at offset {}", message, self.pos)
+    }
+
+    /// Skip whitespace and comments, stopping at newlines unless `newline_ok`
+    fn skip_space(&mut self, newline_ok: bool) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c == ' ' || c == '\t' || (newline_ok && (c == '\r' || c == '\n')) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn is_name_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '-' || c == '_'
+    }
+
+    fn parse_name(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(Self::is_name_char) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("Expected rule name"));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn symbol_id(&mut self, name: &str) -> u32 {
+        let next_id = self.grammar.symbol_ids.len() as u32;
+        *self
+            .grammar
+            .symbol_ids
+            .entry(name.to_string())
+            .or_insert(next_id)
+    }
+
+    fn generate_symbol_id(&mut self, base_name: &str) -> u32 {
+        let id = self.grammar.symbol_ids.len() as u32;
+        self.grammar
+            .symbol_ids
+            .insert(format!("{}_{}", base_name, id), id);
+        id
+    }
+
+    fn add_rule(&mut self, id: u32, rule: Vec<WhisperGrammarElement>) {
+        let id = id as usize;
+        if self.grammar.rules.len() <= id {
+            self.grammar.rules.resize(id + 1, Vec::new());
+        }
+        self.grammar.rules[id] = rule;
+    }
+
+    /// Parse a single (possibly escaped) character
+    fn parse_char(&mut self) -> Result<u32, String> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("Unexpected end of input"))?;
+        self.pos += 1;
+        if c != '\\' {
+            return Ok(c as u32);
+        }
+
+        let escaped = self
+            .peek()
+            .ok_or_else(|| self.error("Unexpected end of input"))?;
+        self.pos += 1;
+        match escaped {
+            'n' => Ok('\n' as u32),
+            'r' => Ok('\r' as u32),
+            't' => Ok('\t' as u32),
+            '\\' | '"' | '[' | ']' | '-' => Ok(escaped as u32),
+            'x' => {
+                let digits: String = self.chars.iter().skip(self.pos).take(2).collect();
+                let value = u32::from_str_radix(&digits, 16)
+                    .map_err(|_| self.error("Invalid \\x escape"))?;
+                self.pos += 2;
+                Ok(value)
+            }
+            _ => Err(self.error(&format!("Unknown escape '\\{}'", escaped))),
+        }
+    }
+
+    fn parse_grammar(&mut self) -> Result<(), String> {
+        self.skip_space(true);
+        while self.peek().is_some() {
+            self.parse_rule()?;
+            self.skip_space(true);
+        }
+        Ok(())
+    }
+
+    fn parse_rule(&mut self) -> Result<(), String> {
+        let name = self.parse_name()?;
+        self.skip_space(false);
+        let id = self.symbol_id(&name);
+        if self.chars[self.pos..].starts_with(&[':', ':', '=']) {
+            self.pos += 3;
+        } else {
+            return Err(self.error("Expected ::="));
+        }
+        self.skip_space(true);
+        self.parse_alternates(&name, id, false)?;
+
+        match self.peek() {
+            None | Some('\n') | Some('\r') => Ok(()),
+            Some(c) => Err(self.error(&format!("Unexpected '{}'", c))),
+        }
+    }
+
+    fn parse_alternates(
+        &mut self,
+        rule_name: &str,
+        rule_id: u32,
+        nested: bool,
+    ) -> Result<(), String> {
+        let mut rule = Vec::new();
+        self.parse_sequence(rule_name, &mut rule, nested)?;
+        while self.peek() == Some('|') {
+            rule.push(element(WhisperGrammarElementType::Alternate, 0));
+            self.pos += 1;
+            self.skip_space(true);
+            self.parse_sequence(rule_name, &mut rule, nested)?;
+        }
+        rule.push(element(WhisperGrammarElementType::End, 0));
+        self.add_rule(rule_id, rule);
+        Ok(())
+    }
+
+    fn parse_sequence(
+        &mut self,
+        rule_name: &str,
+        out: &mut Vec<WhisperGrammarElement>,
+        nested: bool,
+    ) -> Result<(), String> {
+        let mut last_sym_start = out.len();
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    last_sym_start = out.len();
+                    while self.peek() != Some('"') {
+                        let value = self.parse_char()?;
+                        out.push(element(WhisperGrammarElementType::Character, value));
+                    }
+                    self.pos += 1;
+                }
+                '[' => {
+                    self.pos += 1;
+                    last_sym_start = out.len();
+                    let mut start_type = WhisperGrammarElementType::Character;
+                    if self.peek() == Some('^') {
+                        self.pos += 1;
+                        start_type = WhisperGrammarElementType::NotCharacter;
+                    }
+                    while self.peek() != Some(']') {
+                        let element_type = if out.len() > last_sym_start {
+                            WhisperGrammarElementType::CharacterAlternate
+                        } else {
+                            start_type
+                        };
+                        let value = self.parse_char()?;
+                        out.push(element(element_type, value));
+                        if self.peek() == Some('-') && self.peek_at(1) != Some(']') {
+                            self.pos += 1;
+                            let upper = self.parse_char()?;
+                            out.push(element(
+                                WhisperGrammarElementType::CharacterRangeUpper,
+                                upper,
+                            ));
+                        }
+                    }
+                    self.pos += 1;
+                }
+                '(' => {
+                    self.pos += 1;
+                    self.skip_space(true);
+                    let sub_id = self.generate_symbol_id(rule_name);
+                    self.parse_alternates(rule_name, sub_id, true)?;
+                    last_sym_start = out.len();
+                    out.push(element(WhisperGrammarElementType::RuleReference, sub_id));
+                    if self.peek() != Some(')') {
+                        return Err(self.error("Expected ')'"));
+                    }
+                    self.pos += 1;
+                }
+                '*' | '+' | '?' => {
+                    if last_sym_start == out.len() {
+                        return Err(self.error(&format!("Expecting an item before '{}'", c)));
+                    }
+                    self.pos += 1;
+
+                    // S* --> S' ::= S S' |
+                    // S+ --> S' ::= S S' | S
+                    // S? --> S' ::= S |
+                    let sub_id = self.generate_symbol_id(rule_name);
+                    let item = out[last_sym_start..].to_vec();
+                    let mut sub_rule = item.clone();
+                    if c != '?' {
+                        sub_rule.push(element(WhisperGrammarElementType::RuleReference, sub_id));
+                    }
+                    sub_rule.push(element(WhisperGrammarElementType::Alternate, 0));
+                    if c == '+' {
+                        sub_rule.extend(item);
+                    }
+                    sub_rule.push(element(WhisperGrammarElementType::End, 0));
+                    self.add_rule(sub_id, sub_rule);
+
+                    out.truncate(last_sym_start);
+                    out.push(element(WhisperGrammarElementType::RuleReference, sub_id));
+                }
+                c if Self::is_name_char(c) => {
+                    let name = self.parse_name()?;
+                    let ref_id = self.symbol_id(&name);
+                    last_sym_start = out.len();
+                    out.push(element(WhisperGrammarElementType::RuleReference, ref_id));
+                }
+                _ => break,
+            }
+            self.skip_space(nested);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple_grammar() {
+        let grammar = ParsedGrammar::parse(r#"root ::= "yes" | "no""#).unwrap();
+        assert_eq!(grammar.rule_count(), 1);
+        assert_eq!(grammar.start_rule(), 0);
+
+        let elements = grammar.elements();
+        assert_eq!(elements.len(), 7);
+        assert_eq!(
+            elements[0],
+            element(WhisperGrammarElementType::Character, 'y' as u32)
+        );
+        assert_eq!(
+            elements[3],
+            element(WhisperGrammarElementType::Alternate, 0)
+        );
+        assert_eq!(elements[6], element(WhisperGrammarElementType::End, 0));
+    }
+
+    #[test]
+    fn test_parse_grammar_with_rules_classes_and_repetition() {
+        let source = r#"
+            # Spoken commands
+            root    ::= command " " number
+            command ::= ("play" | "stop")
+            number  ::= [0-9]+
+        "#;
+        let grammar = ParsedGrammar::parse(source).unwrap();
+
+        // root, command, number, the group and the repetition
+        assert_eq!(grammar.rule_count(), 5);
+        assert_eq!(grammar.start_rule(), 0);
+        assert!(grammar.elements().contains(&element(
+            WhisperGrammarElementType::CharacterRangeUpper,
+            '9' as u32
+        )));
+    }
+
+    #[test]
+    fn test_parse_grammar_errors() {
+        assert!(ParsedGrammar::parse(r#"command ::= "go""#).is_err());
+        assert!(ParsedGrammar::parse("root ::= missing").is_err());
+        assert!(ParsedGrammar::parse(r#"root "go""#).is_err());
+        assert!(ParsedGrammar::parse(r#"root ::= ("go""#).is_err());
+        assert!(ParsedGrammar::parse("root ::= *").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Fixed-bucket histogram of transcription times
+//!
+//! Each bucket counts the values up to and including its upper bound that
+//! did not fit an earlier bucket; a final unbounded bucket catches the rest.
+//! Counts are not cumulative, so percentiles are estimated by walking the
+//! buckets until the running total passes the wanted fraction.
+
+use serde::{Deserialize, Serialize};
+
+/// Default bucket upper bounds in milliseconds
+pub const DEFAULT_LATENCY_BUCKETS_MS: [u64; 7] = [100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// One bucket of a serialized histogram
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// Inclusive upper bound in milliseconds (None for the overflow bucket)
+    pub le: Option<u64>,
+    /// Number of values that landed in this bucket
+    pub count: u64,
+}
+
+/// Histogram with configurable bucket boundaries
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    /// Sorted, de-duplicated upper bounds
+    bounds: Vec<u64>,
+    /// One count per bound plus the overflow bucket
+    counts: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram
+    ///
+    /// # Arguments
+    /// * `bounds` - Bucket upper bounds in milliseconds, in any order
+    pub fn new(bounds: &[u64]) -> Self {
+        let mut bounds = bounds.to_vec();
+        bounds.sort_unstable();
+        bounds.dedup();
+        let counts = vec![0; bounds.len() + 1];
+        Self { bounds, counts }
+    }
+
+    /// Count a value in the first bucket whose bound is not below it
+    ///
+    /// # Arguments
+    /// * `value_ms` - Value in milliseconds
+    pub fn record(&mut self, value_ms: u64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value_ms);
+        self.counts[bucket] += 1;
+    }
+
+    /// Buckets in ascending order, ending with the overflow bucket
+    pub fn buckets(&self) -> Vec<HistogramBucket> {
+        self.bounds
+            .iter()
+            .map(|&bound| Some(bound))
+            .chain(std::iter::once(None))
+            .zip(&self.counts)
+            .map(|(le, &count)| HistogramBucket { le, count })
+            .collect()
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new(&DEFAULT_LATENCY_BUCKETS_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_land_in_expected_buckets() {
+        let mut histogram = LatencyHistogram::new(&[500, 100, 1000]);
+        for value in [0, 100, 101, 499, 500, 999, 1000, 1001, 60000] {
+            histogram.record(value);
+        }
+
+        assert_eq!(
+            histogram.buckets(),
+            vec![
+                HistogramBucket {
+                    le: Some(100),
+                    count: 2
+                },
+                HistogramBucket {
+                    le: Some(500),
+                    count: 3
+                },
+                HistogramBucket {
+                    le: Some(1000),
+                    count: 2
+                },
+                HistogramBucket { le: None, count: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_buckets_start_empty() {
+        let buckets = LatencyHistogram::default().buckets();
+        assert_eq!(buckets.len(), DEFAULT_LATENCY_BUCKETS_MS.len() + 1);
+        assert!(buckets.iter().all(|bucket| bucket.count == 0));
+    }
+}
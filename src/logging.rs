@@ -1,14 +1,43 @@
 use log::LevelFilter;
 use std::io::Write;
+use std::str::FromStr;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+/// Formats log records can be written to stderr in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per record
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {} (expected text or json)", s)),
+        }
+    }
+}
+
 /// Configure logging to output to stderr with proper formatting
-pub fn configure_logging() {
+///
+/// # Arguments
+/// * `format` - Format records are written in
+pub fn configure_logging(format: LogFormat) {
     // Set up log level to Info for normal operation, Debug for detailed info
     log::set_max_level(LevelFilter::Info);
 
     // Simple stderr logger implementation
-    let logger = Box::new(CustomLogger::new());
+    let logger: Box<dyn log::Log> = match format {
+        LogFormat::Text => Box::new(CustomLogger::new()),
+        LogFormat::Json => Box::new(JsonLogger),
+    };
 
     // Apply the logger
     if let Err(e) = log::set_logger(Box::leak(logger)) {
@@ -16,6 +45,14 @@ pub fn configure_logging() {
     }
 }
 
+/// Seconds since the Unix epoch
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 /// Custom logger that outputs to stderr with formatting
 pub struct CustomLogger {
     start_time: Instant,
@@ -30,13 +67,7 @@ impl CustomLogger {
 
     fn format_log(&self, level: log::Level, _target: &str, message: &str) -> String {
         let elapsed = self.start_time.elapsed();
-        let timestamp = format!(
-            "{}",
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        );
+        let timestamp = unix_timestamp();
         format!(
             "[{} {} {}.{:03}s] {}",
             timestamp,
@@ -65,3 +96,64 @@ impl log::Log for CustomLogger {
         std::io::stderr().flush().unwrap();
     }
 }
+
+/// Logger that writes one JSON object per record to stderr
+pub struct JsonLogger;
+
+impl JsonLogger {
+    fn format_record(&self, record: &log::Record) -> String {
+        serde_json::json!({
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+            "timestamp": unix_timestamp(),
+        })
+        .to_string()
+    }
+}
+
+impl log::Log for JsonLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", self.format_record(record));
+        }
+    }
+
+    fn flush(&self) {
+        std::io::stderr().flush().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_logger_formats_record() {
+        let line = JsonLogger.format_record(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("whisper_background_server::audio")
+                .args(format_args!("Dropped {} \"late\" chunks", 2))
+                .build(),
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["target"], "whisper_background_server::audio");
+        assert_eq!(json["message"], "Dropped 2 \"late\" chunks");
+        assert!(json["timestamp"].as_u64().unwrap() > 0);
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("text".parse(), Ok(LogFormat::Text));
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
@@ -1,28 +1,78 @@
+use std::collections::HashMap;
 use std::env;
 use std::io::{self, Write};
 use std::path::Path;
 use std::process;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
-use whisper_rs::{WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    DtwMode, DtwModelPreset, DtwParameters, WhisperContext, WhisperContextParameters,
+};
 
 mod audio;
+mod commands;
 mod environment;
+mod grammar;
+mod histogram;
 mod logging;
+mod rate_limit;
+mod recent;
+#[cfg(all(unix, feature = "socket"))]
+mod socket;
+mod state_pool;
 mod transcription;
+mod worker;
 use audio::{AudioBuffer, AudioProcessor};
-use environment::{Config, parse_arguments};
-use transcription::{TranscriptionConfig, TranscriptionService};
+use environment::{
+    Config, ErrorStream, EventStream, OutputFormat, RateLimitPolicy, parse_arguments,
+};
+use rate_limit::TokenBucket;
+use tokio::sync::mpsc;
+use transcription::{TranscriptionConfig, TranscriptionError, TranscriptionService};
 
 /// Structure to hold the loaded model and configuration
+///
+/// Owned by the transcription worker once the server is initialized.
 #[derive(Debug)]
 pub struct ServerState {
     /// Configuration used to initialize the server
     pub config: Config,
     /// Transcription service (contains the Whisper context)
     pub transcription_service: TranscriptionService,
+    /// Destination of non-result events
+    pub events: EventSink,
+}
+
+/// What a session needs to serve the requests of one input
+///
+/// A session reads stdin (or the `--input-fifo`), or one socket connection,
+/// and hands its transcriptions to the shared worker.
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Server configuration
+    pub config: Config,
+    /// Queue of the worker that owns the model
+    pub queue: worker::WorkQueue,
+    /// Destination of non-result events
+    pub events: EventSink,
+    /// Destination of results (stdout, or the connected client in socket mode)
+    pub output: EventSink,
+    /// Whether requests come from a socket connection rather than stdin
+    pub remote: bool,
+}
+
+/// Response to the `load_model` command
+#[derive(Serialize, Deserialize)]
+struct ModelLoadedResponse {
+    /// Event type (always "model_loaded")
+    event: String,
+    /// Server information for the new model
+    #[serde(flatten)]
+    server_info: ServerInfo,
 }
 
 /// Information about the loaded model and server state
@@ -64,19 +114,99 @@ pub struct ServerParameters {
     pub audio_format: String,
 }
 
-/// Initialize the Whisper model with the given configuration
+/// Build the Whisper context parameters for the given configuration
+///
+/// GPU-only options are dropped under `--cpu-only`.
 ///
 /// # Arguments
 /// * `config` - Server configuration
 ///
 /// # Returns
-/// * `Result<ServerState, String>` - Initialized server state on success, error message on failure
-pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
-    info!("Initializing Whisper Background Server");
-    debug!("Model path: {}", config.model_path);
-    debug!("Threads: {:?}", config.threads);
-    debug!("CPU only: {}", config.cpu_only);
+/// * `(WhisperContextParameters, Vec<&str>)` - The parameters and the GPU flags that were ignored
+fn context_parameters(config: &Config) -> (WhisperContextParameters<'static>, Vec<&'static str>) {
+    let mut params = WhisperContextParameters::new();
+    let mut ignored = Vec::new();
+    if config.cpu_only {
+        if config.flash_attn {
+            ignored.push("--flash-attn");
+        }
+    } else {
+        params.use_gpu(true);
+        params.flash_attn(config.flash_attn);
+    }
+    if let Some(model_preset) = config.dtw_preset.as_deref().and_then(dtw_model_preset) {
+        params.dtw_parameters(DtwParameters {
+            mode: DtwMode::ModelPreset { model_preset },
+            ..Default::default()
+        });
+    }
+    (params, ignored)
+}
+
+/// Map a `--dtw` preset name to the whisper alignment-head preset
+///
+/// # Arguments
+/// * `name` - Preset name as accepted by `--dtw`
+///
+/// # Returns
+/// * `Option<DtwModelPreset>` - The preset, or None for an unknown name
+fn dtw_model_preset(name: &str) -> Option<DtwModelPreset> {
+    let preset = match name {
+        "tiny" => DtwModelPreset::Tiny,
+        "tiny.en" => DtwModelPreset::TinyEn,
+        "base" => DtwModelPreset::Base,
+        "base.en" => DtwModelPreset::BaseEn,
+        "small" => DtwModelPreset::Small,
+        "small.en" => DtwModelPreset::SmallEn,
+        "medium" => DtwModelPreset::Medium,
+        "medium.en" => DtwModelPreset::MediumEn,
+        "large.v1" => DtwModelPreset::LargeV1,
+        "large.v2" => DtwModelPreset::LargeV2,
+        "large.v3" => DtwModelPreset::LargeV3,
+        "large.v3.turbo" => DtwModelPreset::LargeV3Turbo,
+        _ => return None,
+    };
+    Some(preset)
+}
+
+/// Check that a DTW preset fits the loaded model
+///
+/// Alignment heads differ per model size, and a mismatched preset produces
+/// meaningless timings. Large model revisions cannot be told apart from the
+/// model header, so only the size and the English-only variant are checked.
+///
+/// # Arguments
+/// * `preset` - Preset name as accepted by `--dtw`
+/// * `model_type` - Model size reported by whisper.cpp (e.g. "base")
+/// * `multilingual` - Whether the loaded model is multilingual
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if the preset matches, error message otherwise
+fn validate_dtw_preset(preset: &str, model_type: &str, multilingual: bool) -> Result<(), String> {
+    let size = preset.split('.').next().unwrap_or(preset);
+    let english_only = preset.ends_with(".en");
+    if size != model_type || english_only == multilingual {
+        let loaded = if multilingual {
+            model_type.to_string()
+        } else {
+            format!("{}.en", model_type)
+        };
+        return Err(format!(
+            "DTW preset {} does not match the loaded {} model",
+            preset, loaded
+        ));
+    }
+    Ok(())
+}
 
+/// Load and validate the Whisper model named by the configuration
+///
+/// # Arguments
+/// * `config` - Server configuration
+///
+/// # Returns
+/// * `Result<WhisperContext, String>` - Loaded context on success, error message on failure
+fn load_context(config: &Config) -> Result<WhisperContext, String> {
     // Validate model path
     environment::validate_model_path(&config.model_path)?;
 
@@ -84,9 +214,9 @@ pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
     info!("Loading Whisper model from: {}", config.model_path);
 
     // Set up parameters based on config
-    let mut params = WhisperContextParameters::new();
-    if !config.cpu_only {
-        params.use_gpu(true);
+    let (params, ignored) = context_parameters(config);
+    for flag in ignored {
+        warn!("{} has no effect with --cpu-only and is ignored", flag);
     }
 
     let context = match WhisperContext::new_with_params(&config.model_path, params) {
@@ -100,15 +230,46 @@ pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
         }
     };
 
-    // Note: Thread configuration may need to be set through different methods
-    // or may not be available in this version of whisper-rs
-    if let Some(threads) = config.threads {
-        info!(
-            "Note: Thread count {} specified, but may need to be configured differently",
-            threads
-        );
+    // A corrupted or wrong-type model can load without the parts inference needs
+    let (has_encoder, has_decoder) = transcription::model_components(&context);
+    transcription::check_model_architecture(has_encoder, has_decoder)
+        .map_err(|e| format!("{} ({})", e, e.error_code()))?;
+
+    if let Some(ref preset) = config.dtw_preset {
+        let model_type = context
+            .model_type_readable_str()
+            .map_err(|e| format!("Failed to read model type: {}", e))?;
+        validate_dtw_preset(preset, model_type, context.is_multilingual())?;
+        info!("DTW token timestamps enabled with preset {}", preset);
     }
 
+    Ok(context)
+}
+
+/// Initialize the Whisper model with the given configuration
+///
+/// # Arguments
+/// * `config` - Server configuration
+///
+/// # Returns
+/// * `Result<ServerState, String>` - Initialized server state on success, error message on failure
+pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
+    info!("Initializing Whisper Background Server");
+    debug!("Model path: {}", config.model_path);
+    debug!("Threads: {:?}", config.threads);
+    debug!("CPU only: {}", config.cpu_only);
+
+    // Open the event stream before the slow model load so a missing fd fails fast
+    let events = EventSink::open(config.events_to)?;
+
+    let context = load_context(&config)?;
+
+    // Per-language overrides are opt-in since they add a detection pass to every request
+    let language_overrides = match config.language_overrides_path {
+        Some(ref path) => transcription::load_language_overrides(path)?,
+        None => HashMap::new(),
+    };
+
     // Create transcription configuration
     let transcription_config = TranscriptionConfig {
         language: None, // Auto-detect
@@ -120,6 +281,47 @@ pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
         beam_size: Some(5),    // Updated to match new default
         suppress_blank: true,
         word_timestamps: false,
+        pre_emphasis: config.pre_emphasis,
+        trim_silence: config.trim_silence,
+        agc: config.agc.then(|| audio::AgcSettings {
+            target_rms: config
+                .agc_target_rms
+                .unwrap_or(audio::DEFAULT_AGC_TARGET_RMS),
+            max_gain: config.agc_max_gain.unwrap_or(audio::DEFAULT_AGC_MAX_GAIN),
+        }),
+        preprocess_order: config
+            .preprocess_order
+            .clone()
+            .unwrap_or_else(|| audio::PreprocessStep::DEFAULT_ORDER.to_vec()),
+        chunk_ms: config.chunk_secs.map(|secs| secs * 1000),
+        chunk_overlap_ms: config.chunk_overlap_ms.unwrap_or(0),
+        low_memory: config.low_memory,
+        skip_silent: config.skip_silent,
+        language_overrides,
+        timestamp_decimals: config
+            .timestamp_decimals
+            .unwrap_or(transcription::DEFAULT_TIMESTAMP_DECIMALS),
+        state_pool_size: config
+            .state_pool_size
+            .unwrap_or(transcription::DEFAULT_STATE_POOL_SIZE),
+        with_translation: config.with_translation,
+        min_segment_ms: config.min_segment_ms,
+        max_segments: config.max_segments,
+        merge_short_segments: config.merge_short_segments,
+        deterministic: config.deterministic,
+        threads: config.threads,
+        greedy_fallback: config.greedy_fallback,
+        phrase_gap_ms: config.phrase_segments.then(|| {
+            config
+                .phrase_gap_ms
+                .unwrap_or(transcription::DEFAULT_PHRASE_GAP_MS)
+        }),
+        speaker_gap_ms: config.speaker_hints.then(|| {
+            config
+                .speaker_gap_ms
+                .unwrap_or(transcription::DEFAULT_SPEAKER_GAP_MS)
+        }),
+        ..Default::default()
     };
 
     // Create transcription service
@@ -138,6 +340,7 @@ pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
     let server_state = ServerState {
         config,
         transcription_service,
+        events,
     };
 
     // Send server info to stdout
@@ -158,6 +361,26 @@ pub async fn initialize_server(config: Config) -> Result<ServerState, String> {
 /// # Returns
 /// * `Result<(), String>` - Ok if successful, error message if failed
 fn send_server_info(server_state: &ServerState) -> Result<(), String> {
+    let server_info = server_info(server_state)?;
+    debug!("Sending server info");
+    // Serialize to JSON and write to stdout
+    match serde_json::to_string(&server_info) {
+        Ok(json) => server_state
+            .events
+            .write_line(&json)
+            .map_err(|e| format!("Failed to write server info: {}", e)),
+        Err(e) => Err(format!("Failed to serialize server info: {}", e)),
+    }
+}
+
+/// Describe the loaded model and server parameters
+///
+/// # Arguments
+/// * `server_state` - The initialized server state
+///
+/// # Returns
+/// * `Result<ServerInfo, String>` - Server information, error if the model file cannot be inspected
+fn server_info(server_state: &ServerState) -> Result<ServerInfo, String> {
     let path = Path::new(&server_state.config.model_path);
 
     // Get file size
@@ -194,57 +417,157 @@ fn send_server_info(server_state: &ServerState) -> Result<(), String> {
             audio_format: "16kHz mono PCM".to_string(),
         },
     };
-    debug!("Sending server info");
-    // Serialize to JSON and write to stdout
-    match serde_json::to_string(&server_info) {
-        Ok(json) => {
-            println!("{}", json);
-            io::stdout()
-                .flush()
-                .map_err(|e| format!("Failed to flush stdout: {}", e))?;
-            Ok(())
-        }
-        Err(e) => Err(format!("Failed to serialize server info: {}", e)),
+    Ok(server_info)
+}
+
+/// Replace the loaded model without restarting the server
+///
+/// The new model is loaded and validated with the current configuration
+/// before it replaces the old one, so a failed load leaves the server
+/// running on the previous model. Swaps run on the transcription worker,
+/// which handles one job at a time, so a swap never overlaps a
+/// transcription or another swap.
+///
+/// # Arguments
+/// * `server_state` - Server state holding the current model
+/// * `model_path` - Path of the model to load
+///
+/// # Returns
+/// * `Result<ServerInfo, String>` - Server information for the new model, error message on failure
+fn swap_model(server_state: &mut ServerState, model_path: &str) -> Result<ServerInfo, String> {
+    info!("Loading replacement model from: {}", model_path);
+    let config = Config {
+        model_path: model_path.to_string(),
+        ..server_state.config.clone()
+    };
+    let context = load_context(&config)?;
+
+    // The previous context is freed once it is replaced
+    server_state.transcription_service.replace_context(context);
+    server_state.config = config;
+    info!("Model replaced with {}", model_path);
+    server_info(server_state)
+}
+
+/// Handle the `load_model` command
+///
+/// # Arguments
+/// * `command` - Command read from the input
+/// * `queue` - Queue of the worker holding the current model
+///
+/// # Returns
+/// * `Result<String, String>` - Serialized response on success, error message on failure
+async fn handle_load_model(
+    command: &commands::CommandRequest,
+    queue: &worker::WorkQueue,
+) -> Result<String, String> {
+    let path = command
+        .string_arg("path")
+        .ok_or_else(|| "load_model requires a string \"path\"".to_string())?;
+    let server_info = queue.load_model(path).await?;
+    serde_json::to_string(&ModelLoadedResponse {
+        event: "model_loaded".to_string(),
+        server_info,
+    })
+    .map_err(|e| format!("Failed to serialize server info: {}", e))
+}
+
+/// Build the structured output for a transcription result
+///
+/// In deterministic mode the generation timestamp and the measured duration
+/// are left out, so identical input renders to identical bytes.
+///
+/// # Arguments
+/// * `result` - The transcription result
+/// * `config` - Server configuration
+///
+/// # Returns
+/// * `TranscriptionOutput` - Output with all relevant fields
+fn transcription_output(
+    result: &transcription::TranscriptionResult,
+    config: &Config,
+) -> TranscriptionOutput {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string();
+    TranscriptionOutput {
+        text: result.text.clone(),
+        language: result.language.clone(),
+        segments: result.segments.clone(),
+        success: result.success,
+        error: result.error.clone(),
+        duration_ms: result.duration_ms.filter(|_| !config.deterministic),
+        trimmed_leading_ms: result.trimmed_leading_ms,
+        trimmed_trailing_ms: result.trimmed_trailing_ms,
+        segments_total: result.segments_total,
+        segments_emitted: result.segments_emitted,
+        error_code: result.error_code.clone(),
+        input_audio: result.input_audio.clone(),
+        translated_text: result.translated_text.clone(),
+        segments_too_short: result.segments_too_short,
+        segments_truncated: result.segments_truncated,
+        metrics: result.metrics.clone().filter(|_| config.include_metrics),
+        fallback_used: result.fallback_used,
+        avg_logprob: result.avg_logprob,
+        low_confidence: config
+            .min_avg_logprob
+            .zip(result.avg_logprob)
+            .map(|(min_avg_logprob, avg_logprob)| avg_logprob < min_avg_logprob),
+        decoding: result.decoding.clone().filter(|_| config.debug_decoding),
+        no_speech: result.no_speech,
+        reason: result.reason.clone(),
+        audio_duration_ms: result
+            .metrics
+            .as_ref()
+            .map(|metrics| metrics.audio_duration_ms),
+        temperature: result
+            .decoding
+            .as_ref()
+            .map(|decoding| decoding.temperature),
+        timestamp: (!config.deterministic).then_some(timestamp),
     }
 }
 
-/// Send transcription result to stdout as JSON
+/// Send transcription result to the output in the configured output format
+///
+/// Failed results are routed according to `--errors-to`, where stdout stands
+/// for the output.
 ///
 /// # Arguments
 /// * `result` - The transcription result to format and send
+/// * `config` - Server configuration (output format and error routing)
+/// * `output` - Destination of results
 ///
 /// # Returns
 /// * `Result<(), String>` - Ok if successful, error message if failed
 fn send_transcription_result_json(
     result: &transcription::TranscriptionResult,
+    config: &Config,
+    output: &EventSink,
 ) -> Result<(), String> {
+    let format = config.output_format;
     debug!("Formatting transcription result as JSON for output");
+    let structured = transcription_output(result, config);
 
-    // Create a structured output object that includes all relevant fields
-    let output = TranscriptionOutput {
-        text: result.text.clone(),
-        language: result.language.clone(),
-        segments: result.segments.clone(),
-        success: result.success,
-        error: result.error.clone(),
-        duration_ms: result.duration_ms,
-        timestamp: Some(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-                .to_string(),
-        ),
-    };
-
-    // Serialize to JSON and write to stdout
-    match serde_json::to_string(&output) {
+    // Render in the configured format and write to the output
+    match render_output(&structured, format, config.output_fields.as_deref()) {
         Ok(json) => {
-            debug!("Successfully serialized transcription result to JSON");
-            println!("{}", json);
+            debug!(
+                "Successfully rendered transcription result as {}",
+                format.as_str()
+            );
 
-            // Flush stdout to ensure the output is sent immediately
-            match io::stdout().flush() {
+            // Write and flush under the stream locks so the output is sent immediately
+            let written = if result.success {
+                output.write_line(&json)
+            } else {
+                output.with_writer(|out| {
+                    write_error_line(config.errors_to, &json, out, &mut io::stderr().lock())
+                })
+            };
+            match written {
                 Ok(_) => {
                     debug!("Successfully flushed stdout after JSON output");
                     Ok(())
@@ -262,8 +585,480 @@ fn send_transcription_result_json(
     }
 }
 
-/// Structured transcription output for JSON serialization
+/// Transcribe one request with its options merged over the server configuration
+///
+/// Segments of a piece split off by `--max-buffer-ms` are shifted to the
+/// piece's position in the request.
+///
+/// # Arguments
+/// * `base` - Server transcription configuration
+/// * `audio_data` - Audio and options of the request
+/// * `transcribe` - Runs the transcription of the request with the merged configuration
+///
+/// # Returns
+/// * `Result<TranscriptionResult, TranscriptionError>` - Result, or an error for invalid options
+fn transcribe_request<F>(
+    base: &TranscriptionConfig,
+    audio_data: &audio::AudioData,
+    transcribe: F,
+) -> Result<transcription::TranscriptionResult, TranscriptionError>
+where
+    F: FnOnce(
+        &audio::AudioData,
+        &TranscriptionConfig,
+    ) -> Result<transcription::TranscriptionResult, TranscriptionError>,
+{
+    let config = transcription::request_config(base, audio_data.options.as_ref())?;
+    let mut result = transcribe(audio_data, &config)?;
+    if audio_data.offset_ms > 0
+        && let Some(ref mut segments) = result.segments
+    {
+        transcription::offset_segment_timestamps(segments, audio_data.offset_ms);
+        transcription::round_segment_timestamps(segments, config.timestamp_decimals);
+    }
+    Ok(result)
+}
+
+/// Send an error result for a request that failed before or during transcription
+///
+/// # Arguments
+/// * `error` - Error reported to the client
+/// * `config` - Server configuration (output format and error routing)
+/// * `output` - Destination of results
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if successful, error message if failed
+fn send_error_result(
+    error: &TranscriptionError,
+    config: &Config,
+    output: &EventSink,
+) -> Result<(), String> {
+    send_transcription_result_json(&error.into(), config, output)
+}
+
+/// Write an error line to the streams selected by `--errors-to`
+///
+/// # Arguments
+/// * `routing` - Streams to write to
+/// * `line` - Rendered error line
+/// * `stdout` - Standard output writer
+/// * `stderr` - Standard error writer
+fn write_error_line<O: Write + ?Sized, E: Write + ?Sized>(
+    routing: ErrorStream,
+    line: &str,
+    stdout: &mut O,
+    stderr: &mut E,
+) -> io::Result<()> {
+    // A line written to both streams carries the same number on each
+    let line = stamp_output_seq(line);
+    if routing.to_stdout() {
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()?;
+    }
+    if routing.to_stderr() {
+        writeln!(stderr, "{}", line)?;
+        stderr.flush()?;
+    }
+    Ok(())
+}
+
+/// Error event reported when a control command fails
+#[derive(Serialize, Deserialize)]
+struct CommandErrorEvent {
+    /// Event type (always "error")
+    event: String,
+    /// Command that failed
+    command: String,
+    /// Error message
+    error: String,
+    /// Stable error code (only for unknown commands)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_code: Option<String>,
+    /// Commands the server understands (only for unknown commands)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    known_commands: Option<Vec<String>>,
+}
+
+impl CommandErrorEvent {
+    /// Build the error event for a failed command
+    ///
+    /// # Arguments
+    /// * `command` - Name of the command that failed
+    /// * `error` - Error message
+    fn new(command: &str, error: String) -> Self {
+        let unknown = !commands::is_known(command);
+        Self {
+            event: "error".to_string(),
+            command: command.to_string(),
+            error,
+            error_code: unknown.then(|| "UNKNOWN_COMMAND".to_string()),
+            known_commands: unknown.then(|| {
+                commands::KNOWN_COMMANDS
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Warning event sent before a result whose input audio is heavily clipped
+#[derive(Serialize, Deserialize)]
+struct ClippingWarning {
+    /// Event type (always "warning")
+    event: String,
+    /// Warning code (always "CLIPPING")
+    code: String,
+    /// Fraction of samples at full scale
+    clipping_ratio: f32,
+}
+
+impl ClippingWarning {
+    /// Warning for a result, if its input clipped more than the threshold
+    fn for_result(result: &transcription::TranscriptionResult) -> Option<Self> {
+        let ratio = result.input_audio.as_ref()?.clipping_ratio;
+        (ratio > audio::CLIPPING_WARNING_RATIO).then(|| Self {
+            event: "warning".to_string(),
+            code: "CLIPPING".to_string(),
+            clipping_ratio: ratio,
+        })
+    }
+}
+
+/// Warning event sent when a request's declared duration disagrees with its audio
+#[derive(Serialize, Deserialize)]
+struct DurationWarning {
+    /// Event type (always "warning")
+    event: String,
+    /// Warning code (always "DURATION_MISMATCH")
+    code: String,
+    /// Duration declared by the client in milliseconds
+    declared_ms: u64,
+    /// Duration implied by the received samples in milliseconds
+    computed_ms: u64,
+}
+
+impl From<audio::DurationMismatch> for DurationWarning {
+    fn from(mismatch: audio::DurationMismatch) -> Self {
+        Self {
+            event: "warning".to_string(),
+            code: "DURATION_MISMATCH".to_string(),
+            declared_ms: mismatch.declared_ms,
+            computed_ms: mismatch.computed_ms,
+        }
+    }
+}
+
+/// Render a transcription output in the given format
+///
+/// This match is the dispatch table for output formats; every variant listed
+/// in `OutputFormat::ALL` (and reported by `list_output_formats`) has an arm.
+///
+/// # Arguments
+/// * `output` - Structured output to render
+/// * `format` - Format to render it in
+/// * `fields` - Allowlist of JSON fields to keep (all fields when None)
+///
+/// # Returns
+/// * `Result<String, String>` - Rendered line on success, error message on failure
+fn render_output(
+    output: &TranscriptionOutput,
+    format: OutputFormat,
+    fields: Option<&[String]>,
+) -> Result<String, String> {
+    match format {
+        OutputFormat::Json => {
+            let mut value = serde_json::to_value(output).map_err(|e| e.to_string())?;
+            if let (Some(fields), Some(object)) = (fields, value.as_object_mut()) {
+                object.retain(|key, _| fields.iter().any(|field| field == key));
+            }
+            serde_json::to_string(&value).map_err(|e| e.to_string())
+        }
+        OutputFormat::Text => Ok(match (&output.error, output.success) {
+            (Some(error), false) => format!("error: {}", error),
+            _ => output.text.clone(),
+        }),
+        OutputFormat::OpenAi => match (&output.error, output.success) {
+            (Some(error), false) => serde_json::to_string(&OpenAiErrorResponse {
+                error: OpenAiError {
+                    message: error.clone(),
+                    error_type: "server_error".to_string(),
+                    code: output.error_code.clone(),
+                },
+            }),
+            _ => serde_json::to_string(&OpenAiTranscription::from(output)),
+        }
+        .map_err(|e| e.to_string()),
+        // whisper.cpp has no error shape, so failures keep the default JSON error
+        OutputFormat::WhisperCpp => match (&output.error, output.success) {
+            (Some(_), false) => serde_json::to_string(output),
+            _ => serde_json::to_string(&WhisperCppTranscription::from(output)),
+        }
+        .map_err(|e| e.to_string()),
+    }
+}
+
+/// Format seconds as whisper.cpp's `HH:MM:SS,mmm` timestamp
+fn whisper_cpp_timestamp(seconds: f32) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        total_ms / 3_600_000,
+        total_ms / 60_000 % 60,
+        total_ms / 1000 % 60,
+        total_ms % 1000
+    )
+}
+
+/// Result in the shape of whisper.cpp's `--output-json` file
+#[derive(Serialize, Deserialize)]
+struct WhisperCppTranscription {
+    /// Detected language
+    result: WhisperCppResult,
+    /// Segments with timestamps (empty when timestamps are disabled)
+    transcription: Vec<WhisperCppSegment>,
+}
+
+/// Result-level fields of whisper.cpp's `--output-json` file
+#[derive(Serialize, Deserialize)]
+struct WhisperCppResult {
+    /// Detected language code
+    language: Option<String>,
+}
+
+/// Segment in the shape of whisper.cpp's `--output-json` file
+#[derive(Serialize, Deserialize)]
+struct WhisperCppSegment {
+    /// Start and end as `HH:MM:SS,mmm` strings
+    timestamps: WhisperCppSpan<String>,
+    /// Start and end in milliseconds
+    offsets: WhisperCppSpan<u64>,
+    /// Text content of the segment
+    text: String,
+}
+
+/// Start and end of a whisper.cpp segment
+#[derive(Serialize, Deserialize)]
+struct WhisperCppSpan<T> {
+    /// Segment start
+    from: T,
+    /// Segment end
+    to: T,
+}
+
+impl From<&TranscriptionOutput> for WhisperCppTranscription {
+    fn from(output: &TranscriptionOutput) -> Self {
+        let transcription = output
+            .segments
+            .iter()
+            .flatten()
+            .map(|segment| WhisperCppSegment {
+                timestamps: WhisperCppSpan {
+                    from: whisper_cpp_timestamp(segment.start),
+                    to: whisper_cpp_timestamp(segment.end),
+                },
+                offsets: WhisperCppSpan {
+                    from: (segment.start.max(0.0) * 1000.0).round() as u64,
+                    to: (segment.end.max(0.0) * 1000.0).round() as u64,
+                },
+                text: segment.text.clone(),
+            })
+            .collect();
+        Self {
+            result: WhisperCppResult {
+                language: output.language.clone(),
+            },
+            transcription,
+        }
+    }
+}
+
+/// Full language name OpenAI reports for a detected language code
+fn openai_language_name(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "en" => "english",
+        "zh" => "chinese",
+        "de" => "german",
+        "es" => "spanish",
+        "ru" => "russian",
+        "ko" => "korean",
+        "fr" => "french",
+        "ja" => "japanese",
+        "pt" => "portuguese",
+        "tr" => "turkish",
+        "pl" => "polish",
+        "ca" => "catalan",
+        _ => return None,
+    })
+}
+
+/// Result in the shape of OpenAI's verbose transcription response
+#[derive(Serialize, Deserialize)]
+struct OpenAiTranscription {
+    /// Always "transcribe"
+    task: String,
+    /// Full name of the detected language
+    language: Option<String>,
+    /// Duration of the input audio in seconds
+    duration: Option<f32>,
+    /// The transcribed text
+    text: String,
+    /// Segments with timestamps (empty when timestamps are disabled)
+    segments: Vec<OpenAiSegment>,
+}
+
+/// Segment in the shape of OpenAI's verbose transcription response
+#[derive(Serialize, Deserialize)]
+struct OpenAiSegment {
+    /// Index of the segment
+    id: usize,
+    /// Decoding window offset (always 0, windows are not tracked per segment)
+    seek: u64,
+    /// Start time in seconds
+    start: f32,
+    /// End time in seconds
+    end: f32,
+    /// Text content of the segment
+    text: String,
+    /// Token ids (not tracked, always empty)
+    tokens: Vec<i32>,
+    /// Temperature of the pass that produced the segment
+    temperature: f32,
+    /// Average token log probability of the whole result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    avg_logprob: Option<f32>,
+}
+
+impl From<&TranscriptionOutput> for OpenAiTranscription {
+    fn from(output: &TranscriptionOutput) -> Self {
+        let segments = output
+            .segments
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(id, segment)| OpenAiSegment {
+                id,
+                seek: 0,
+                start: segment.start,
+                end: segment.end,
+                text: segment.text.clone(),
+                tokens: Vec::new(),
+                temperature: output.temperature.unwrap_or(0.0),
+                avg_logprob: output.avg_logprob,
+            })
+            .collect();
+        Self {
+            task: "transcribe".to_string(),
+            language: output
+                .language
+                .as_deref()
+                .and_then(openai_language_name)
+                .map(str::to_string),
+            duration: output.audio_duration_ms.map(|ms| ms as f32 / 1000.0),
+            text: output.text.clone(),
+            segments,
+        }
+    }
+}
+
+/// Failed result in the shape of OpenAI's error response
+#[derive(Serialize, Deserialize)]
+struct OpenAiErrorResponse {
+    /// Error details
+    error: OpenAiError,
+}
+
+/// Error details in the shape of OpenAI's error response
+#[derive(Serialize, Deserialize)]
+struct OpenAiError {
+    /// Human-readable error message
+    message: String,
+    /// Error category (always "server_error")
+    #[serde(rename = "type")]
+    error_type: String,
+    /// Stable machine-readable error code
+    code: Option<String>,
+}
+
+/// Response to the `list_output_formats` command
 #[derive(Serialize, Deserialize)]
+struct OutputFormatsResponse {
+    /// Event type (always "output_formats")
+    event: String,
+    /// Identifiers accepted by `--output-format`
+    formats: Vec<String>,
+}
+
+impl OutputFormatsResponse {
+    fn new() -> Self {
+        Self {
+            event: "output_formats".to_string(),
+            formats: OutputFormat::ALL
+                .iter()
+                .map(|format| format.as_str().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Response to the `buffer_status` command
+#[derive(Serialize, Deserialize)]
+struct BufferStatusResponse {
+    /// Event type (always "buffer_status")
+    event: String,
+    /// Current buffer state
+    #[serde(flatten)]
+    status: audio::BufferStatus,
+}
+
+/// Response to the `recent_results` command
+#[derive(Serialize, Deserialize)]
+struct RecentResultsResponse {
+    /// Event type (always "recent_results")
+    event: String,
+    /// Kept results, newest first
+    results: Vec<recent::RecentResult>,
+}
+
+/// Handle a control command and produce its JSON response
+///
+/// # Arguments
+/// * `command` - Command read from stdin
+/// * `audio_buffer` - Buffer whose state commands may report
+/// * `recent` - Recent results kept with `--keep-recent`
+///
+/// # Returns
+/// * `Result<String, String>` - Serialized response on success, error message on failure
+fn handle_command(
+    command: &commands::CommandRequest,
+    audio_buffer: &AudioBuffer,
+    recent: Option<&recent::RecentResults>,
+) -> Result<String, String> {
+    debug!("Handling command: {}", command.command);
+    match command.command.as_str() {
+        commands::LIST_OUTPUT_FORMATS => serde_json::to_string(&OutputFormatsResponse::new())
+            .map_err(|e| format!("Failed to serialize output formats: {}", e)),
+        commands::BUFFER_STATUS => serde_json::to_string(&BufferStatusResponse {
+            event: "buffer_status".to_string(),
+            status: audio_buffer.status(),
+        })
+        .map_err(|e| format!("Failed to serialize buffer status: {}", e)),
+        commands::RECENT_RESULTS => {
+            let recent = recent.ok_or_else(|| {
+                "Recent results are not kept; start the server with --keep-recent".to_string()
+            })?;
+            serde_json::to_string(&RecentResultsResponse {
+                event: "recent_results".to_string(),
+                results: recent.newest(command.usize_arg("limit")),
+            })
+            .map_err(|e| format!("Failed to serialize recent results: {}", e))
+        }
+        other => Err(format!("Unknown command: {}", other)),
+    }
+}
+
+/// Structured transcription output for JSON serialization
+#[derive(Default, Serialize, Deserialize)]
 struct TranscriptionOutput {
     /// The transcribed text
     text: String,
@@ -277,39 +1072,794 @@ struct TranscriptionOutput {
     error: Option<String>,
     /// Time taken for transcription in milliseconds
     duration_ms: Option<u64>,
+    /// Leading silence removed before inference in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trimmed_leading_ms: Option<u64>,
+    /// Trailing silence removed before inference in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trimmed_trailing_ms: Option<u64>,
+    /// Number of segments produced by Whisper
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    segments_total: Option<usize>,
+    /// Number of segments kept after dropping empty ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    segments_emitted: Option<usize>,
+    /// Stable machine-readable error code if transcription failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_code: Option<String>,
+    /// How the input audio was interpreted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input_audio: Option<audio::InputAudioInfo>,
+    /// English translation of the text (with `--with-translation`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    translated_text: Option<String>,
+    /// Number of segments filtered out for being too short
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    segments_too_short: Option<usize>,
+    /// Number of segments dropped beyond the segment limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    segments_truncated: Option<usize>,
+    /// Server-side timings for this request (with `--include-metrics`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metrics: Option<transcription::RequestMetrics>,
+    /// Whether the text comes from a greedy retry after an empty beam search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fallback_used: Option<bool>,
+    /// Average token log probability weighted by segment token count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    avg_logprob: Option<f32>,
+    /// Whether `avg_logprob` is below `--min-avg-logprob`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    low_confidence: Option<bool>,
+    /// Sampling setup the text was decoded with (with `--debug-decoding`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decoding: Option<transcription::DecodingInfo>,
+    /// Whether inference was skipped because the audio is silent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    no_speech: Option<bool>,
+    /// Stable code explaining an empty successful result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Duration of the input audio in milliseconds, for formats that report it
+    #[serde(skip)]
+    audio_duration_ms: Option<u64>,
+    /// Temperature of the pass that produced the text, for formats that report it
+    #[serde(skip)]
+    temperature: Option<f32>,
     /// Timestamp when the result was generated (ISO 8601 format)
     timestamp: Option<String>,
 }
 
-/// Process JSON audio data from stdin using the async listener
-///
-/// # Arguments
-/// * `server_state` - The initialized server state
-///
-/// # Returns
-/// * `Result<(), String>` - Ok if successful, error message if failed
-async fn process_audio_stream(server_state: &ServerState) -> Result<(), String> {
-    debug!("Starting JSON audio processing from stdin");
-    debug!(
-        "JSON audio processing initialized with server state: {:?}",
-        server_state
-    );
+/// Running totals for the current session, reported when input ends
+#[derive(Debug, Default)]
+struct SessionStats {
+    /// Number of requests read from stdin (including rejected ones)
+    total_requests: u64,
+    /// Number of requests that produced a successful transcription
+    successful_requests: u64,
+    /// Number of requests that failed to parse or transcribe
+    failed_requests: u64,
+    /// Sum of transcription times for successful requests after the cold start
+    total_transcription_ms: u64,
+    /// Number of sequenced chunks ignored because their seq was already received
+    duplicate_chunks: u64,
+    /// Number of leading successful transcriptions kept out of the average
+    cold_start_requests: u64,
+    /// Transcription times of the cold-start requests
+    cold_start_ms: Vec<u64>,
+    /// Distribution of transcription times after the cold start
+    transcription_ms: histogram::LatencyHistogram,
+}
 
-    // Create audio buffer for JSON processing
-    let mut audio_buffer = AudioBuffer::new();
-    debug!("Audio buffer created for JSON processing");
+impl SessionStats {
+    /// Create stats for a session
+    ///
+    /// # Arguments
+    /// * `cold_start_requests` - Leading successful transcriptions kept out of the average
+    /// * `latency_buckets_ms` - Upper bounds of the transcription time histogram
+    fn new(cold_start_requests: u64, latency_buckets_ms: &[u64]) -> Self {
+        Self {
+            cold_start_requests,
+            transcription_ms: histogram::LatencyHistogram::new(latency_buckets_ms),
+            ..Self::default()
+        }
+    }
+
+    /// Record a request that produced a transcription result
+    fn record_result(&mut self, result: &transcription::TranscriptionResult) {
+        self.total_requests += 1;
+        if result.success {
+            self.successful_requests += 1;
+            let duration_ms = result.duration_ms.unwrap_or(0);
+            if self.successful_requests <= self.cold_start_requests {
+                self.cold_start_ms.push(duration_ms);
+            } else {
+                self.total_transcription_ms += duration_ms;
+                self.transcription_ms.record(duration_ms);
+            }
+        } else {
+            self.failed_requests += 1;
+        }
+    }
+
+    /// Record a request that was rejected before transcription
+    fn record_rejected(&mut self) {
+        self.total_requests += 1;
+        self.failed_requests += 1;
+    }
+
+    /// Record a retried chunk that was ignored
+    fn record_duplicate(&mut self) {
+        self.total_requests += 1;
+        self.duplicate_chunks += 1;
+    }
+
+    /// Build the end-of-run summary event
+    fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            event: "summary".to_string(),
+            total_requests: self.total_requests,
+            successful_requests: self.successful_requests,
+            failed_requests: self.failed_requests,
+            avg_transcription_ms: {
+                let steady_requests = self.successful_requests - self.cold_start_ms.len() as u64;
+                (steady_requests > 0)
+                    .then(|| self.total_transcription_ms as f64 / steady_requests as f64)
+            },
+            duplicate_chunks: self.duplicate_chunks,
+            cold_start_ms: self.cold_start_ms.clone(),
+            transcription_ms_histogram: self.transcription_ms.buckets(),
+        }
+    }
+}
+
+/// End-of-run summary event for JSON serialization
+#[derive(Serialize, Deserialize)]
+struct SessionSummary {
+    /// Event type (always "summary")
+    event: String,
+    /// Number of requests read from stdin
+    total_requests: u64,
+    /// Number of successful transcriptions
+    successful_requests: u64,
+    /// Number of failed requests
+    failed_requests: u64,
+    /// Average transcription time of successful requests in milliseconds
+    avg_transcription_ms: Option<f64>,
+    /// Number of retried sequenced chunks that were ignored
+    duplicate_chunks: u64,
+    /// Transcription times excluded from the average as cold starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cold_start_ms: Vec<u64>,
+    /// Transcription times of successful requests after the cold start, by bucket
+    transcription_ms_histogram: Vec<histogram::HistogramBucket>,
+}
+
+/// Send the end-of-run session summary as JSON
+///
+/// # Arguments
+/// * `stats` - Totals accumulated over the session
+/// * `events` - Destination of non-result events
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if successful, error message if failed
+fn send_session_summary(stats: &SessionStats, events: &EventSink) -> Result<(), String> {
+    let json = serde_json::to_string(&stats.summary())
+        .map_err(|e| format!("Failed to serialize session summary: {}", e))?;
+    events
+        .write_line(&json)
+        .map_err(|e| format!("Failed to write session summary: {}", e))
+}
+
+/// Sequence number of the next line written to any output stream
+static OUTPUT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Stamp a JSON object line with the next output sequence number
+///
+/// The number is added as the first field so the rest of the line keeps its
+/// field order. Lines that are not JSON objects (plain text results) are
+/// returned unchanged. Callers stamp while holding the stream's lock so the
+/// numbers on each stream are strictly increasing.
+///
+/// # Arguments
+/// * `line` - Serialized line to write
+///
+/// # Returns
+/// * `String` - The line with a leading `seq` field
+fn stamp_output_seq(line: &str) -> String {
+    let Some(fields) = line.strip_prefix('{') else {
+        return line.to_string();
+    };
+    let seq = OUTPUT_SEQ.fetch_add(1, Ordering::Relaxed);
+    if fields.trim_start().starts_with('}') {
+        format!("{{\"seq\":{}{}", seq, fields)
+    } else {
+        format!("{{\"seq\":{},{}", seq, fields)
+    }
+}
+
+/// Write a single line to stdout and flush it while holding the stdout lock
+///
+/// All JSON output goes through here so events written from other tasks
+/// (such as heartbeats) never interleave with results.
+///
+/// # Arguments
+/// * `line` - Serialized JSON to write
+fn write_output_line(line: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", stamp_output_seq(line))?;
+    stdout.flush()
+}
+
+/// Destination of output lines
+///
+/// Used both for non-result events selected by `--events-to` and for results.
+#[derive(Clone)]
+pub enum EventSink {
+    /// Shared with results on stdout
+    Stdout,
+    /// Standard error
+    Stderr,
+    /// Any other writer, such as file descriptor 3
+    Writer(Arc<Mutex<dyn Write + Send>>),
+    /// Lines handed to a writer task, such as the one sending to a socket connection
+    Queued(mpsc::Sender<String>),
+}
+
+impl std::fmt::Debug for EventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EventSink::Stdout => "Stdout",
+            EventSink::Stderr => "Stderr",
+            EventSink::Writer(_) => "Writer",
+            EventSink::Queued(_) => "Queued",
+        })
+    }
+}
+
+impl EventSink {
+    /// Open the configured event stream
+    ///
+    /// # Arguments
+    /// * `stream` - Stream selected by `--events-to`
+    ///
+    /// # Returns
+    /// * `Result<Self, String>` - The sink, error if fd 3 is not open for writing
+    fn open(stream: EventStream) -> Result<Self, String> {
+        match stream {
+            EventStream::Stdout => Ok(EventSink::Stdout),
+            EventStream::Stderr => Ok(EventSink::Stderr),
+            EventStream::Fd3 => std::fs::OpenOptions::new()
+                .write(true)
+                .open("/dev/fd/3")
+                .map(|file| EventSink::Writer(Arc::new(Mutex::new(file))))
+                .map_err(|e| format!("--events-to fd3 requires fd 3 to be open: {}", e)),
+        }
+    }
+
+    /// Run a function with the sink's writer locked
+    ///
+    /// # Arguments
+    /// * `f` - Function writing to the sink
+    fn with_writer<T>(&self, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+        match self {
+            EventSink::Stdout => f(&mut io::stdout().lock()),
+            EventSink::Stderr => f(&mut io::stderr().lock()),
+            EventSink::Writer(writer) => f(&mut *writer.lock().unwrap_or_else(|e| e.into_inner())),
+            EventSink::Queued(lines) => f(&mut QueuedLines {
+                lines,
+                pending: Vec::new(),
+            }),
+        }
+    }
+
+    /// Write a single event line and flush it
+    ///
+    /// # Arguments
+    /// * `line` - Serialized JSON to write
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        match self {
+            EventSink::Stdout => write_output_line(line),
+            EventSink::Stderr => {
+                let mut stderr = io::stderr().lock();
+                writeln!(stderr, "{}", stamp_output_seq(line))?;
+                stderr.flush()
+            }
+            EventSink::Writer(writer) => {
+                let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+                writeln!(writer, "{}", stamp_output_seq(line))?;
+                writer.flush()
+            }
+            EventSink::Queued(lines) => queue_line(lines, stamp_output_seq(line)),
+        }
+    }
+}
+
+/// Hand a line to the writer task of a queued sink
+///
+/// A full queue means the reader of the output has stopped keeping up, so
+/// the line is refused rather than waited on.
+///
+/// # Arguments
+/// * `lines` - Queue of the writer task
+/// * `line` - Line to write, without its newline
+fn queue_line(lines: &mpsc::Sender<String>, line: String) -> io::Result<()> {
+    lines.try_send(line).map_err(|e| match e {
+        mpsc::error::TrySendError::Full(_) => {
+            io::Error::new(io::ErrorKind::WouldBlock, "Output queue is full")
+        }
+        mpsc::error::TrySendError::Closed(_) => {
+            io::Error::new(io::ErrorKind::BrokenPipe, "Output is closed")
+        }
+    })
+}
+
+/// Writer that queues each complete line on a queued sink when flushed
+struct QueuedLines<'a> {
+    /// Queue of the writer task
+    lines: &'a mpsc::Sender<String>,
+    /// Bytes written since the last complete line
+    pending: Vec<u8>,
+}
+
+impl Write for QueuedLines<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            queue_line(
+                self.lines,
+                String::from_utf8_lossy(&line[..end]).into_owned(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a segment decoded by `--stream-segments`, or a discard of earlier ones, as an event
+///
+/// # Arguments
+/// * `events` - Destination of non-result events
+/// * `event` - Segment that was just decoded, or the pass whose segments are withdrawn
+fn emit_segment_event(events: &EventSink, event: transcription::StreamEvent) {
+    match serde_json::to_string(&event) {
+        Ok(json) => {
+            if let Err(e) = events.write_line(&json) {
+                warn!("Failed to send segment event: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize segment event: {}", e),
+    }
+}
+
+/// Idle heartbeat event for JSON serialization
+#[derive(Serialize, Deserialize)]
+struct HeartbeatEvent {
+    /// Event type (always "heartbeat")
+    event: String,
+    /// Seconds since the server started processing input
+    uptime_secs: u64,
+}
+
+/// Tracks whether the server is idle so heartbeats are only sent while waiting for input
+#[derive(Debug)]
+struct ActivityTracker {
+    /// When input processing started
+    started: Instant,
+    /// When the server last finished handling input
+    last_activity: Mutex<Instant>,
+    /// Whether a request is currently being handled
+    busy: AtomicBool,
+}
+
+impl ActivityTracker {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last_activity: Mutex::new(now),
+            busy: AtomicBool::new(false),
+        }
+    }
+
+    /// Mark the start of handling a request
+    fn start_work(&self) {
+        self.busy.store(true, Ordering::SeqCst);
+    }
+
+    /// Mark the server as idle again
+    fn finish_work(&self) {
+        *self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Instant::now();
+        self.busy.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the server has been idle for at least `interval` at `now`
+    fn idle_for(&self, now: Instant, interval: Duration) -> bool {
+        if self.busy.load(Ordering::SeqCst) {
+            return false;
+        }
+        let last_activity = *self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        now.saturating_duration_since(last_activity) >= interval
+    }
+
+    /// Heartbeat event reporting the uptime at `now`
+    fn heartbeat_event(&self, now: Instant) -> HeartbeatEvent {
+        HeartbeatEvent {
+            event: "heartbeat".to_string(),
+            uptime_secs: now.saturating_duration_since(self.started).as_secs(),
+        }
+    }
+}
+
+/// Spawn a task that emits heartbeat events while the server is idle
+///
+/// # Arguments
+/// * `tracker` - Activity shared with the processing loop
+/// * `interval` - Idle time between heartbeats
+///
+/// # Returns
+/// * `tokio::task::JoinHandle<()>` - Handle used to stop the task
+fn spawn_heartbeat(
+    tracker: Arc<ActivityTracker>,
+    interval: Duration,
+    events: EventSink,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            if !tracker.idle_for(now, interval) {
+                continue;
+            }
+            match serde_json::to_string(&tracker.heartbeat_event(now)) {
+                Ok(json) => {
+                    if let Err(e) = events.write_line(&json) {
+                        warn!("Failed to send heartbeat: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to serialize heartbeat: {}", e),
+            }
+        }
+    })
+}
+
+/// Spawn a task that keeps the model warm while the server is idle
+///
+/// After every `interval` spent idle a tiny silent inference touches the
+/// model so its pages are not evicted. The inference is queued on the
+/// worker like a transcription, so it always warms the current model and
+/// never runs alongside a request. The task stops once the worker has.
+///
+/// # Arguments
+/// * `tracker` - Activity shared with the processing loop
+/// * `queue` - Queue of the worker holding the model
+/// * `interval` - Idle time between keepalive inferences
+///
+/// # Returns
+/// * `JoinHandle` - Handle used to stop the task
+fn spawn_keepalive(
+    tracker: Arc<ActivityTracker>,
+    queue: worker::WorkQueue,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if !tracker.idle_for(Instant::now(), interval) {
+                continue;
+            }
+            match queue.warm().await {
+                Some(Ok(())) => debug!("Keepalive inference completed"),
+                Some(Err(e)) => warn!("Keepalive inference failed: {}", e),
+                None => {
+                    debug!("Transcription worker stopped, stopping keepalive");
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Background task of a session, stopped when the session ends
+///
+/// Dropping the guard aborts the task, so a session that is cancelled
+/// (e.g. a socket client that stopped reading) does not leave it running.
+struct TaskGuard(tokio::task::JoinHandle<()>);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Admit a request through the rate limiter according to the policy
+///
+/// # Arguments
+/// * `limiter` - Token bucket shared by all requests
+/// * `policy` - Whether to wait for a token or reject immediately
+///
+/// # Returns
+/// * `bool` - Whether the request may be processed
+async fn admit_request(limiter: &mut TokenBucket, policy: RateLimitPolicy) -> bool {
+    loop {
+        match limiter.try_acquire(Instant::now()) {
+            Ok(()) => return true,
+            Err(_) if policy == RateLimitPolicy::Reject => return false,
+            Err(wait) => {
+                debug!("Rate limit reached, waiting {:?}", wait);
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+/// Open the input requests are read from
+///
+/// # Arguments
+/// * `config` - Server configuration
+///
+/// # Returns
+/// * `Result<audio::InputReader, String>` - The FIFO given by `--input-fifo`, otherwise stdin
+async fn open_input(config: &Config) -> Result<audio::InputReader, String> {
+    match &config.input_fifo {
+        Some(path) => {
+            info!("Waiting for a writer on FIFO {}", path);
+            audio::InputReader::fifo(Path::new(path))
+                .await
+                .map_err(|e| format!("Failed to open input FIFO {}: {}", path, e))
+        }
+        None => Ok(audio::InputReader::stdin()),
+    }
+}
+
+/// Process JSON audio data from the input using the async listener
+///
+/// # Arguments
+/// * `session` - Configuration, worker queue and output sinks of the session
+/// * `input` - Input requests are read from
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if successful, error message if failed
+async fn process_audio_stream(
+    session: &Session,
+    mut input: audio::InputReader,
+) -> Result<(), String> {
+    debug!("Starting JSON audio processing from the input");
+    debug!(
+        "JSON audio processing initialized with session: {:?}",
+        session
+    );
+
+    // Create audio buffer for JSON processing
+    let mut audio_buffer = match session.config.max_buffer_ms {
+        Some(ms) => {
+            debug!("Flushing at most {} ms of audio per transcription", ms);
+            AudioBuffer::with_max_buffer_ms(ms)
+        }
+        None => AudioBuffer::new(),
+    };
+    debug!("Audio buffer created for JSON processing");
+    let mut stats = SessionStats::new(
+        session.config.exclude_cold_start.unwrap_or(0),
+        session
+            .config
+            .latency_buckets_ms
+            .as_deref()
+            .unwrap_or(&histogram::DEFAULT_LATENCY_BUCKETS_MS),
+    );
+
+    // Sequenced chunks are reassembled in order before buffering
+    let mut recent = session.config.keep_recent.map(recent::RecentResults::new);
+
+    let reorder_timeout_ms = session
+        .config
+        .reorder_timeout_ms
+        .unwrap_or(audio::DEFAULT_REORDER_TIMEOUT_MS);
+    let mut reorder = audio::ReorderBuffer::new(Duration::from_millis(reorder_timeout_ms));
+
+    // Limit request admission if requested
+    let mut rate_limiter = session
+        .config
+        .max_rps
+        .map(|rps| TokenBucket::new(rps, Instant::now()));
+
+    // Emit heartbeats while idle if requested
+    let activity = Arc::new(ActivityTracker::new());
+
+    let _heartbeat = session.config.heartbeat_secs.map(|secs| {
+        debug!("Sending heartbeats every {} seconds while idle", secs);
+        TaskGuard(spawn_heartbeat(
+            Arc::clone(&activity),
+            Duration::from_secs(secs),
+            session.events.clone(),
+        ))
+    });
+
+    // Keep the model warm while idle if requested
+    let _keepalive = session.config.keepalive_secs.map(|secs| {
+        TaskGuard(spawn_keepalive(
+            Arc::clone(&activity),
+            session.queue.clone(),
+            Duration::from_secs(secs),
+        ))
+    });
+
+    // Stream decoded segments to this session's events if requested
+    let emitter: Option<transcription::SegmentEmitter> =
+        session.config.stream_segments.then(|| {
+            let events = session.events.clone();
+            Arc::new(move |event| emit_segment_event(&events, event)) as _
+        });
 
     // Process JSON audio data as it arrives
     debug!("Starting JSON audio processing loop");
     loop {
-        debug!("Reading JSON audio data from stdin");
-        match audio::read_json_audio().await {
-            Ok(Some(audio_data)) => {
+        activity.finish_work();
+        debug!("Reading JSON audio data from the input");
+        let deadline = reorder.deadline();
+        let expiry = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into());
+        let read_result = tokio::select! {
+            read_result = audio::read_json_audio(&mut input) => match read_result {
+                // Chunks still waiting for missing ones are transcribed before stopping
+                Ok(None) => match reorder.drain() {
+                    Some(remaining) => {
+                        warn!("Input ended with sequenced chunks missing, transcribing the rest");
+                        Ok(Some(audio::InputMessage::Audio(Box::new(remaining))))
+                    }
+                    None => Ok(None),
+                },
+                read_result => read_result,
+            },
+            // Held chunks stop waiting even if no further chunk arrives
+            _ = expiry, if deadline.is_some() => match reorder.expire(Instant::now()) {
+                Some(released) => Ok(Some(audio::InputMessage::Audio(Box::new(released)))),
+                None => continue,
+            },
+        };
+        activity.start_work();
+        match read_result {
+            Ok(Some(audio::InputMessage::Command(command))) => {
+                let response = match command.command.as_str() {
+                    commands::LOAD_MODEL
+                        if session.remote && !session.config.allow_remote_load_model =>
+                    {
+                        Err("load_model is disabled on socket connections; start the server with --allow-remote-load-model to allow it".to_string())
+                    }
+                    commands::LOAD_MODEL => handle_load_model(&command, &session.queue).await,
+                    _ => handle_command(&command, &audio_buffer, recent.as_ref()),
+                };
+                match response.and_then(|response| {
+                    session
+                        .events
+                        .write_line(&response)
+                        .map_err(|e| format!("Failed to write command response: {}", e))
+                }) {
+                    Ok(()) => debug!("Command {} handled", command.command),
+                    Err(e) => {
+                        error!("Command {} failed: {}", command.command, e);
+                        let event = CommandErrorEvent::new(&command.command, e);
+                        let written = serde_json::to_string(&event)
+                            .map_err(|e| e.to_string())
+                            .and_then(|json| {
+                                session
+                                    .output
+                                    .with_writer(|out| {
+                                        write_error_line(
+                                            session.config.errors_to,
+                                            &json,
+                                            out,
+                                            &mut io::stderr().lock(),
+                                        )
+                                    })
+                                    .map_err(|e| e.to_string())
+                            });
+                        if let Err(e) = written {
+                            eprintln!("JSON output error for command: {}", e);
+                        }
+                    }
+                }
+            }
+            Ok(Some(audio::InputMessage::Audio(audio_data))) => {
+                let audio_data = *audio_data;
                 debug!("Received JSON audio data: {} bytes", audio_data.data.len());
+                let received_at = Instant::now();
+
+                // Apply request admission before buffering
+                if let Some(limiter) = &mut rate_limiter
+                    && !admit_request(limiter, session.config.rate_limit_policy).await
+                {
+                    warn!("Rejecting request over the rate limit");
+                    stats.record_rejected();
+                    let error = TranscriptionError::RateLimited;
+                    if let Err(e) = send_error_result(&error, &session.config, &session.output) {
+                        eprintln!("JSON output error for result: {}", e);
+                    }
+                    continue;
+                }
+
+                // A retried chunk must not be appended twice
+                if let Some(seq) = audio_data.seq
+                    && reorder.is_duplicate(seq)
+                {
+                    warn!("Ignoring duplicate audio chunk {}", seq);
+                    stats.record_duplicate();
+                    continue;
+                }
+
+                // A declared duration that disagrees with the audio is a client bug
+                let tolerance_ms = session
+                    .config
+                    .duration_tolerance_ms
+                    .unwrap_or(audio::DEFAULT_DURATION_TOLERANCE_MS);
+                if let Some(mismatch) = audio_data.duration_mismatch(tolerance_ms) {
+                    warn!(
+                        "Declared duration {}ms differs from the {}ms received",
+                        mismatch.declared_ms, mismatch.computed_ms
+                    );
+                    if session.config.strict_duration {
+                        stats.record_rejected();
+                        let error = TranscriptionError::DurationMismatch {
+                            declared_ms: mismatch.declared_ms,
+                            computed_ms: mismatch.computed_ms,
+                        };
+                        if let Err(e) = send_error_result(&error, &session.config, &session.output)
+                        {
+                            eprintln!("JSON output error for result: {}", e);
+                        }
+                        continue;
+                    }
+                    match serde_json::to_string(&DurationWarning::from(mismatch)) {
+                        Ok(json) => {
+                            if let Err(e) = session.events.write_line(&json) {
+                                warn!("Failed to send duration warning: {}", e);
+                            }
+                        }
+                        Err(e) => warn!("Failed to serialize duration warning: {}", e),
+                    }
+                }
+
+                // Hold sequenced chunks until the ones before them have arrived
+                let audio_data = match audio_data.seq {
+                    Some(seq) => match reorder.push(seq, audio_data, received_at) {
+                        Some(assembled) => assembled,
+                        None => {
+                            debug!(
+                                "Holding audio chunk {} ({} chunks waiting)",
+                                seq,
+                                reorder.pending_count()
+                            );
+                            continue;
+                        }
+                    },
+                    None => audio_data,
+                };
 
                 // Add audio data to buffer
                 if let Err(e) = audio_buffer.process_audio(&audio_data) {
                     error!("Failed to process audio data: {}", e);
+                    stats.record_rejected();
+                    let error = TranscriptionError::AudioDataError(e);
+                    if let Err(json_error) =
+                        send_error_result(&error, &session.config, &session.output)
+                    {
+                        eprintln!("JSON output error for result: {}", json_error);
+                    }
                     continue;
                 }
 
@@ -317,8 +1867,8 @@ async fn process_audio_stream(server_state: &ServerState) -> Result<(), String>
                 let total_bytes = audio_buffer.total_bytes_received();
                 debug!("Buffer contains {} bytes", total_bytes);
 
-                // Check if buffer is ready and process audio data
-                if audio_buffer.is_ready() {
+                // Transcribe buffered audio until the buffer is drained
+                while audio_buffer.is_ready() {
                     debug!("Audio buffer ready for transcription");
 
                     // Take audio data for transcription
@@ -328,15 +1878,20 @@ async fn process_audio_stream(server_state: &ServerState) -> Result<(), String>
                             audio_data.data.len()
                         );
 
-                        // Perform transcription using the transcription service
+                        // Perform transcription on the worker that owns the model
                         debug!("Starting transcription process");
-                        match server_state
-                            .transcription_service
-                            .transcribe(&audio_data.data)
+                        match session
+                            .queue
+                            .transcribe(audio_data, received_at, emitter.clone())
+                            .await
                         {
                             Ok(result) => {
                                 debug!("Transcription completed successfully");
                                 debug!("Transcribed text: {}", result.text);
+                                stats.record_result(&result);
+                                if let Some(recent) = &mut recent {
+                                    recent.record(&result);
+                                }
 
                                 if let Some(language) = &result.language {
                                     debug!("Detected language: {}", language);
@@ -346,9 +1901,27 @@ async fn process_audio_stream(server_state: &ServerState) -> Result<(), String>
                                     debug!("Transcription took {} ms", duration_ms);
                                 }
 
+                                // Warn before the result if the input gain is too high
+                                if let Some(warning) = ClippingWarning::for_result(&result) {
+                                    match serde_json::to_string(&warning) {
+                                        Ok(json) => {
+                                            if let Err(e) = session.events.write_line(&json) {
+                                                warn!("Failed to send clipping warning: {}", e);
+                                            }
+                                        }
+                                        Err(e) => {
+                                            warn!("Failed to serialize clipping warning: {}", e)
+                                        }
+                                    }
+                                }
+
                                 debug!("Formatting transcription result as JSON for output");
                                 // Format and send result to stdout as JSON
-                                match send_transcription_result_json(&result) {
+                                match send_transcription_result_json(
+                                    &result,
+                                    &session.config,
+                                    &session.output,
+                                ) {
                                     Ok(_) => {
                                         debug!(
                                             "Transcription result successfully sent to stdout as JSON"
@@ -366,21 +1939,20 @@ async fn process_audio_stream(server_state: &ServerState) -> Result<(), String>
                             }
                             Err(e) => {
                                 error!("Transcription failed: {}", e);
-                                // Log error to stderr
-                                eprintln!("Transcription error: {}", e);
 
-                                // Send error result as JSON
+                                // Send error result to the configured error streams
                                 debug!("Creating error result for JSON output");
-                                let error_result = transcription::TranscriptionResult {
-                                    text: String::new(),
-                                    language: None,
-                                    segments: None,
-                                    success: false,
-                                    error: Some(e.to_string()),
-                                    duration_ms: None,
-                                };
-
-                                match send_transcription_result_json(&error_result) {
+                                let error_result = transcription::TranscriptionResult::from(&e);
+                                stats.record_result(&error_result);
+                                if let Some(recent) = &mut recent {
+                                    recent.record(&error_result);
+                                }
+
+                                match send_transcription_result_json(
+                                    &error_result,
+                                    &session.config,
+                                    &session.output,
+                                ) {
                                     Ok(_) => {
                                         debug!("Error result successfully sent to stdout as JSON");
                                     }
@@ -403,47 +1975,137 @@ async fn process_audio_stream(server_state: &ServerState) -> Result<(), String>
             }
             Err(e) => {
                 error!("Error reading JSON audio data: {}", e);
-                // Log error to stderr
-                eprintln!("JSON audio data read error: {}", e);
+                stats.record_rejected();
+                // Report the rejected request to the configured error streams
+                let error = TranscriptionError::from(e);
+                if let Err(json_error) = send_error_result(&error, &session.config, &session.output)
+                {
+                    eprintln!("JSON output error for result: {}", json_error);
+                }
                 continue;
             }
         }
     }
 
     debug!("JSON audio processing completed");
+    info!(
+        "Session finished: {} requests ({} failed)",
+        stats.total_requests, stats.failed_requests
+    );
+    send_session_summary(&stats, &session.events)?;
     Ok(())
 }
 
+/// Exit code for invalid command-line arguments
+pub const EXIT_USAGE: i32 = 2;
+
+/// Exit code for a model or server that failed to initialize
+pub const EXIT_MODEL: i32 = 3;
+
+/// Exit code for a failure while serving requests
+pub const EXIT_RUNTIME: i32 = 4;
+
+/// Errors that stop the server
+#[derive(Debug)]
+pub enum AppError {
+    /// The command-line arguments were invalid
+    Usage(String),
+    /// Loading the model or building the transcription service failed
+    Initialization(String),
+    /// Reading requests or writing results failed
+    Processing(String),
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Usage(e) => write!(f, "{}", e),
+            AppError::Initialization(e) => write!(f, "Failed to initialize server: {}", e),
+            AppError::Processing(e) => write!(f, "Audio processing failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl AppError {
+    /// Process exit code reported for the error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Usage(_) => EXIT_USAGE,
+            AppError::Initialization(_) => EXIT_MODEL,
+            AppError::Processing(_) => EXIT_RUNTIME,
+        }
+    }
+}
+
+/// Run the server until its input is closed
+///
+/// # Arguments
+/// * `config` - Parsed server configuration
+///
+/// # Returns
+/// * `Result<(), AppError>` - Ok once the session finished, error if startup or processing failed
+async fn run(config: Config) -> Result<(), AppError> {
+    eprintln!("Configuration loaded successfully:");
+    eprintln!("  Model path: {}", config.model_path);
+    eprintln!("  Threads: {:?}", config.threads);
+    eprintln!("  CPU only: {}", config.cpu_only);
+
+    // Apply scheduling priority on this thread before the transcription worker is spawned
+    if let Err(e) = environment::apply_process_priority(&config) {
+        warn!("Failed to apply process priority: {}", e);
+    }
+
+    // Initialize server with configuration
+    let server_state = initialize_server(config)
+        .await
+        .map_err(AppError::Initialization)?;
+    info!("Server initialized successfully, ready for audio processing");
+
+    // Hand the model to the worker that runs every transcription
+    let session = Session {
+        config: server_state.config.clone(),
+        events: server_state.events.clone(),
+        output: EventSink::Stdout,
+        remote: false,
+        queue: worker::spawn(server_state)
+            .map_err(|e| AppError::Initialization(format!("Failed to start worker: {}", e)))?,
+    };
+
+    // Serve socket connections instead of stdin if requested
+    #[cfg(all(unix, feature = "socket"))]
+    if let Some(listener) = socket::bind_configured(&session.config)
+        .await
+        .map_err(AppError::Processing)?
+    {
+        return socket::serve(session, listener)
+            .await
+            .map_err(AppError::Processing);
+    }
+
+    // Start audio processing
+    let input = open_input(&session.config)
+        .await
+        .map_err(AppError::Processing)?;
+    process_audio_stream(&session, input)
+        .await
+        .map_err(AppError::Processing)
+}
+
 #[tokio::main]
 async fn main() {
-    // Initialize logging first
-    logging::configure_logging();
-    info!("Starting Whisper Background Server");
-
-    // Parse command line arguments
-    match parse_arguments(env::args()) {
+    // Parse command line arguments first, since they choose the log format
+    let exit_code = match parse_arguments(env::args()).map_err(AppError::Usage) {
         Ok(config) => {
-            eprintln!("Configuration loaded successfully:");
-            eprintln!("  Model path: {}", config.model_path);
-            eprintln!("  Threads: {:?}", config.threads);
-            eprintln!("  CPU only: {}", config.cpu_only);
-
-            // Initialize server with configuration
-            match initialize_server(config).await {
-                Ok(server_state) => {
-                    info!("Server initialized successfully, ready for audio processing");
-
-                    // Start audio processing
-                    if let Err(e) = process_audio_stream(&server_state).await {
-                        error!("Audio processing failed: {}", e);
-                        eprintln!("Error: {}", e);
-                        process::exit(1);
-                    }
-                }
+            logging::configure_logging(config.log_format);
+            info!("Starting Whisper Background Server");
+            match run(config).await {
+                Ok(()) => 0,
                 Err(e) => {
-                    error!("Failed to initialize server: {}", e);
+                    error!("{}", e);
                     eprintln!("Error: {}", e);
-                    process::exit(1);
+                    e.exit_code()
                 }
             }
         }
@@ -452,9 +2114,13 @@ async fn main() {
             eprintln!(
                 "Usage: whisper-background-server <model-path> [--threads <number>] [--cpu-only]"
             );
-            process::exit(1);
+            e.exit_code()
         }
-    }
+    };
+
+    // Make sure buffered log records reach stderr before exiting
+    log::logger().flush();
+    process::exit(exit_code);
 }
 
 #[cfg(test)]
@@ -527,6 +2193,7 @@ mod tests {
             error: None,
             duration_ms: Some(1000),
             timestamp: Some("1234567890".to_string()),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&output).unwrap();
@@ -549,12 +2216,14 @@ mod tests {
                 end: 1.0,
                 text: "Hello".to_string(),
                 confidence: Some(0.95),
+                likely_new_speaker: None,
             },
             transcription::TranscriptionSegment {
                 start: 1.0,
                 end: 2.0,
                 text: "world".to_string(),
                 confidence: Some(0.90),
+                likely_new_speaker: None,
             },
         ];
 
@@ -566,6 +2235,7 @@ mod tests {
             error: None,
             duration_ms: Some(1000),
             timestamp: Some("1234567890".to_string()),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&output).unwrap();
@@ -590,6 +2260,7 @@ mod tests {
             error: Some("Transcription failed".to_string()),
             duration_ms: None,
             timestamp: Some("1234567890".to_string()),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&output).unwrap();
@@ -600,6 +2271,31 @@ mod tests {
         assert_eq!(deserialized.text, String::new());
     }
 
+    #[test]
+    fn test_no_speech_result_counts_as_success() {
+        let result = transcription::TranscriptionResult {
+            success: true,
+            duration_ms: Some(1),
+            no_speech: Some(true),
+            reason: Some(transcription::NO_SPEECH_DETECTED.to_string()),
+            ..Default::default()
+        };
+
+        let json: serde_json::Value =
+            serde_json::to_value(transcription_output(&result, &Config::default())).unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["no_speech"], true);
+        assert_eq!(json["reason"], "NO_SPEECH_DETECTED");
+        assert_eq!(json["text"], "");
+        assert!(json.get("error_code").is_none());
+
+        let mut stats = SessionStats::default();
+        stats.record_result(&result);
+        let summary = stats.summary();
+        assert_eq!(summary.successful_requests, 1);
+        assert_eq!(summary.failed_requests, 0);
+    }
+
     #[test]
     fn test_model_attributes_serialization() {
         let attributes = ModelAttributes {
@@ -649,4 +2345,1016 @@ mod tests {
         assert_eq!(deserialized.cpu_only, false);
         assert_eq!(deserialized.audio_format, "16kHz mono PCM".to_string());
     }
+
+    #[test]
+    fn test_session_summary_counts() {
+        let mut stats = SessionStats::default();
+        let success = |duration_ms| transcription::TranscriptionResult {
+            text: "Hello".to_string(),
+            language: None,
+            segments: None,
+            success: true,
+            error: None,
+            duration_ms: Some(duration_ms),
+            ..Default::default()
+        };
+
+        stats.record_result(&success(100));
+        stats.record_result(&success(300));
+        stats.record_result(&transcription::TranscriptionResult {
+            text: String::new(),
+            language: None,
+            segments: None,
+            success: false,
+            error: Some("Transcription failed".to_string()),
+            duration_ms: None,
+            ..Default::default()
+        });
+        stats.record_rejected();
+        stats.record_duplicate();
+
+        let summary = stats.summary();
+        assert_eq!(summary.event, "summary");
+        assert_eq!(summary.total_requests, 5);
+        assert_eq!(summary.successful_requests, 2);
+        assert_eq!(summary.failed_requests, 2);
+        assert_eq!(summary.avg_transcription_ms, Some(200.0));
+        assert_eq!(summary.duplicate_chunks, 1);
+
+        let json: serde_json::Value = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["event"], "summary");
+        assert_eq!(json["total_requests"], 5);
+        assert_eq!(json["duplicate_chunks"], 1);
+    }
+
+    #[test]
+    fn test_session_summary_empty_session() {
+        let summary = SessionStats::default().summary();
+        assert_eq!(summary.total_requests, 0);
+        assert_eq!(summary.avg_transcription_ms, None);
+    }
+
+    #[test]
+    fn test_heartbeat_due_only_when_idle() {
+        let tracker = ActivityTracker::new();
+        let interval = Duration::from_secs(5);
+        let start = *tracker.last_activity.lock().unwrap();
+
+        assert!(!tracker.idle_for(start + Duration::from_secs(4), interval));
+        assert!(tracker.idle_for(start + Duration::from_secs(5), interval));
+
+        tracker.start_work();
+        assert!(!tracker.idle_for(start + Duration::from_secs(60), interval));
+    }
+
+    #[test]
+    fn test_heartbeat_event_serialization() {
+        let tracker = ActivityTracker::new();
+        let event = tracker.heartbeat_event(tracker.started + Duration::from_secs(42));
+
+        let json: serde_json::Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "heartbeat");
+        assert_eq!(json["uptime_secs"], 42);
+    }
+
+    #[test]
+    fn test_list_output_formats_covers_every_format() {
+        let command = commands::parse_command(r#"{"command":"list_output_formats"}"#).unwrap();
+        let response: OutputFormatsResponse =
+            serde_json::from_str(&handle_command(&command, &AudioBuffer::new(), None).unwrap())
+                .unwrap();
+
+        assert_eq!(response.event, "output_formats");
+        assert_eq!(
+            response.formats,
+            vec!["json", "text", "openai", "whisper-cpp"]
+        );
+
+        // Every listed format must be renderable
+        let output = TranscriptionOutput {
+            text: "Hello world".to_string(),
+            success: true,
+            ..Default::default()
+        };
+        for id in &response.formats {
+            let format: OutputFormat = id.parse().unwrap();
+            assert!(render_output(&output, format, None).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_render_output_text() {
+        let output = TranscriptionOutput {
+            text: "Hello world".to_string(),
+            success: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_output(&output, OutputFormat::Text, None).unwrap(),
+            "Hello world"
+        );
+
+        let failed = TranscriptionOutput {
+            success: false,
+            error: Some("Audio data is empty".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_output(&failed, OutputFormat::Text, None).unwrap(),
+            "error: Audio data is empty"
+        );
+    }
+
+    #[test]
+    fn test_write_error_line_routing() {
+        let cases = [
+            (ErrorStream::Stdout, true, false),
+            (ErrorStream::Stderr, false, true),
+            (ErrorStream::Both, true, true),
+        ];
+        for (routing, expect_stdout, expect_stderr) in cases {
+            let mut stdout = Vec::new();
+            let mut stderr = Vec::new();
+            write_error_line(routing, r#"{"success":false}"#, &mut stdout, &mut stderr).unwrap();
+
+            // Lines are stamped with an output sequence number ahead of the payload
+            let expected = ",\"success\":false}\n";
+            assert_eq!(
+                stdout.ends_with(expected.as_bytes()),
+                expect_stdout,
+                "{:?}",
+                routing
+            );
+            assert_eq!(
+                stderr.ends_with(expected.as_bytes()),
+                expect_stderr,
+                "{:?}",
+                routing
+            );
+            assert_eq!(stdout.is_empty(), !expect_stdout);
+            assert_eq!(stderr.is_empty(), !expect_stderr);
+            if routing == ErrorStream::Both {
+                assert_eq!(stdout, stderr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_clipping_warning_threshold() {
+        let mut input_audio =
+            audio::InputAudioInfo::new(audio::AudioFormatInfo::default(), audio::PCM_S16LE);
+        input_audio.clipping_ratio = 0.25;
+        let clipped = transcription::TranscriptionResult {
+            success: true,
+            input_audio: Some(input_audio.clone()),
+            ..Default::default()
+        };
+        let warning = ClippingWarning::for_result(&clipped).unwrap();
+        assert_eq!(warning.code, "CLIPPING");
+        assert_eq!(warning.clipping_ratio, 0.25);
+
+        input_audio.clipping_ratio = 0.0;
+        let clean = transcription::TranscriptionResult {
+            success: true,
+            input_audio: Some(input_audio),
+            ..Default::default()
+        };
+        assert!(ClippingWarning::for_result(&clean).is_none());
+    }
+
+    #[test]
+    fn test_render_output_field_allowlist() {
+        let output = TranscriptionOutput {
+            text: "Hello world".to_string(),
+            language: Some("en".to_string()),
+            success: true,
+            duration_ms: Some(1000),
+            timestamp: Some("1234567890".to_string()),
+            ..Default::default()
+        };
+        let fields = vec!["text".to_string(), "language".to_string()];
+
+        let json = render_output(&output, OutputFormat::Json, Some(&fields)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.len(), 2);
+        assert_eq!(object["text"], "Hello world");
+        assert_eq!(object["language"], "en");
+        assert!(!object.contains_key("success"));
+        assert!(!object.contains_key("timestamp"));
+    }
+
+    #[test]
+    fn test_context_parameters_apply_flash_attn_on_gpu() {
+        let config = Config {
+            flash_attn: true,
+            ..Default::default()
+        };
+        let (params, ignored) = context_parameters(&config);
+        assert!(params.use_gpu);
+        assert!(params.flash_attn);
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn test_context_parameters_ignore_gpu_flags_when_cpu_only() {
+        let config = Config {
+            cpu_only: true,
+            flash_attn: true,
+            ..Default::default()
+        };
+        let (params, ignored) = context_parameters(&config);
+        assert!(!params.use_gpu);
+        assert!(!params.flash_attn);
+        assert_eq!(ignored, vec!["--flash-attn"]);
+    }
+
+    #[test]
+    fn test_dtw_model_preset_mapping() {
+        for name in environment::DTW_PRESETS {
+            assert!(dtw_model_preset(name).is_some(), "unmapped preset {}", name);
+        }
+        assert!(matches!(
+            dtw_model_preset("base.en"),
+            Some(DtwModelPreset::BaseEn)
+        ));
+        assert!(matches!(
+            dtw_model_preset("large.v3.turbo"),
+            Some(DtwModelPreset::LargeV3Turbo)
+        ));
+        assert!(dtw_model_preset("huge").is_none());
+
+        let config = Config {
+            dtw_preset: Some("small".to_string()),
+            ..Default::default()
+        };
+        let (params, _) = context_parameters(&config);
+        assert!(matches!(
+            params.dtw_parameters.mode,
+            DtwMode::ModelPreset {
+                model_preset: DtwModelPreset::Small
+            }
+        ));
+    }
+
+    #[test]
+    fn test_validate_dtw_preset() {
+        assert!(validate_dtw_preset("base", "base", true).is_ok());
+        assert!(validate_dtw_preset("base.en", "base", false).is_ok());
+        assert!(validate_dtw_preset("large.v3", "large", true).is_ok());
+        assert!(validate_dtw_preset("base.en", "base", true).is_err());
+        assert!(validate_dtw_preset("tiny", "base", true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_with_missing_model_returns_error() {
+        let config = Config {
+            model_path: "/nonexistent/ggml-missing.bin".to_string(),
+            ..Default::default()
+        };
+        let error = run(config).await.unwrap_err();
+        assert!(matches!(error, AppError::Initialization(_)));
+        assert!(error.to_string().contains("/nonexistent/ggml-missing.bin"));
+        assert_eq!(error.exit_code(), EXIT_MODEL);
+    }
+
+    #[test]
+    fn test_exit_code_per_error_category() {
+        let cases = [
+            (AppError::Usage("bad flag".to_string()), 2),
+            (AppError::Initialization("no model".to_string()), 3),
+            (AppError::Processing("broken pipe".to_string()), 4),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_buffer_status_command() {
+        let mut audio_buffer = AudioBuffer::new();
+        audio_buffer.set_audio_data(audio::AudioData {
+            data: vec![0; 64000],
+            timestamp: Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: audio::InputAudioInfo::default(),
+        });
+
+        let command = commands::parse_command(r#"{"command":"buffer_status"}"#).unwrap();
+        let response: serde_json::Value =
+            serde_json::from_str(&handle_command(&command, &audio_buffer, None).unwrap()).unwrap();
+
+        assert_eq!(response["event"], "buffer_status");
+        assert_eq!(response["total_bytes_received"], 64000);
+        assert_eq!(response["buffered_bytes"], 64000);
+        assert_eq!(response["is_ready"], true);
+        assert_eq!(response["estimated_seconds"], 2.0);
+    }
+
+    #[test]
+    fn test_recent_results_command_returns_newest_first() {
+        let mut recent = recent::RecentResults::new(2);
+        for text in ["first", "second", "third"] {
+            recent.record(&transcription::TranscriptionResult {
+                text: text.to_string(),
+                success: true,
+                ..Default::default()
+            });
+        }
+
+        let command = commands::parse_command(r#"{"command":"recent_results"}"#).unwrap();
+        let response: serde_json::Value = serde_json::from_str(
+            &handle_command(&command, &AudioBuffer::new(), Some(&recent)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(response["event"], "recent_results");
+        let texts: Vec<_> = response["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["third", "second"]);
+
+        let command = commands::parse_command(r#"{"command":"recent_results","limit":1}"#).unwrap();
+        let response: serde_json::Value = serde_json::from_str(
+            &handle_command(&command, &AudioBuffer::new(), Some(&recent)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(response["results"].as_array().unwrap().len(), 1);
+        assert_eq!(response["results"][0]["text"], "third");
+
+        let error = handle_command(&command, &AudioBuffer::new(), None).unwrap_err();
+        assert!(error.contains("--keep-recent"));
+    }
+
+    #[test]
+    fn test_deterministic_output_is_byte_identical() {
+        let config = Config {
+            deterministic: true,
+            ..Default::default()
+        };
+        // Stub transcription: same text for the same buffer, but timing varies per run
+        let runs = std::cell::Cell::new(0);
+        let transcribe = |buffer: &[u8]| {
+            runs.set(runs.get() + 1);
+            transcription::TranscriptionResult {
+                text: format!("{} bytes", buffer.len()),
+                success: true,
+                duration_ms: Some(100 + runs.get()),
+                ..Default::default()
+            }
+        };
+        let buffer = vec![0u8; 3200];
+
+        let first = transcription_output(&transcribe(&buffer), &config);
+        std::thread::sleep(Duration::from_millis(5));
+        let second = transcription_output(&transcribe(&buffer), &config);
+
+        let first = render_output(&first, OutputFormat::Json, None).unwrap();
+        let second = render_output(&second, OutputFormat::Json, None).unwrap();
+        assert_eq!(first, second);
+        let value: serde_json::Value = serde_json::from_str(&first).unwrap();
+        assert!(value["timestamp"].is_null());
+        assert!(value["duration_ms"].is_null());
+    }
+
+    /// Server state on the model named by `WHISPER_TEST_MODEL`
+    ///
+    /// Tests that need a real model are skipped when the variable is unset.
+    fn test_model_state() -> Option<ServerState> {
+        let Ok(model_path) = std::env::var("WHISPER_TEST_MODEL") else {
+            eprintln!("WHISPER_TEST_MODEL is not set, skipping");
+            return None;
+        };
+        let config = Config {
+            model_path,
+            ..Default::default()
+        };
+        let context = load_context(&config).unwrap();
+        Some(ServerState {
+            transcription_service: TranscriptionService::new(
+                context,
+                TranscriptionConfig::default(),
+            )
+            .unwrap(),
+            config,
+            events: EventSink::Stderr,
+        })
+    }
+
+    #[test]
+    fn test_failed_swap_keeps_the_loaded_model() {
+        let Some(mut state) = test_model_state() else {
+            return;
+        };
+        let model_path = state.config.model_path.clone();
+        let before = serde_json::to_value(server_info(&state).unwrap()).unwrap();
+
+        let error = swap_model(&mut state, "/nonexistent/ggml-small.bin")
+            .err()
+            .unwrap();
+        assert!(error.contains("/nonexistent/ggml-small.bin"));
+
+        // The previous model is still configured, reported and usable
+        assert_eq!(state.config.model_path, model_path);
+        let after = serde_json::to_value(server_info(&state).unwrap()).unwrap();
+        assert_eq!(after, before);
+        let silence = vec![0; audio::ms_to_samples(1000) * 2];
+        assert!(state.transcription_service.transcribe(&silence).is_ok());
+    }
+
+    #[test]
+    fn test_successful_swap_updates_server_info() {
+        let Some(mut state) = test_model_state() else {
+            return;
+        };
+        let replacement =
+            std::env::temp_dir().join(format!("ggml-swap-test-{}.bin", std::process::id()));
+        std::fs::copy(&state.config.model_path, &replacement).unwrap();
+        let replacement_path = replacement.to_str().unwrap().to_string();
+
+        let info = swap_model(&mut state, &replacement_path).unwrap();
+        let expected_name = format!("ggml-swap-test-{}", std::process::id());
+        assert_eq!(info.model_name, expected_name);
+        assert_eq!(state.config.model_path, replacement_path);
+        assert_eq!(server_info(&state).unwrap().model_name, expected_name);
+
+        let silence = vec![0; audio::ms_to_samples(1000) * 2];
+        assert!(state.transcription_service.transcribe(&silence).is_ok());
+        std::fs::remove_file(&replacement).unwrap();
+    }
+
+    #[test]
+    fn test_load_context_rejects_missing_replacement_model() {
+        let config = Config {
+            model_path: "/nonexistent/ggml-small.bin".to_string(),
+            ..Default::default()
+        };
+        let error = load_context(&config).unwrap_err();
+        assert!(error.contains("/nonexistent/ggml-small.bin"));
+    }
+
+    #[tokio::test]
+    async fn test_held_chunks_are_transcribed_when_the_gap_times_out() {
+        use tokio::io::AsyncWriteExt;
+
+        // Worker answering each transcription with the number of bytes it was given
+        let (queue, mut jobs) = worker::WorkQueue::channel();
+        tokio::spawn(async move {
+            while let Some(job) = jobs.recv().await {
+                if let worker::Job::Transcribe { audio, reply, .. } = job {
+                    let _ = reply.send(Ok(transcription::TranscriptionResult {
+                        text: format!("{} bytes", audio.data.len()),
+                        success: true,
+                        ..Default::default()
+                    }));
+                }
+            }
+        });
+        let (lines, mut output) = mpsc::channel(16);
+        let session = Session {
+            config: Config {
+                reorder_timeout_ms: Some(50),
+                ..Default::default()
+            },
+            queue,
+            events: EventSink::Queued(lines.clone()),
+            output: EventSink::Queued(lines),
+            remote: false,
+        };
+        let (mut client, input) = tokio::io::duplex(1024);
+        let serving = tokio::spawn(async move {
+            process_audio_stream(&session, audio::InputReader::new(input)).await
+        });
+
+        // Chunk 0 never arrives and the client sends nothing after chunk 1
+        client
+            .write_all(b"{\"seq\":1,\"audio_data\":{\"data\":[1,0,2,0]}}\n")
+            .await
+            .unwrap();
+        let line = tokio::time::timeout(Duration::from_secs(5), output.recv())
+            .await
+            .expect("held chunk was not transcribed while the input stayed open")
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["text"], "4 bytes");
+
+        drop(client);
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_scheduled_only_when_idle() {
+        let tracker = Arc::new(ActivityTracker::new());
+        tracker.start_work();
+        // No worker is running, so the first scheduled keepalive stops the task
+        let (queue, jobs) = worker::WorkQueue::channel();
+        drop(jobs);
+        let keepalive = spawn_keepalive(Arc::clone(&tracker), queue, Duration::from_millis(10));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!keepalive.is_finished());
+
+        tracker.finish_work();
+        tokio::time::timeout(Duration::from_secs(1), keepalive)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_metrics_only_included_with_flag() {
+        let result = transcription::TranscriptionResult {
+            text: "hello".to_string(),
+            success: true,
+            metrics: Some(transcription::RequestMetrics {
+                decode_ms: 2,
+                inference_ms: 150,
+                queue_wait_ms: 5,
+                audio_duration_ms: 1000,
+            }),
+            ..Default::default()
+        };
+
+        let output = transcription_output(&result, &Config::default());
+        let json = render_output(&output, OutputFormat::Json, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value.get("metrics").is_none());
+
+        let config = Config {
+            include_metrics: true,
+            ..Default::default()
+        };
+        let output = transcription_output(&result, &config);
+        let json = render_output(&output, OutputFormat::Json, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["metrics"]["decode_ms"], 2);
+        assert_eq!(value["metrics"]["inference_ms"], 150);
+        assert_eq!(value["metrics"]["queue_wait_ms"], 5);
+        assert_eq!(value["metrics"]["audio_duration_ms"], 1000);
+    }
+
+    #[test]
+    fn test_low_confidence_marked_below_threshold() {
+        let result = |avg_logprob| transcription::TranscriptionResult {
+            text: "hello".to_string(),
+            success: true,
+            avg_logprob: Some(avg_logprob),
+            ..Default::default()
+        };
+
+        let output = transcription_output(&result(-1.5), &Config::default());
+        assert_eq!(output.avg_logprob, Some(-1.5));
+        assert_eq!(output.low_confidence, None);
+
+        let config = Config {
+            min_avg_logprob: Some(-1.0),
+            ..Default::default()
+        };
+        let output = transcription_output(&result(-1.5), &config);
+        assert_eq!(output.low_confidence, Some(true));
+        let output = transcription_output(&result(-0.4), &config);
+        assert_eq!(output.low_confidence, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_burst_over_rate_rejected_under_reject_policy() {
+        let mut limiter = TokenBucket::new(2.0, Instant::now());
+
+        let mut admitted = Vec::new();
+        for _ in 0..5 {
+            admitted.push(admit_request(&mut limiter, RateLimitPolicy::Reject).await);
+        }
+        assert_eq!(admitted, vec![true, true, false, false, false]);
+    }
+
+    #[tokio::test]
+    async fn test_burst_over_rate_waits_under_block_policy() {
+        let mut limiter = TokenBucket::new(100.0, Instant::now());
+        let start = Instant::now();
+
+        for _ in 0..101 {
+            assert!(admit_request(&mut limiter, RateLimitPolicy::Block).await);
+        }
+        // The request beyond the burst waited for the next token
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_session_summary_excludes_cold_start() {
+        let success = |duration_ms| transcription::TranscriptionResult {
+            text: "Hello".to_string(),
+            success: true,
+            duration_ms: Some(duration_ms),
+            ..Default::default()
+        };
+
+        let mut stats = SessionStats::new(1, &histogram::DEFAULT_LATENCY_BUCKETS_MS);
+        stats.record_result(&success(2000));
+        // Only cold-start data so far, so there is no steady-state average
+        assert_eq!(stats.summary().avg_transcription_ms, None);
+
+        stats.record_result(&success(100));
+        stats.record_result(&success(300));
+        let summary = stats.summary();
+        assert_eq!(summary.successful_requests, 3);
+        assert_eq!(summary.avg_transcription_ms, Some(200.0));
+        assert_eq!(summary.cold_start_ms, vec![2000]);
+
+        let json = serde_json::to_value(SessionStats::default().summary()).unwrap();
+        assert!(json.get("cold_start_ms").is_none());
+    }
+
+    #[test]
+    fn test_session_summary_histogram() {
+        let success = |duration_ms| transcription::TranscriptionResult {
+            text: "Hello".to_string(),
+            success: true,
+            duration_ms: Some(duration_ms),
+            ..Default::default()
+        };
+
+        let mut stats = SessionStats::new(0, &[200, 1000]);
+        for duration_ms in [150, 250, 900, 4000] {
+            stats.record_result(&success(duration_ms));
+        }
+
+        let json = serde_json::to_value(stats.summary()).unwrap();
+        assert_eq!(
+            json["transcription_ms_histogram"],
+            serde_json::json!([
+                {"le": 200, "count": 1},
+                {"le": 1000, "count": 2},
+                {"le": null, "count": 1}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_openai_output_matches_verbose_shape() {
+        let result = transcription::TranscriptionResult {
+            text: "Hello world. Bye.".to_string(),
+            language: Some("en".to_string()),
+            segments: Some(vec![
+                transcription::TranscriptionSegment {
+                    start: 0.0,
+                    end: 1.5,
+                    text: "Hello world.".to_string(),
+                    confidence: None,
+                    likely_new_speaker: None,
+                },
+                transcription::TranscriptionSegment {
+                    start: 1.5,
+                    end: 2.0,
+                    text: "Bye.".to_string(),
+                    confidence: None,
+                    likely_new_speaker: None,
+                },
+            ]),
+            success: true,
+            avg_logprob: Some(-0.25),
+            metrics: Some(transcription::RequestMetrics {
+                audio_duration_ms: 2500,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let output = transcription_output(&result, &Config::default());
+        let json = render_output(&output, OutputFormat::OpenAi, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "task": "transcribe",
+                "language": "english",
+                "duration": 2.5,
+                "text": "Hello world. Bye.",
+                "segments": [
+                    {
+                        "id": 0,
+                        "seek": 0,
+                        "start": 0.0,
+                        "end": 1.5,
+                        "text": "Hello world.",
+                        "tokens": [],
+                        "temperature": 0.0,
+                        "avg_logprob": -0.25
+                    },
+                    {
+                        "id": 1,
+                        "seek": 0,
+                        "start": 1.5,
+                        "end": 2.0,
+                        "text": "Bye.",
+                        "tokens": [],
+                        "temperature": 0.0,
+                        "avg_logprob": -0.25
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_openai_output_error_shape() {
+        let error = TranscriptionError::AudioDataError("Audio data is empty".to_string());
+        let output = transcription_output(
+            &transcription::TranscriptionResult::from(&error),
+            &Config::default(),
+        );
+        let json = render_output(&output, OutputFormat::OpenAi, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["error"]["type"], "server_error");
+        assert_eq!(value["error"]["code"], "AUDIO_DATA_ERROR");
+        assert!(value["error"]["message"].is_string());
+        assert!(value.get("text").is_none());
+    }
+
+    #[test]
+    fn test_whisper_cpp_output_shape() {
+        let output = TranscriptionOutput {
+            text: "Hello world. Bye.".to_string(),
+            language: Some("en".to_string()),
+            segments: Some(vec![
+                transcription::TranscriptionSegment {
+                    start: 0.0,
+                    end: 1.5,
+                    text: "Hello world.".to_string(),
+                    confidence: None,
+                    likely_new_speaker: None,
+                },
+                transcription::TranscriptionSegment {
+                    start: 61.25,
+                    end: 3723.004,
+                    text: "Bye.".to_string(),
+                    confidence: None,
+                    likely_new_speaker: None,
+                },
+            ]),
+            success: true,
+            ..Default::default()
+        };
+
+        let json = render_output(&output, OutputFormat::WhisperCpp, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["result"]["language"], "en");
+        assert_eq!(
+            value["transcription"],
+            serde_json::json!([
+                {
+                    "timestamps": {"from": "00:00:00,000", "to": "00:00:01,500"},
+                    "offsets": {"from": 0, "to": 1500},
+                    "text": "Hello world."
+                },
+                {
+                    "timestamps": {"from": "00:01:01,250", "to": "01:02:03,004"},
+                    "offsets": {"from": 61250, "to": 3723004},
+                    "text": "Bye."
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unknown_command_error_event() {
+        let command = commands::parse_command(r#"{"command":"foo"}"#).unwrap();
+        let error = handle_command(&command, &AudioBuffer::new(), None).unwrap_err();
+
+        let json = serde_json::to_value(CommandErrorEvent::new(&command.command, error)).unwrap();
+        assert_eq!(json["event"], "error");
+        assert_eq!(json["error_code"], "UNKNOWN_COMMAND");
+        assert_eq!(json["command"], "foo");
+        assert_eq!(
+            json["known_commands"],
+            serde_json::json!([
+                "list_output_formats",
+                "buffer_status",
+                "load_model",
+                "recent_results"
+            ])
+        );
+
+        // Known commands that fail carry no error code
+        let json =
+            serde_json::to_value(CommandErrorEvent::new("load_model", "bad path".to_string()))
+                .unwrap();
+        assert!(json.get("error_code").is_none());
+        assert!(json.get("known_commands").is_none());
+    }
+
+    #[test]
+    fn test_events_written_to_configured_sink() {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let events = EventSink::Writer(buffer.clone());
+
+        let tracker = ActivityTracker::new();
+        let heartbeat = tracker.heartbeat_event(tracker.started + Duration::from_secs(5));
+        events
+            .write_line(&serde_json::to_string(&heartbeat).unwrap())
+            .unwrap();
+        send_session_summary(&SessionStats::default(), &events).unwrap();
+
+        let written = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "heartbeat");
+        assert_eq!(lines[1]["event"], "summary");
+
+        assert!(matches!(
+            EventSink::open(EventStream::Stderr),
+            Ok(EventSink::Stderr)
+        ));
+        assert!(matches!(
+            EventSink::open(EventStream::Stdout),
+            Ok(EventSink::Stdout)
+        ));
+    }
+
+    #[test]
+    fn test_queued_sink_refuses_lines_once_full() {
+        let (lines, mut queued) = mpsc::channel(2);
+        let output = EventSink::Queued(lines);
+
+        output.write_line(r#"{"event":"heartbeat"}"#).unwrap();
+        // Error lines are split into lines when flushed
+        output
+            .with_writer(|out| write_error_line(ErrorStream::Stdout, "{}", out, &mut io::sink()))
+            .unwrap();
+        let error = output.write_line("{}").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+
+        assert!(queued.try_recv().unwrap().contains("heartbeat"));
+        assert!(queued.try_recv().unwrap().starts_with(r#"{"seq":"#));
+
+        drop(queued);
+        let error = output.write_line("{}").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_output_lines_have_increasing_seq() {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let events = EventSink::Writer(buffer.clone());
+        for line in [r#"{"event":"heartbeat"}"#, "{}", r#"{"event":"summary"}"#] {
+            events.write_line(line).unwrap();
+        }
+
+        let written = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let seqs: Vec<u64> = lines
+            .iter()
+            .map(|line| line["seq"].as_u64().unwrap())
+            .collect();
+        assert_eq!(seqs.len(), 3);
+        assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(lines[0]["event"], "heartbeat");
+        assert_eq!(lines[2]["event"], "summary");
+
+        // The seq field comes first and the rest of the line is untouched
+        assert!(written.starts_with(r#"{"seq":"#));
+        assert!(
+            written
+                .lines()
+                .next()
+                .unwrap()
+                .ends_with(r#","event":"heartbeat"}"#)
+        );
+        // Plain text results are not JSON and are left as they are
+        assert_eq!(stamp_output_seq("hello world"), "hello world");
+    }
+
+    #[test]
+    fn test_duration_warning_event() {
+        let warning = DurationWarning::from(audio::DurationMismatch {
+            declared_ms: 2500,
+            computed_ms: 1000,
+        });
+        let json: serde_json::Value = serde_json::to_value(&warning).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "event": "warning",
+                "code": "DURATION_MISMATCH",
+                "declared_ms": 2500,
+                "computed_ms": 1000
+            })
+        );
+
+        let error = TranscriptionError::DurationMismatch {
+            declared_ms: 2500,
+            computed_ms: 1000,
+        };
+        assert_eq!(error.error_code(), "DURATION_MISMATCH");
+    }
+
+    #[test]
+    fn test_detected_language_reaches_output() {
+        let config = Config::default();
+        for (lang_id, expected) in [
+            (6, Some("fr")),
+            (0, Some("en")),
+            (99, Some("yue")),
+            (999, None),
+        ] {
+            let result = transcription::TranscriptionResult {
+                text: "Bonjour".to_string(),
+                language: transcription::language_from_id(lang_id),
+                success: true,
+                ..Default::default()
+            };
+            let output = transcription_output(&result, &config);
+            assert_eq!(output.language.as_deref(), expected);
+
+            let json: serde_json::Value =
+                serde_json::from_str(&render_output(&output, OutputFormat::Json, None).unwrap())
+                    .unwrap();
+            assert_eq!(json["language"].as_str(), expected);
+        }
+    }
+
+    #[test]
+    fn test_transcribe_request_applies_per_request_options() {
+        let base = TranscriptionConfig::default();
+        let request = |language: &str| audio::AudioData {
+            data: vec![0; 320],
+            timestamp: Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: Some(transcription::TranscriptionOptions {
+                language: Some(language.to_string()),
+                ..Default::default()
+            }),
+            offset_ms: 0,
+            input_audio: audio::InputAudioInfo::default(),
+        };
+
+        // Record the language each transcription was asked to use
+        let mut languages = Vec::new();
+        for audio_data in [request("fr"), request("de")] {
+            transcribe_request(&base, &audio_data, |audio, config| {
+                assert_eq!(audio.data.len(), 320);
+                languages.push(config.language.clone());
+                Ok(transcription::TranscriptionResult::default())
+            })
+            .unwrap();
+        }
+        assert_eq!(
+            languages,
+            vec![Some("fr".to_string()), Some("de".to_string())]
+        );
+        assert_eq!(base.language, None);
+
+        // Invalid options are rejected before transcribing
+        let error = transcribe_request(&base, &request("xx"), |_, _| {
+            panic!("invalid options must not be transcribed")
+        })
+        .unwrap_err();
+        assert_eq!(error.error_code(), "INVALID_FIELD_VALUE");
+    }
+
+    #[test]
+    fn test_transcribe_request_places_split_pieces() {
+        let mut buffer = AudioBuffer::with_max_buffer_ms(100);
+        buffer.set_audio_data(audio::AudioData {
+            data: vec![0; audio::ms_to_samples(250) * 2],
+            timestamp: Instant::now(),
+            seq: None,
+            duration_ms: None,
+            options: None,
+            offset_ms: 0,
+            input_audio: audio::InputAudioInfo::default(),
+        });
+
+        // Every piece decodes a segment at 0.05-0.1s of its own audio
+        let mut starts = Vec::new();
+        while let Some(piece) = buffer.take_audio_data() {
+            let result = transcribe_request(&TranscriptionConfig::default(), &piece, |_, _| {
+                Ok(transcription::TranscriptionResult {
+                    segments: Some(vec![transcription::TranscriptionSegment {
+                        start: 0.05,
+                        end: 0.1,
+                        text: "tick".to_string(),
+                        confidence: None,
+                        likely_new_speaker: None,
+                    }]),
+                    ..Default::default()
+                })
+            })
+            .unwrap();
+            let segment = &result.segments.unwrap()[0];
+            starts.push((segment.start, segment.end));
+        }
+        assert_eq!(starts, vec![(0.05, 0.1), (0.15, 0.2), (0.25, 0.3)]);
+    }
 }
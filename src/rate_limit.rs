@@ -0,0 +1,115 @@
+//! Token-bucket admission control for incoming requests
+//!
+//! The bucket holds up to one second's worth of tokens (at least one) and
+//! refills continuously at the configured rate. Each admitted request spends
+//! one token; when the bucket is empty the caller is told how long to wait
+//! for the next token, and decides whether to wait or reject the request.
+
+use std::time::{Duration, Instant};
+
+/// Token bucket refilled at a fixed rate
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Maximum number of tokens held
+    burst: f64,
+    /// Tokens currently available
+    tokens: f64,
+    /// When the bucket was last refilled
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    ///
+    /// # Arguments
+    /// * `rate` - Requests admitted per second
+    /// * `now` - Current time
+    pub fn new(rate: f64, now: Instant) -> Self {
+        let burst = rate.max(1.0);
+        Self {
+            rate,
+            burst,
+            tokens: burst,
+            last_refill: now,
+        }
+    }
+
+    /// Spend a token if one is available
+    ///
+    /// # Arguments
+    /// * `now` - Current time
+    ///
+    /// # Returns
+    /// * `Result<(), Duration>` - Ok if admitted, otherwise the wait until a token is available
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_up_to_rate_is_admitted() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3.0, start);
+
+        for _ in 0..3 {
+            assert!(bucket.try_acquire(start).is_ok());
+        }
+        let wait = bucket.try_acquire(start).unwrap_err();
+        assert!((wait.as_secs_f64() - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tokens_refill_over_time() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, start);
+        assert!(bucket.try_acquire(start).is_ok());
+        assert!(bucket.try_acquire(start).is_ok());
+        assert!(bucket.try_acquire(start).is_err());
+
+        // Half a second refills one token at 2 requests per second
+        assert!(
+            bucket
+                .try_acquire(start + Duration::from_millis(500))
+                .is_ok()
+        );
+        assert!(
+            bucket
+                .try_acquire(start + Duration::from_millis(500))
+                .is_err()
+        );
+
+        // Refilling never exceeds the burst size
+        let later = start + Duration::from_secs(60);
+        assert!(bucket.try_acquire(later).is_ok());
+        assert!(bucket.try_acquire(later).is_ok());
+        assert!(bucket.try_acquire(later).is_err());
+    }
+
+    #[test]
+    fn test_fractional_rate_allows_single_request() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(0.5, start);
+        assert!(bucket.try_acquire(start).is_ok());
+        assert_eq!(
+            bucket.try_acquire(start).unwrap_err(),
+            Duration::from_secs(2)
+        );
+    }
+}
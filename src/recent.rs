@@ -0,0 +1,164 @@
+//! Ring buffer of recent transcription results
+//!
+//! With `--keep-recent <n>` the last `n` results of the session are kept so
+//! the `recent_results` command can replay them when debugging a client. Only
+//! the text and metadata are kept, never the audio; once the buffer is full
+//! the oldest result is dropped for each new one.
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::transcription::TranscriptionResult;
+
+/// Text and metadata of one transcription result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentResult {
+    /// The transcribed text
+    pub text: String,
+    /// Language detected (if available)
+    pub language: Option<String>,
+    /// Whether the transcription was completed successfully
+    pub success: bool,
+    /// Machine-readable error code if transcription failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// Time taken for transcription in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Duration of the input audio in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_duration_ms: Option<u64>,
+    /// Seconds since the Unix epoch when the result was produced
+    pub timestamp: u64,
+}
+
+impl RecentResult {
+    /// Keep the text and metadata of a result
+    ///
+    /// # Arguments
+    /// * `result` - Result to keep
+    /// * `timestamp` - Seconds since the Unix epoch when it was produced
+    pub fn new(result: &TranscriptionResult, timestamp: u64) -> Self {
+        Self {
+            text: result.text.clone(),
+            language: result.language.clone(),
+            success: result.success,
+            error_code: result.error_code.clone(),
+            duration_ms: result.duration_ms,
+            audio_duration_ms: result
+                .metrics
+                .as_ref()
+                .map(|metrics| metrics.audio_duration_ms),
+            timestamp,
+        }
+    }
+}
+
+/// Bounded buffer holding the most recent results
+#[derive(Debug, Clone)]
+pub struct RecentResults {
+    /// Most results kept
+    capacity: usize,
+    /// Kept results, oldest first
+    results: VecDeque<RecentResult>,
+}
+
+impl RecentResults {
+    /// Create an empty buffer
+    ///
+    /// # Arguments
+    /// * `capacity` - Most results kept
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            results: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Keep a result, dropping the oldest one if the buffer is full
+    ///
+    /// # Arguments
+    /// * `result` - Result to keep
+    pub fn push(&mut self, result: RecentResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.results.len() == self.capacity {
+            self.results.pop_front();
+        }
+        self.results.push_back(result);
+    }
+
+    /// Keep a result produced now
+    ///
+    /// # Arguments
+    /// * `result` - Result to keep
+    pub fn record(&mut self, result: &TranscriptionResult) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.push(RecentResult::new(result, timestamp));
+    }
+
+    /// Kept results, newest first
+    ///
+    /// # Arguments
+    /// * `limit` - Most results returned (all kept results when None)
+    pub fn newest(&self, limit: Option<usize>) -> Vec<RecentResult> {
+        self.results
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(text: &str) -> RecentResult {
+        RecentResult {
+            text: text.to_string(),
+            language: Some("en".to_string()),
+            success: true,
+            error_code: None,
+            duration_ms: Some(100),
+            audio_duration_ms: None,
+            timestamp: 0,
+        }
+    }
+
+    fn texts(results: &[RecentResult]) -> Vec<&str> {
+        results.iter().map(|result| result.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_keeps_newest_results_up_to_capacity() {
+        let mut recent = RecentResults::new(3);
+        for text in ["one", "two", "three", "four"] {
+            recent.push(result(text));
+        }
+
+        assert_eq!(texts(&recent.newest(None)), vec!["four", "three", "two"]);
+        assert_eq!(texts(&recent.newest(Some(2))), vec!["four", "three"]);
+        assert!(recent.newest(Some(0)).is_empty());
+    }
+
+    #[test]
+    fn test_keeps_metadata_of_failed_results() {
+        let failed = TranscriptionResult {
+            success: false,
+            error: Some("Transcription failed".to_string()),
+            error_code: Some("TRANSCRIPTION_FAILED".to_string()),
+            ..Default::default()
+        };
+        let kept = RecentResult::new(&failed, 1234567890);
+        assert!(!kept.success);
+        assert_eq!(kept.error_code.as_deref(), Some("TRANSCRIPTION_FAILED"));
+        assert_eq!(kept.timestamp, 1234567890);
+    }
+}
@@ -0,0 +1,531 @@
+//! Socket listeners
+//!
+//! With `--socket <path>` or `--listen <addr:port>` requests are read from
+//! connections to a Unix domain socket or a TCP port instead of stdin. Each
+//! connection speaks the same newline-delimited JSON protocol: its requests
+//! are read from it and its results, command responses and (with
+//! `--events-to stdout`) events are written back to it.
+//!
+//! Every connection is served by its own session task, so an idle or slow
+//! client never delays another: requests are read and buffered per
+//! connection and only the transcriptions are queued on the worker that owns
+//! the model, which runs them one at a time. Output goes through a writer
+//! task per connection; a client that does not accept a line within
+//! [`WRITE_TIMEOUT`] is disconnected, and lines beyond
+//! [`OUTPUT_QUEUE_LINES`] waiting for it are dropped. Up to
+//! `--max-connections` connections may be open at once; further clients are
+//! answered with a `TOO_MANY_CONNECTIONS` error and disconnected.
+//!
+//! Clients may reach the server over the network, so `load_model`, which
+//! opens a file on the server and replaces the model for every connection,
+//! is refused unless `--allow-remote-load-model` is given.
+
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::audio::InputReader;
+use crate::environment::{Config, EventStream};
+use crate::transcription::TranscriptionError;
+use crate::{EventSink, Session, process_audio_stream, send_error_result};
+
+/// Connections open at once by default
+pub const DEFAULT_MAX_CONNECTIONS: usize = 8;
+
+/// Output lines queued for a connection before it counts as not reading
+pub const OUTPUT_QUEUE_LINES: usize = 1024;
+
+/// Time a client gets to accept one line of output before it is disconnected
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Listening socket of either kind
+pub enum Listener {
+    /// Unix domain socket from `--socket`
+    Unix(UnixListener),
+    /// TCP socket from `--listen`
+    Tcp(TcpListener),
+}
+
+/// Accepted client
+pub struct Connection {
+    /// Requests read from the client
+    input: InputReader,
+    /// Sink queueing lines for the client
+    output: EventSink,
+    /// Task writing the queued lines, which ends early if the client stops reading
+    writer: JoinHandle<()>,
+}
+
+impl Listener {
+    /// Bind a Unix domain socket
+    ///
+    /// A socket file left behind by an earlier run is replaced; any other file at
+    /// the path is left alone and binding fails.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the socket file
+    ///
+    /// # Returns
+    /// * `io::Result<Self>` - Listener on success, error if the path is taken
+    pub fn bind_unix(path: &Path) -> io::Result<Self> {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        UnixListener::bind(path).map(Listener::Unix)
+    }
+
+    /// Bind a TCP socket
+    ///
+    /// # Arguments
+    /// * `addr` - Address and port to listen on, e.g. `127.0.0.1:7700`
+    ///
+    /// # Returns
+    /// * `io::Result<Self>` - Listener on success, error if the address cannot be bound
+    pub async fn bind_tcp(addr: &str) -> io::Result<Self> {
+        TcpListener::bind(addr).await.map(Listener::Tcp)
+    }
+
+    /// Accept the next connection
+    ///
+    /// The socket stays non-blocking: requests are read from its read half
+    /// and output is written to its write half by a task of its own.
+    ///
+    /// # Returns
+    /// * `io::Result<Connection>` - The accepted client
+    pub async fn accept(&self) -> io::Result<Connection> {
+        match self {
+            Listener::Unix(listener) => {
+                let (reader, writer) = listener.accept().await?.0.into_split();
+                Ok(connection(reader, writer))
+            }
+            Listener::Tcp(listener) => {
+                let (stream, peer) = listener.accept().await?;
+                debug!("Accepted TCP connection from {}", peer);
+                let (reader, writer) = stream.into_split();
+                Ok(connection(reader, writer))
+            }
+        }
+    }
+}
+
+/// Bind the listener selected by `--socket` or `--listen`
+///
+/// # Arguments
+/// * `config` - Server configuration
+///
+/// # Returns
+/// * `Result<Option<Listener>, String>` - The bound listener, None if neither option is set
+pub async fn bind_configured(config: &Config) -> Result<Option<Listener>, String> {
+    if let Some(path) = &config.socket_path {
+        info!("Listening on Unix socket {}", path);
+        return Listener::bind_unix(Path::new(path))
+            .map(Some)
+            .map_err(|e| format!("Failed to bind socket {}: {}", path, e));
+    }
+    if let Some(addr) = &config.listen_addr {
+        info!("Listening on TCP address {}", addr);
+        return Listener::bind_tcp(addr)
+            .await
+            .map(Some)
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e));
+    }
+    Ok(None)
+}
+
+/// Wrap the two halves of a stream as a connection
+fn connection(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    writer: impl AsyncWrite + Unpin + Send + 'static,
+) -> Connection {
+    let (lines, queued) = mpsc::channel(OUTPUT_QUEUE_LINES);
+    Connection {
+        input: InputReader::new(reader),
+        output: EventSink::Queued(lines),
+        writer: tokio::spawn(write_lines(writer, queued, WRITE_TIMEOUT)),
+    }
+}
+
+/// Write queued lines to a client until the queue closes or a write fails
+///
+/// # Arguments
+/// * `writer` - Write half of the connection
+/// * `lines` - Lines queued for the client
+/// * `timeout` - Time the client gets to accept each line
+async fn write_lines(
+    mut writer: impl AsyncWrite + Unpin,
+    mut lines: mpsc::Receiver<String>,
+    timeout: Duration,
+) {
+    while let Some(mut line) = lines.recv().await {
+        line.push('\n');
+        match tokio::time::timeout(timeout, writer.write_all(line.as_bytes())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                debug!("Failed to write to client: {}", e);
+                return;
+            }
+            Err(_) => {
+                warn!("Client did not accept output within {:?}", timeout);
+                return;
+            }
+        }
+    }
+    if let Err(e) = writer.shutdown().await {
+        debug!("Failed to close connection: {}", e);
+    }
+}
+
+/// Send results, and events configured for stdout, to a connection
+///
+/// The session is marked remote, which restricts the commands it accepts.
+///
+/// # Arguments
+/// * `session` - Session of the connection
+/// * `output` - Sink writing to the connected client
+fn route_output(session: &mut Session, output: EventSink) {
+    if session.config.events_to == EventStream::Stdout {
+        session.events = output.clone();
+    }
+    session.output = output;
+    session.remote = true;
+}
+
+/// Serve one connection until the client disconnects or stops reading
+///
+/// # Arguments
+/// * `session` - Session shared by all connections, routed to this one
+/// * `connection` - The accepted client
+/// * `_slot` - Connection slot, freed when the client is done
+async fn serve_connection(
+    mut session: Session,
+    connection: Connection,
+    _slot: OwnedSemaphorePermit,
+) {
+    info!("Client connected");
+    route_output(&mut session, connection.output);
+
+    // The writer only ends while the session runs if the client stopped reading
+    let mut writer = connection.writer;
+    tokio::select! {
+        result = process_audio_stream(&session, connection.input) => match result {
+            Ok(()) => info!("Client disconnected"),
+            Err(e) => warn!("Client session ended with an error: {}", e),
+        },
+        _ = &mut writer => warn!("Client stopped accepting output, disconnecting"),
+    }
+}
+
+/// Serve connections, each on its own task, until accepting fails
+///
+/// Clients arriving while every slot is taken are answered with a
+/// `TOO_MANY_CONNECTIONS` error and disconnected.
+///
+/// # Arguments
+/// * `session` - Session with the configuration and worker queue shared by all connections
+/// * `listener` - Listening socket
+///
+/// # Returns
+/// * `Result<(), String>` - Error message once the listener fails
+pub async fn serve(session: Session, listener: Listener) -> Result<(), String> {
+    let max_connections = session
+        .config
+        .max_connections
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+    let slots = Arc::new(Semaphore::new(max_connections));
+
+    loop {
+        let connection = listener
+            .accept()
+            .await
+            .map_err(|e| format!("Failed to accept connection: {}", e))?;
+        match Arc::clone(&slots).try_acquire_owned() {
+            Ok(slot) => {
+                tokio::spawn(serve_connection(session.clone(), connection, slot));
+            }
+            Err(_) => {
+                warn!("Rejecting connection, all slots are taken");
+                if let Err(e) = send_error_result(
+                    &TranscriptionError::TooManyConnections,
+                    &session.config,
+                    &connection.output,
+                ) {
+                    warn!("Failed to reject connection: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::TranscriptionResult;
+    use crate::worker::{Job, WorkQueue};
+    use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+
+    /// Worker answering each transcription with the number of bytes it was given
+    /// and failing every model swap
+    fn stub_worker() -> WorkQueue {
+        let (queue, mut jobs) = WorkQueue::channel();
+        tokio::spawn(async move {
+            while let Some(job) = jobs.recv().await {
+                match job {
+                    Job::Transcribe { audio, reply, .. } => {
+                        let _ = reply.send(Ok(TranscriptionResult {
+                            text: format!("{} bytes", audio.data.len()),
+                            success: true,
+                            ..Default::default()
+                        }));
+                    }
+                    Job::LoadModel { path, reply } => {
+                        let _ = reply.send(Err(format!("stub cannot load {}", path)));
+                    }
+                    Job::Warm { reply } => {
+                        let _ = reply.send(Ok(()));
+                    }
+                }
+            }
+        });
+        queue
+    }
+
+    /// Serve a listener's connections with a stub worker
+    fn spawn_serve(config: Config, listener: Listener) {
+        let session = Session {
+            config,
+            queue: stub_worker(),
+            events: EventSink::Stdout,
+            output: EventSink::Stdout,
+            remote: false,
+        };
+        tokio::spawn(serve(session, listener));
+    }
+
+    /// Serve TCP connections on loopback with a stub worker
+    async fn serve_stub(config: Config) -> std::net::SocketAddr {
+        let listener = Listener::bind_tcp("127.0.0.1:0").await.unwrap();
+        let Listener::Tcp(ref tcp) = listener else {
+            unreachable!()
+        };
+        let addr = tcp.local_addr().unwrap();
+        spawn_serve(config, listener);
+        addr
+    }
+
+    /// Client connected to a served socket
+    struct Client {
+        lines: Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>,
+        writer: Box<dyn AsyncWrite + Unpin + Send>,
+    }
+
+    impl Client {
+        fn new(
+            reader: impl AsyncRead + Unpin + Send + 'static,
+            writer: impl AsyncWrite + Unpin + Send + 'static,
+        ) -> Self {
+            let reader: Box<dyn AsyncRead + Unpin + Send> = Box::new(reader);
+            Self {
+                lines: BufReader::new(reader).lines(),
+                writer: Box::new(writer),
+            }
+        }
+
+        async fn connect(addr: std::net::SocketAddr) -> Self {
+            let (reader, writer) = tokio::net::TcpStream::connect(addr)
+                .await
+                .unwrap()
+                .into_split();
+            Self::new(reader, writer)
+        }
+
+        async fn connect_unix(path: &Path) -> Self {
+            let (reader, writer) = tokio::net::UnixStream::connect(path)
+                .await
+                .unwrap()
+                .into_split();
+            Self::new(reader, writer)
+        }
+
+        async fn send(&mut self, line: &str) {
+            self.writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        /// Close the sending side, ending the client's input
+        async fn finish(&mut self) {
+            self.writer.shutdown().await.unwrap();
+        }
+
+        /// Next line written back, failing the test if none arrives soon
+        async fn next_line(&mut self) -> Option<String> {
+            tokio::time::timeout(Duration::from_secs(5), self.lines.next_line())
+                .await
+                .expect("no output from the server")
+                .unwrap()
+        }
+
+        async fn next_json(&mut self) -> serde_json::Value {
+            serde_json::from_str(&self.next_line().await.unwrap()).unwrap()
+        }
+    }
+
+    const REQUEST: &str = "{\"audio_data\":{\"data\":[1,0,2,0]}}\n";
+
+    #[tokio::test]
+    async fn test_unix_connection_reads_requests_and_receives_output() {
+        let dir = std::env::temp_dir().join(format!("whisper-socket-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.sock");
+
+        // A stale socket file from an earlier run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        spawn_serve(Config::default(), Listener::bind_unix(&path).unwrap());
+
+        let mut client = Client::connect_unix(&path).await;
+        client.send(REQUEST).await;
+        let result = client.next_json().await;
+        assert_eq!(result["text"], "4 bytes");
+        assert_eq!(result["success"], true);
+
+        // Commands are answered on the same connection
+        client.send("{\"command\":\"buffer_status\"}\n").await;
+        assert_eq!(client.next_json().await["event"], "buffer_status");
+
+        // Closing the input ends the session with its own summary
+        client.finish().await;
+        let summary = client.next_json().await;
+        assert_eq!(summary["event"], "summary");
+        assert_eq!(summary["total_requests"], 1);
+        assert_eq!(summary["successful_requests"], 1);
+        assert!(client.next_line().await.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bind_unix_refuses_to_replace_other_files() {
+        let path = std::env::temp_dir().join(format!("whisper-not-socket-{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let error = Listener::bind_unix(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connection_over_loopback() {
+        let addr = serve_stub(Config::default()).await;
+
+        let mut client = Client::connect(addr).await;
+        client.send(REQUEST).await;
+        client.send("not json\n").await;
+        assert_eq!(client.next_json().await["text"], "4 bytes");
+        assert_eq!(client.next_json().await["error_code"], "INVALID_JSON");
+
+        client.finish().await;
+        let summary = client.next_json().await;
+        assert_eq!(summary["event"], "summary");
+        assert_eq!(summary["total_requests"], 2);
+        assert_eq!(summary["failed_requests"], 1);
+        assert!(client.next_line().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_idle_client_does_not_block_others() {
+        let addr = serve_stub(Config::default()).await;
+
+        // The first client connects and never sends anything
+        let _idle = Client::connect(addr).await;
+
+        let mut active = Client::connect(addr).await;
+        active.send(REQUEST).await;
+        let result = active.next_json().await;
+        assert_eq!(result["text"], "4 bytes");
+        assert_eq!(result["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_connections_over_the_limit_are_rejected() {
+        let addr = serve_stub(Config {
+            max_connections: Some(1),
+            ..Default::default()
+        })
+        .await;
+
+        // The first client takes the only slot
+        let mut first = Client::connect(addr).await;
+
+        // The second is answered with an error and disconnected
+        let mut second = Client::connect(addr).await;
+        let json = second.next_json().await;
+        assert_eq!(json["error_code"], "TOO_MANY_CONNECTIONS");
+        assert_eq!(json["success"], false);
+        assert!(second.next_line().await.is_none());
+
+        // The first is still served
+        first.send(REQUEST).await;
+        assert_eq!(first.next_json().await["text"], "4 bytes");
+    }
+
+    #[tokio::test]
+    async fn test_load_model_is_refused_on_connections_by_default() {
+        const LOAD: &str = "{\"command\":\"load_model\",\"path\":\"/tmp/model.bin\"}\n";
+
+        let addr = serve_stub(Config::default()).await;
+        let mut client = Client::connect(addr).await;
+        client.send(LOAD).await;
+        let json = client.next_json().await;
+        assert_eq!(json["event"], "error");
+        assert_eq!(json["command"], "load_model");
+        let error = json["error"].as_str().unwrap();
+        assert!(error.contains("--allow-remote-load-model"), "{}", error);
+
+        // The operator opted in, so the request reaches the worker
+        let addr = serve_stub(Config {
+            allow_remote_load_model: true,
+            ..Default::default()
+        })
+        .await;
+        let mut client = Client::connect(addr).await;
+        client.send(LOAD).await;
+        assert_eq!(
+            client.next_json().await["error"],
+            "stub cannot load /tmp/model.bin"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_that_stops_reading_is_dropped() {
+        let (server, _client) = tokio::net::UnixStream::pair().unwrap();
+        let (lines, queued) = mpsc::channel(OUTPUT_QUEUE_LINES);
+        let writer = tokio::spawn(write_lines(server, queued, Duration::from_millis(50)));
+
+        // The client never reads, so the socket buffer fills up
+        let line = "x".repeat(64 * 1024);
+        for _ in 0..64 {
+            if lines.send(line.clone()).await.is_err() {
+                break;
+            }
+        }
+        tokio::time::timeout(Duration::from_secs(5), writer)
+            .await
+            .expect("writer kept waiting on a client that does not read")
+            .unwrap();
+        assert!(lines.is_closed());
+    }
+}
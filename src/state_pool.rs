@@ -0,0 +1,202 @@
+//! Pool of reusable Whisper states
+//!
+//! Creating a `WhisperState` allocates the model's working buffers, so the
+//! transcription service keeps a few idle states around instead of creating
+//! one per request. `whisper_full` clears a state's previous results before
+//! decoding, so a returned state needs no explicit reset; a state whose
+//! decode failed is dropped instead of being returned, and the next checkout
+//! creates a fresh one.
+//!
+//! Creating a state can fail when the runtime is out of resources, so
+//! checkouts may retry with a growing pause before giving up.
+
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::warn;
+
+/// Bounded pool of idle states
+#[derive(Debug)]
+pub struct StatePool<T> {
+    /// States ready to be checked out
+    idle: Mutex<Vec<T>>,
+    /// Maximum number of idle states kept (0 disables pooling)
+    capacity: usize,
+}
+
+impl<T> StatePool<T> {
+    /// Create an empty pool
+    ///
+    /// # Arguments
+    /// * `capacity` - Maximum number of idle states kept
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            idle: Mutex::new(Vec::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Take an idle state, or create one if none is available
+    ///
+    /// # Arguments
+    /// * `create` - Factory used when the pool is empty
+    ///
+    /// # Returns
+    /// * `Result<T, E>` - A state ready for use, or the factory's error
+    pub fn checkout<E>(&self, create: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let pooled = self
+            .idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .pop();
+        match pooled {
+            Some(state) => Ok(state),
+            None => create(),
+        }
+    }
+
+    /// Take an idle state, retrying creation with backoff if it fails
+    ///
+    /// # Arguments
+    /// * `create` - Factory used when the pool is empty
+    /// * `retries` - Number of retries after the first failed attempt
+    /// * `backoff` - Pause before the first retry, growing linearly with each retry
+    ///
+    /// # Returns
+    /// * `Result<T, E>` - A state ready for use, or the last factory error
+    pub fn checkout_with_retry<E: Display>(
+        &self,
+        mut create: impl FnMut() -> Result<T, E>,
+        retries: u32,
+        backoff: Duration,
+    ) -> Result<T, E> {
+        let mut attempt = 0;
+        loop {
+            match self.checkout(&mut create) {
+                Ok(state) => return Ok(state),
+                Err(e) if attempt < retries => {
+                    attempt += 1;
+                    let pause = backoff * attempt;
+                    warn!(
+                        "Failed to create Whisper state ({}), retry {} of {} in {:?}",
+                        e, attempt, retries, pause
+                    );
+                    std::thread::sleep(pause);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Return a state to the pool, dropping it if the pool is full
+    ///
+    /// # Arguments
+    /// * `state` - State that finished a successful decode
+    pub fn checkin(&self, state: T) {
+        let mut idle = self
+            .idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if idle.len() < self.capacity {
+            idle.push(state);
+        }
+    }
+
+    /// Number of idle states currently held
+    pub fn idle_count(&self) -> usize {
+        self.idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_checkout_reuses_returned_state() {
+        let pool = StatePool::new(2);
+        let created = Cell::new(0);
+        let create = || -> Result<u32, String> {
+            created.set(created.get() + 1);
+            Ok(created.get())
+        };
+
+        let first = pool.checkout(create).unwrap();
+        assert_eq!(created.get(), 1);
+        pool.checkin(first);
+        assert_eq!(pool.idle_count(), 1);
+
+        // The returned state is reused instead of creating a new one
+        let reused = pool.checkout(create).unwrap();
+        assert_eq!(reused, first);
+        assert_eq!(created.get(), 1);
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[test]
+    fn test_discarded_state_is_recreated() {
+        let pool = StatePool::new(1);
+        let created = Cell::new(0);
+        let create = || -> Result<u32, String> {
+            created.set(created.get() + 1);
+            Ok(created.get())
+        };
+
+        // A state that is never checked back in is replaced on the next checkout
+        let _failed = pool.checkout(create).unwrap();
+        let fresh = pool.checkout(create).unwrap();
+        assert_eq!(fresh, 2);
+    }
+
+    #[test]
+    fn test_checkin_respects_capacity() {
+        let pool = StatePool::new(1);
+        pool.checkin(1);
+        pool.checkin(2);
+        assert_eq!(pool.idle_count(), 1);
+
+        let disabled = StatePool::new(0);
+        disabled.checkin(1);
+        assert_eq!(disabled.idle_count(), 0);
+
+        let error: Result<u32, String> = disabled.checkout(|| Err("no model".to_string()));
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_checkout_retries_failed_creation() {
+        let pool: StatePool<u32> = StatePool::new(1);
+        let attempts = Cell::new(0);
+        let flaky = || -> Result<u32, String> {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err("out of memory".to_string())
+            } else {
+                Ok(7)
+            }
+        };
+
+        let state = pool.checkout_with_retry(flaky, 3, Duration::ZERO).unwrap();
+        assert_eq!(state, 7);
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn test_checkout_gives_up_after_retries() {
+        let pool: StatePool<u32> = StatePool::new(1);
+        let attempts = Cell::new(0);
+        let failing = || -> Result<u32, String> {
+            attempts.set(attempts.get() + 1);
+            Err("out of memory".to_string())
+        };
+
+        let error = pool.checkout_with_retry(failing, 2, Duration::ZERO);
+        assert_eq!(error, Err("out of memory".to_string()));
+        assert_eq!(attempts.get(), 3);
+    }
+}
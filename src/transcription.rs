@@ -1,8 +1,15 @@
+use crate::audio;
+use crate::grammar::ParsedGrammar;
+use crate::state_pool::StatePool;
 use base64::Engine;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
+use whisper_rs::{FullParams, SamplingStrategy, SegmentCallbackData, WhisperContext, WhisperState};
 
 /// JSON request structure for audio transcription
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +18,12 @@ pub struct TranscriptionRequest {
     pub audio_data: AudioDataFormat,
     /// Transcription options
     pub options: Option<TranscriptionOptions>,
+    /// Sequence number of the chunk when chunks may arrive out of order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    /// Duration of the audio in milliseconds as declared by the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
 }
 
 /// Audio data format - supports both base64 and binary representations
@@ -22,19 +35,28 @@ pub enum AudioDataFormat {
         /// Base64 encoded audio string
         data: String,
         /// Format hint (optional)
-        #[serde(rename = "format")]
-        _format: Option<String>,
+        format: Option<String>,
     },
     /// Binary audio data as Vec<u8>
     Binary {
         /// Binary audio data
         data: Vec<u8>,
         /// Format hint (optional)
-        #[serde(rename = "format")]
-        _format: Option<String>,
+        format: Option<String>,
     },
 }
 
+impl AudioDataFormat {
+    /// Format hint sent with the audio (e.g. "pcm" or "wav")
+    pub fn format_hint(&self) -> Option<&str> {
+        match self {
+            AudioDataFormat::Base64 { format, .. } | AudioDataFormat::Binary { format, .. } => {
+                format.as_deref()
+            }
+        }
+    }
+}
+
 /// Transcription options that can be configured via JSON
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionOptions {
@@ -48,14 +70,28 @@ pub struct TranscriptionOptions {
     pub max_tokens: Option<usize>,
     /// Temperature for sampling (0.0 to 1.0)
     pub temperature: Option<f32>,
+    /// Explicit temperature schedule tried in order (overrides `temperature`)
+    pub temperatures: Option<Vec<f32>>,
     /// Whether to use beam search decoding
     pub use_beam_search: Option<bool>,
     /// Number of beams for beam search
     pub beam_size: Option<i32>,
+    /// Beam-search patience (must be greater than 0.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patience: Option<f32>,
     /// Whether to suppress blank tokens
     pub suppress_blank: Option<bool>,
     /// Whether to enable word timestamps
     pub word_timestamps: Option<bool>,
+    /// GBNF grammar constraining the decoded text (must define a `root` rule)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<String>,
+    /// Sample rate of raw audio in Hz, resampled to 16 kHz when it differs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Interleaved channels in raw audio, mixed down to mono when more than one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
 }
 
 impl Default for TranscriptionOptions {
@@ -66,10 +102,15 @@ impl Default for TranscriptionOptions {
             include_timestamps: Some(true),
             max_tokens: None,
             temperature: Some(0.0),
+            temperatures: None,
             use_beam_search: Some(true), // Changed to true to match client
             beam_size: Some(5),          // Changed to Some(5) to match client
+            patience: None,
             suppress_blank: Some(true),
             word_timestamps: Some(false), // Matches client's word_timestamps parameter
+            grammar: None,
+            sample_rate: None,
+            channels: None,
         }
     }
 }
@@ -80,8 +121,8 @@ impl Default for TranscriptionOptions {
 /// * `request` - The transcription request containing audio data
 ///
 /// # Returns
-/// * `Result<Vec<u8>, String>` - Audio data as Vec<u8> on success, error message on failure
-pub fn extract_audio_data(request: &TranscriptionRequest) -> Result<Vec<u8>, String> {
+/// * `Result<Vec<u8>, JsonError>` - Audio data as Vec<u8> on success, error on failure
+pub fn extract_audio_data(request: &TranscriptionRequest) -> Result<Vec<u8>, JsonError> {
     debug!("Extracting audio data from transcription request");
 
     match &request.audio_data {
@@ -95,11 +136,19 @@ pub fn extract_audio_data(request: &TranscriptionRequest) -> Result<Vec<u8>, Str
                         "Successfully decoded base64 audio data: {} bytes",
                         decoded_data.len()
                     );
+
+                    // Validate decoded data is not empty (parity with the binary path)
+                    if decoded_data.is_empty() {
+                        error!("Decoded base64 audio data is empty");
+                        return Err(JsonError::EmptyAudio(
+                            "Decoded base64 audio data is empty".to_string(),
+                        ));
+                    }
                     Ok(decoded_data)
                 }
                 Err(e) => {
                     error!("Failed to decode base64 audio data: {}", e);
-                    Err(format!("Failed to decode base64 audio data: {}", e))
+                    Err(JsonError::InvalidBase64(e.to_string()))
                 }
             }
         }
@@ -109,7 +158,9 @@ pub fn extract_audio_data(request: &TranscriptionRequest) -> Result<Vec<u8>, Str
             // Validate binary data is not empty
             if data.is_empty() {
                 error!("Binary audio data is empty");
-                return Err("Binary audio data is empty".to_string());
+                return Err(JsonError::EmptyAudio(
+                    "Binary audio data is empty".to_string(),
+                ));
             }
 
             // Create a copy of the binary data
@@ -123,6 +174,30 @@ pub fn extract_audio_data(request: &TranscriptionRequest) -> Result<Vec<u8>, Str
     }
 }
 
+/// Effective configuration for a request
+///
+/// Options sent with the request are validated and override the server's
+/// configuration field by field.
+///
+/// # Arguments
+/// * `base` - Server transcription configuration
+/// * `options` - Options sent with the request, if any
+///
+/// # Returns
+/// * `Result<TranscriptionConfig, TranscriptionError>` - Merged configuration, or the first invalid option
+pub fn request_config(
+    base: &TranscriptionConfig,
+    options: Option<&TranscriptionOptions>,
+) -> Result<TranscriptionConfig, TranscriptionError> {
+    let Some(options) = options else {
+        return Ok(base.clone());
+    };
+    if let Some(error) = validate_transcription_options(options)?.into_iter().next() {
+        return Err(JsonError::InvalidFieldValue(error.field, error.message).into());
+    }
+    Ok(update_config_from_options(base, options))
+}
+
 /// Update transcription configuration from JSON options
 ///
 /// # Arguments
@@ -131,10 +206,6 @@ pub fn extract_audio_data(request: &TranscriptionRequest) -> Result<Vec<u8>, Str
 ///
 /// # Returns
 /// * `TranscriptionConfig` - Updated configuration
-#[allow(
-    dead_code,
-    reason = "Function kept for future configuration management capabilities"
-)]
 pub fn update_config_from_options(
     config: &TranscriptionConfig,
     options: &TranscriptionOptions,
@@ -164,6 +235,10 @@ pub fn update_config_from_options(
         updated_config.temperature = temperature;
     }
 
+    if let Some(ref temperatures) = options.temperatures {
+        updated_config.temperatures = Some(temperatures.clone());
+    }
+
     if let Some(use_beam_search) = options.use_beam_search {
         updated_config.use_beam_search = use_beam_search;
     }
@@ -172,6 +247,10 @@ pub fn update_config_from_options(
         updated_config.beam_size = Some(beam_size);
     }
 
+    if let Some(patience) = options.patience {
+        updated_config.patience = Some(patience);
+    }
+
     if let Some(suppress_blank) = options.suppress_blank {
         updated_config.suppress_blank = suppress_blank;
     }
@@ -180,6 +259,10 @@ pub fn update_config_from_options(
         updated_config.word_timestamps = word_timestamps;
     }
 
+    if let Some(ref grammar) = options.grammar {
+        updated_config.grammar = Some(grammar.clone());
+    }
+
     debug!("Updated transcription configuration: {:?}", updated_config);
 
     updated_config
@@ -198,14 +281,65 @@ pub struct TranscriptionConfig {
     pub max_tokens: Option<usize>,
     /// Temperature for sampling (0.0 to 1.0)
     pub temperature: f32,
+    /// Explicit temperature schedule tried in order (overrides `temperature`)
+    pub temperatures: Option<Vec<f32>>,
     /// Whether to use beam search decoding
     pub use_beam_search: bool,
     /// Number of beams for beam search
     pub beam_size: Option<i32>,
+    /// Beam-search patience (DEFAULT_PATIENCE when None)
+    pub patience: Option<f32>,
     /// Whether to suppress blank tokens
     pub suppress_blank: bool,
     /// Whether to enable word timestamps
     pub word_timestamps: bool,
+    /// Pre-emphasis filter coefficient applied to samples before inference
+    pub pre_emphasis: Option<f32>,
+    /// Whether to trim leading/trailing silence before inference
+    pub trim_silence: bool,
+    /// Automatic gain control applied before inference (disabled when None)
+    pub agc: Option<audio::AgcSettings>,
+    /// Order the preprocessing steps run in
+    pub preprocess_order: Vec<audio::PreprocessStep>,
+    /// Window length for splitting long audio into chunks (disabled when None)
+    pub chunk_ms: Option<u64>,
+    /// Overlap shared by consecutive chunks in milliseconds
+    pub chunk_overlap_ms: u64,
+    /// Convert audio one window at a time instead of up front (needs `chunk_ms`)
+    pub low_memory: bool,
+    /// Skip inference when the audio is silent throughout
+    pub skip_silent: bool,
+    /// GBNF grammar constraining the decoded text
+    pub grammar: Option<String>,
+    /// Maximum segment length in characters (whisper's default when None)
+    pub max_segment_chars: Option<i32>,
+    /// Overrides applied when auto-detection picks one of these languages
+    ///
+    /// Empty unless `--language-overrides` is given, since any entry costs an
+    /// extra detection pass on every auto-detected request.
+    pub language_overrides: HashMap<String, LanguageOverride>,
+    /// Decimal places segment timestamps are rounded to
+    pub timestamp_decimals: u32,
+    /// Number of idle Whisper states kept for reuse (0 creates one per request)
+    pub state_pool_size: usize,
+    /// Run a second, translating pass and report its text alongside the original
+    pub with_translation: bool,
+    /// Segments shorter than this many milliseconds are filtered out
+    pub min_segment_ms: Option<u64>,
+    /// Most segments returned per result; the rest are counted but dropped
+    pub max_segments: Option<usize>,
+    /// Merge the text of short segments into their neighbours instead of discarding it
+    pub merge_short_segments: bool,
+    /// Decode greedily on one thread at temperature 0 for reproducible output
+    pub deterministic: bool,
+    /// Threads used for decoding (available parallelism when None)
+    pub threads: Option<usize>,
+    /// Pause in milliseconds before a segment that marks a likely speaker change (hints off when None)
+    pub speaker_gap_ms: Option<u64>,
+    /// Pause in milliseconds that ends a phrase when re-grouping words (native segments when None)
+    pub phrase_gap_ms: Option<u64>,
+    /// Retry greedily when beam search produces no text
+    pub greedy_fallback: bool,
 }
 
 impl Default for TranscriptionConfig {
@@ -216,16 +350,439 @@ impl Default for TranscriptionConfig {
             include_timestamps: false,
             max_tokens: None,
             temperature: 0.0,
+            temperatures: None,
             use_beam_search: false,
             beam_size: None,
+            patience: None,
             suppress_blank: true,
             word_timestamps: false,
+            pre_emphasis: None,
+            trim_silence: false,
+            agc: None,
+            preprocess_order: audio::PreprocessStep::DEFAULT_ORDER.to_vec(),
+            chunk_ms: None,
+            chunk_overlap_ms: 0,
+            low_memory: false,
+            skip_silent: false,
+            grammar: None,
+            max_segment_chars: None,
+            language_overrides: HashMap::new(),
+            timestamp_decimals: DEFAULT_TIMESTAMP_DECIMALS,
+            state_pool_size: DEFAULT_STATE_POOL_SIZE,
+            with_translation: false,
+            min_segment_ms: None,
+            max_segments: None,
+            merge_short_segments: false,
+            deterministic: false,
+            threads: None,
+            speaker_gap_ms: None,
+            phrase_gap_ms: None,
+            greedy_fallback: false,
+        }
+    }
+}
+
+/// Settings applied on top of the configuration when a language is detected
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LanguageOverride {
+    /// Number of beams for beam search
+    pub beam_size: Option<i32>,
+    /// Temperature for sampling (0.0 to 1.0)
+    pub temperature: Option<f32>,
+    /// Whether to suppress blank tokens
+    pub suppress_blank: Option<bool>,
+    /// Maximum segment length in characters
+    pub max_segment_chars: Option<i32>,
+}
+
+impl LanguageOverride {
+    /// Apply the fields that are set onto a configuration
+    pub fn apply(&self, config: &mut TranscriptionConfig) {
+        if let Some(beam_size) = self.beam_size {
+            config.beam_size = Some(beam_size);
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(suppress_blank) = self.suppress_blank {
+            config.suppress_blank = suppress_blank;
+        }
+        if let Some(max_segment_chars) = self.max_segment_chars {
+            config.max_segment_chars = Some(max_segment_chars);
+        }
+    }
+}
+
+/// Built-in per-language overrides
+///
+/// Chinese, Japanese and Korean text has no spaces to split on, so whisper
+/// tends to emit very long segments; capping the segment length keeps
+/// timestamps useful.
+pub fn builtin_language_overrides() -> HashMap<String, LanguageOverride> {
+    ["zh", "ja", "ko"]
+        .into_iter()
+        .map(|lang| {
+            (
+                lang.to_string(),
+                LanguageOverride {
+                    max_segment_chars: Some(30),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+/// Load per-language overrides from a JSON file, layered over the built-ins
+///
+/// The file maps language codes to override objects, e.g.
+/// `{"ja": {"max_segment_chars": 20}, "de": {"beam_size": 8}}`. An entry
+/// replaces the built-in override for the same language.
+///
+/// # Arguments
+/// * `path` - Path to the JSON file
+///
+/// # Returns
+/// * `Result<HashMap<String, LanguageOverride>, String>` - Overrides on success, error message on failure
+pub fn load_language_overrides(path: &str) -> Result<HashMap<String, LanguageOverride>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read language overrides {}: {}", path, e))?;
+    let file_overrides: HashMap<String, LanguageOverride> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid language overrides {}: {}", path, e))?;
+
+    let mut overrides = builtin_language_overrides();
+    overrides.extend(file_overrides);
+    Ok(overrides)
+}
+
+/// Configuration to decode with once the spoken language is known
+///
+/// # Arguments
+/// * `config` - Base configuration
+/// * `language` - Detected language code
+///
+/// # Returns
+/// * `TranscriptionConfig` - Configuration pinned to the language with its override applied
+pub fn config_for_detected_language(
+    config: &TranscriptionConfig,
+    language: &str,
+) -> TranscriptionConfig {
+    let mut resolved = config.clone();
+    resolved.language = Some(language.to_string());
+    if let Some(language_override) = config.language_overrides.get(language) {
+        debug!("Applying overrides for language {}", language);
+        language_override.apply(&mut resolved);
+    }
+    resolved
+}
+
+/// Check that the model can honour a translation request
+///
+/// English-only models ignore the translate task and would silently return
+/// an untranslated transcript, so the request is rejected instead.
+///
+/// # Arguments
+/// * `config` - Effective configuration for the request
+/// * `multilingual` - Whether the loaded model is multilingual
+///
+/// # Returns
+/// * `Result<(), TranscriptionError>` - Ok if no translation is needed or the model supports it
+pub fn check_translation_supported(
+    config: &TranscriptionConfig,
+    multilingual: bool,
+) -> Result<(), TranscriptionError> {
+    if (config.translate_to_english || config.with_translation) && !multilingual {
+        return Err(TranscriptionError::TranslateRequiresMultilingual);
+    }
+    Ok(())
+}
+
+/// Whether a loaded model has an encoder and a decoder
+///
+/// # Arguments
+/// * `context` - Loaded Whisper context
+///
+/// # Returns
+/// * `(bool, bool)` - Whether the encoder and the decoder have layers
+pub fn model_components(context: &WhisperContext) -> (bool, bool) {
+    let has_encoder = context.model_n_audio_layer() > 0 && context.model_n_audio_ctx() > 0;
+    let has_decoder = context.model_n_text_layer() > 0 && context.model_n_vocab() > 0;
+    (has_encoder, has_decoder)
+}
+
+/// Check that a loaded model can transcribe
+///
+/// # Arguments
+/// * `has_encoder` - Whether the model has an audio encoder
+/// * `has_decoder` - Whether the model has a text decoder
+///
+/// # Returns
+/// * `Result<(), TranscriptionError>` - Ok if both components are present
+pub fn check_model_architecture(
+    has_encoder: bool,
+    has_decoder: bool,
+) -> Result<(), TranscriptionError> {
+    let missing = match (has_encoder, has_decoder) {
+        (true, true) => return Ok(()),
+        (false, true) => "encoder",
+        (true, false) => "decoder",
+        (false, false) => "encoder and decoder",
+    };
+    Err(TranscriptionError::InvalidModelArchitecture(format!(
+        "model has no {}",
+        missing
+    )))
+}
+
+/// Check whether inference can be skipped because the audio is silent
+///
+/// With `skip_silent`, audio whose every frame is below the silence
+/// threshold is answered before a Whisper state is even checked out.
+///
+/// # Arguments
+/// * `config` - Effective configuration for the request
+/// * `audio_data` - Raw 16-bit PCM bytes of the request
+///
+/// # Returns
+/// * `bool` - True if the request should get an empty `no_speech` result
+pub fn skips_as_silent(config: &TranscriptionConfig, audio_data: &[u8]) -> bool {
+    config.skip_silent
+        && !audio::contains_speech(
+            audio::pcm16_samples(audio_data),
+            audio::DEFAULT_SILENCE_THRESHOLD,
+        )
+}
+
+/// Run an untranslated and a translating pass and combine their results
+///
+/// The first pass keeps the spoken language and provides the text, segments
+/// and language; the second pass only contributes `translated_text`.
+///
+/// # Arguments
+/// * `config` - Effective configuration for the request
+/// * `decode` - Runs one decoding pass with the given configuration
+///
+/// # Returns
+/// * `Result<TranscriptionResult, TranscriptionError>` - Combined result, or the first failure
+pub fn transcribe_with_translation<F>(
+    config: &TranscriptionConfig,
+    mut decode: F,
+) -> Result<TranscriptionResult, TranscriptionError>
+where
+    F: FnMut(&TranscriptionConfig) -> Result<TranscriptionResult, TranscriptionError>,
+{
+    let mut result = decode(&TranscriptionConfig {
+        translate_to_english: false,
+        ..config.clone()
+    })?;
+    let translated = decode(&TranscriptionConfig {
+        translate_to_english: true,
+        ..config.clone()
+    })?;
+    result.translated_text = Some(translated.text);
+    // The second pass finishes last, so its duration covers both
+    result.duration_ms = translated.duration_ms;
+    Ok(result)
+}
+
+/// Retry a beam search pass greedily if it produced no text
+///
+/// Beam search occasionally returns nothing where greedy decoding finds
+/// speech. Passes that already decode greedily are returned unchanged.
+///
+/// # Arguments
+/// * `config` - Effective configuration for the pass
+/// * `decode` - Runs one decoding pass with the given configuration
+///
+/// # Returns
+/// * `Result<TranscriptionResult, TranscriptionError>` - Beam search result, or the greedy retry
+pub fn retry_empty_with_greedy<F>(
+    config: &TranscriptionConfig,
+    mut decode: F,
+) -> Result<TranscriptionResult, TranscriptionError>
+where
+    F: FnMut(&TranscriptionConfig) -> Result<TranscriptionResult, TranscriptionError>,
+{
+    let result = decode(config)?;
+    if !result.text.is_empty() || !config.use_beam_search || config.deterministic {
+        return Ok(result);
+    }
+
+    info!("Beam search produced no text, retrying with greedy decoding");
+    let mut retry = decode(&TranscriptionConfig {
+        use_beam_search: false,
+        ..config.clone()
+    })?;
+    retry.fallback_used = Some(true);
+    if let Some(decoding) = &mut retry.decoding {
+        decoding.fallback_used = true;
+    }
+    Ok(retry)
+}
+
+/// Segment timestamps are rounded to milliseconds by default
+pub const DEFAULT_TIMESTAMP_DECIMALS: u32 = 3;
+
+/// Default number of idle Whisper states kept for reuse
+pub const DEFAULT_STATE_POOL_SIZE: usize = 1;
+
+/// Retries when creating a Whisper state fails before the request fails
+const STATE_CREATE_RETRIES: u32 = 3;
+
+/// Pause before the first state creation retry
+const STATE_CREATE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Round a timestamp in seconds to a number of decimal places
+///
+/// # Arguments
+/// * `seconds` - Timestamp to round
+/// * `decimals` - Decimal places to keep
+///
+/// # Returns
+/// * `f32` - Rounded timestamp
+pub fn round_timestamp(seconds: f32, decimals: u32) -> f32 {
+    let scale = 10f64.powi(decimals as i32);
+    ((seconds as f64 * scale).round() / scale) as f32
+}
+
+/// Pause before a segment that marks a likely speaker change by default
+pub const DEFAULT_SPEAKER_GAP_MS: u64 = 1500;
+
+/// Mark segments that follow a long pause as a likely speaker change
+///
+/// This is a pause heuristic, not diarization: the first segment is never
+/// marked, and every later one is marked when the silence since the end of
+/// the previous segment is at least `min_gap_ms`.
+///
+/// # Arguments
+/// * `segments` - Segments in time order
+/// * `min_gap_ms` - Shortest pause treated as a speaker change
+pub fn annotate_speaker_hints(segments: &mut [TranscriptionSegment], min_gap_ms: u64) {
+    let mut previous_end = None;
+    for segment in segments {
+        let new_speaker = previous_end
+            .is_some_and(|end: f32| (segment.start - end) * 1000.0 >= min_gap_ms as f32);
+        segment.likely_new_speaker = Some(new_speaker);
+        previous_end = Some(segment.end);
+    }
+}
+
+/// Pause that ends a phrase by default when re-grouping words
+pub const DEFAULT_PHRASE_GAP_MS: u64 = 500;
+
+/// Punctuation that ends a phrase
+const PHRASE_END: [char; 6] = ['.', '?', '!', ',', ';', ':'];
+
+/// Word with its timing, assembled from token timestamps
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedWord {
+    /// Start time in seconds
+    pub start: f32,
+    /// End time in seconds
+    pub end: f32,
+    /// Text of the word, including attached punctuation
+    pub text: String,
+}
+
+/// Add a decoded token piece to the word list
+///
+/// A piece starting with whitespace begins a new word; any other piece
+/// continues the previous word.
+fn push_word_piece(words: &mut Vec<TimedWord>, piece: &str, start: f32, end: f32) {
+    if piece.trim().is_empty() {
+        return;
+    }
+    match words.last_mut() {
+        Some(word) if !piece.starts_with(char::is_whitespace) => {
+            word.text.push_str(piece);
+            word.end = end;
+        }
+        _ => words.push(TimedWord {
+            start,
+            end,
+            text: piece.trim().to_string(),
+        }),
+    }
+}
+
+/// Re-group timed words into phrases
+///
+/// A phrase ends after a word ending in sentence or clause punctuation, or
+/// before a word that follows a pause of at least `max_gap_ms`.
+///
+/// # Arguments
+/// * `words` - Words in time order
+/// * `max_gap_ms` - Shortest pause that ends a phrase
+///
+/// # Returns
+/// * `Vec<TranscriptionSegment>` - One segment per phrase
+pub fn group_words_into_phrases(words: &[TimedWord], max_gap_ms: u64) -> Vec<TranscriptionSegment> {
+    let mut phrases: Vec<TranscriptionSegment> = Vec::new();
+    let mut phrase_open = false;
+    for word in words {
+        let paused = phrases
+            .last()
+            .is_some_and(|phrase| (word.start - phrase.end) * 1000.0 >= max_gap_ms as f32);
+        match phrases.last_mut() {
+            Some(phrase) if phrase_open && !paused => {
+                phrase.text.push(' ');
+                phrase.text.push_str(&word.text);
+                phrase.end = word.end;
+            }
+            _ => phrases.push(TranscriptionSegment {
+                start: word.start,
+                end: word.end,
+                text: word.text.clone(),
+                confidence: None,
+                likely_new_speaker: None,
+            }),
         }
+        phrase_open = !word.text.ends_with(&PHRASE_END[..]);
+    }
+    phrases
+}
+
+/// Shift every segment by audio removed from the start of the request
+///
+/// Whisper times segments from the start of the buffer it decoded, so after
+/// `--trim-silence` they are moved back onto the client's timeline.
+///
+/// # Arguments
+/// * `segments` - Segments to shift
+/// * `offset_ms` - Leading audio removed before decoding, in milliseconds
+pub fn offset_segment_timestamps(segments: &mut [TranscriptionSegment], offset_ms: u64) {
+    let offset = offset_ms as f32 / 1000.0;
+    for segment in segments {
+        segment.start += offset;
+        segment.end += offset;
+    }
+}
+
+/// Round the start and end of every segment
+pub fn round_segment_timestamps(segments: &mut [TranscriptionSegment], decimals: u32) {
+    for segment in segments {
+        segment.start = round_timestamp(segment.start, decimals);
+        segment.end = round_timestamp(segment.end, decimals);
+    }
+}
+
+/// Temperatures to decode with, in the order they should be tried
+///
+/// An explicit `temperatures` list takes precedence over the scalar
+/// `temperature`, which otherwise forms a single-entry schedule. Deterministic
+/// mode always decodes once at temperature 0.
+pub fn temperature_schedule(config: &TranscriptionConfig) -> Vec<f32> {
+    if config.deterministic {
+        return vec![0.0];
+    }
+    match config.temperatures {
+        Some(ref temperatures) if !temperatures.is_empty() => temperatures.clone(),
+        _ => vec![config.temperature],
     }
 }
 
 /// Transcription result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TranscriptionResult {
     /// The transcribed text
     pub text: String,
@@ -239,107 +796,595 @@ pub struct TranscriptionResult {
     pub error: Option<String>,
     /// Time taken for transcription
     pub duration_ms: Option<u64>,
+    /// Leading silence removed before inference in milliseconds (if trimming is enabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trimmed_leading_ms: Option<u64>,
+    /// Trailing silence removed before inference in milliseconds (if trimming is enabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trimmed_trailing_ms: Option<u64>,
+    /// Number of segments produced by Whisper
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments_total: Option<usize>,
+    /// Number of segments kept after dropping empty ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments_emitted: Option<usize>,
+    /// Stable machine-readable error code if transcription failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// How the input audio was interpreted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_audio: Option<audio::InputAudioInfo>,
+    /// English translation of the text (with `--with-translation`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translated_text: Option<String>,
+    /// Number of segments filtered out for being shorter than `--min-segment-ms`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments_too_short: Option<usize>,
+    /// Number of segments dropped beyond `--max-segments` (their text is kept)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments_truncated: Option<usize>,
+    /// Server-side timings for this request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<RequestMetrics>,
+    /// Whether the text comes from a greedy retry after beam search produced none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_used: Option<bool>,
+    /// Average token log probability across all segments, weighted by token count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_logprob: Option<f32>,
+    /// Number of text tokens `avg_logprob` was computed over
+    #[serde(skip)]
+    pub logprob_tokens: usize,
+    /// Sampling setup the text was actually decoded with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoding: Option<DecodingInfo>,
+    /// Whether inference was skipped because the audio is silent (with `--skip-silent`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_speech: Option<bool>,
+    /// Stable code explaining an empty successful result, e.g. `NO_SPEECH_DETECTED`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
-/// Transcription segment with timing information
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct TranscriptionSegment {
-    /// Start time in seconds
+/// Reason given for results skipped as silent by `--skip-silent`
+pub const NO_SPEECH_DETECTED: &str = "NO_SPEECH_DETECTED";
+
+/// Segment emitted as soon as Whisper decodes it (with `--stream-segments`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentEvent {
+    /// Event type (always "segment")
+    pub event: String,
+    /// Decoding pass that produced the segment
+    pub pass: u32,
+    /// Index of the segment within the request
+    pub index: i32,
+    /// Start time in seconds from the start of the request audio
     pub start: f32,
-    /// End time in seconds
+    /// End time in seconds from the start of the request audio
     pub end: f32,
     /// Text content of the segment
     pub text: String,
-    /// Confidence score (0.0 to 1.0)
-    pub confidence: Option<f32>,
 }
 
-/// Transcription error types
-#[derive(Debug)]
-pub enum TranscriptionError {
-    WhisperContextError(String),
-    AudioDataError(String),
-    TranscriptionFailed(String),
-    ConfigurationError(String),
+/// Withdraws the segments streamed by a pass whose result was not kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentsDiscardedEvent {
+    /// Event type (always "segments_discarded")
+    pub event: String,
+    /// Decoding pass whose segments are withdrawn
+    pub pass: u32,
 }
 
-impl std::fmt::Display for TranscriptionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            TranscriptionError::WhisperContextError(e) => write!(f, "Whisper context error: {}", e),
-            TranscriptionError::AudioDataError(e) => write!(f, "Audio data error: {}", e),
-            TranscriptionError::TranscriptionFailed(e) => write!(f, "Transcription failed: {}", e),
-            TranscriptionError::ConfigurationError(e) => write!(f, "Configuration error: {}", e),
-        }
-    }
+/// Event written while a request is still decoding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StreamEvent {
+    /// A segment was decoded
+    Segment(SegmentEvent),
+    /// Earlier segments were discarded
+    Discarded(SegmentsDiscardedEvent),
 }
 
-impl std::error::Error for TranscriptionError {}
-
-/// JSON parsing and validation errors
-#[derive(Debug)]
-#[allow(
-    dead_code,
-    reason = "Enum kept for future JSON validation error handling"
-)]
-pub enum JsonError {
-    /// Invalid JSON format
-    InvalidJson(String),
-    /// Missing required field
-    MissingField(String),
-    /// Invalid field value
-    InvalidFieldValue(String, String),
-    /// Invalid base64 encoding
-    InvalidBase64(String),
-    /// Audio data validation failed
-    AudioDataError(String),
+/// Samples of a request, converted up front or one window at a time
+enum SampleSource<'a> {
+    /// Every sample converted and preprocessed before decoding
+    Converted(&'a [f32]),
+    /// Raw 16-bit PCM converted per window to bound peak memory
+    Pcm16 {
+        /// Raw PCM bytes of the whole request
+        bytes: &'a [u8],
+        /// Pre-emphasis coefficient applied to each window, if any
+        pre_emphasis: Option<f32>,
+    },
 }
 
-impl std::fmt::Display for JsonError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl SampleSource<'_> {
+    /// Number of samples in the request
+    fn len(&self) -> usize {
         match self {
-            JsonError::InvalidJson(e) => write!(f, "Invalid JSON: {}", e),
-            JsonError::MissingField(field) => write!(f, "Missing required field: {}", field),
-            JsonError::InvalidFieldValue(field, value) => {
-                write!(f, "Invalid value for field '{}': {}", field, value)
-            }
-            JsonError::InvalidBase64(e) => write!(f, "Invalid base64 encoding: {}", e),
-            JsonError::AudioDataError(e) => write!(f, "Audio data error: {}", e),
+            SampleSource::Converted(samples) => samples.len(),
+            SampleSource::Pcm16 { bytes, .. } => bytes.len() / 2,
+        }
+    }
+
+    /// Samples of a range, borrowed when already converted
+    fn window(&self, range: Range<usize>) -> Cow<'_, [f32]> {
+        match self {
+            SampleSource::Converted(samples) => Cow::Borrowed(&samples[range]),
+            SampleSource::Pcm16 {
+                bytes,
+                pre_emphasis,
+            } => Cow::Owned(audio::pcm16_window(bytes, range, *pre_emphasis)),
         }
     }
 }
 
-impl std::error::Error for JsonError {}
+/// Receiver for events emitted while a request is still decoding
+pub type SegmentEmitter = Arc<dyn Fn(StreamEvent) + Send + Sync>;
 
-/// Validation errors for transcription options
-#[derive(Debug)]
-#[allow(dead_code, reason = "Struct kept for future validation error handling")]
-pub struct ValidationError {
-    /// Field name that failed validation
-    pub field: String,
-    /// Validation error message
-    pub message: String,
+/// Shift the segments an emitter receives by a piece's position in its request
+///
+/// # Arguments
+/// * `emitter` - Receiver for the shifted events
+/// * `offset_ms` - Start of the piece within the request, in milliseconds
+///
+/// # Returns
+/// * `SegmentEmitter` - Emitter adding the offset to every segment
+pub fn offset_emitter(emitter: SegmentEmitter, offset_ms: u64) -> SegmentEmitter {
+    if offset_ms == 0 {
+        return emitter;
+    }
+    let offset = offset_ms as f32 / 1000.0;
+    Arc::new(move |event| {
+        emitter(match event {
+            StreamEvent::Segment(segment) => StreamEvent::Segment(SegmentEvent {
+                start: segment.start + offset,
+                end: segment.end + offset,
+                ..segment
+            }),
+            discarded => discarded,
+        })
+    })
 }
 
-impl ValidationError {
-    /// Create a new validation error
-    #[allow(
-        dead_code,
-        reason = "Function kept for future validation error creation"
-    )]
-    pub fn new(field: &str, message: &str) -> Self {
+/// Segments streamed for one request
+///
+/// Whisper numbers segments from zero and times them from the start of the
+/// decoded window on every pass. The stream shifts each segment to its place
+/// in the request audio, numbers segments across windows, and tags them with
+/// the pass that produced them so a pass that is retried (temperature or
+/// greedy fallback) can be withdrawn with a `segments_discarded` event.
+pub struct SegmentStream {
+    emitter: SegmentEmitter,
+    /// Leading silence trimmed before decoding, in milliseconds
+    trim_offset_ms: u64,
+    progress: Mutex<StreamProgress>,
+}
+
+/// Position of a segment stream within its request
+#[derive(Default)]
+struct StreamProgress {
+    /// Start of the window being decoded, in milliseconds
+    window_offset_ms: u64,
+    /// Index given to the next segment
+    next_index: i32,
+    /// Number of passes started so far
+    passes_started: u32,
+    /// Passes not discarded so far, with the index of their first segment
+    passes: Vec<(u32, i32)>,
+}
+
+impl SegmentStream {
+    /// Create a stream for one request
+    ///
+    /// # Arguments
+    /// * `emitter` - Receiver for the stream's events
+    /// * `trim_offset_ms` - Leading silence trimmed before decoding
+    pub fn new(emitter: SegmentEmitter, trim_offset_ms: u64) -> Self {
         Self {
-            field: field.to_string(),
-            message: message.to_string(),
+            emitter,
+            trim_offset_ms,
+            progress: Mutex::new(StreamProgress::default()),
         }
     }
-}
 
-/// Validate transcription options
-#[allow(
-    dead_code,
-    reason = "Function kept for future transcription options validation"
+    /// Lock the progress, which stays consistent even if an emitter panicked
+    fn progress(&self) -> std::sync::MutexGuard<'_, StreamProgress> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the start of the window decoded next
+    ///
+    /// # Arguments
+    /// * `offset_ms` - Window start within the decoded samples, in milliseconds
+    pub fn set_window_offset(&self, offset_ms: u64) {
+        self.progress().window_offset_ms = offset_ms;
+    }
+
+    /// Start a decoding pass, which tags the segments emitted until the next one
+    pub fn begin_pass(&self) {
+        let mut progress = self.progress();
+        let pass = progress.passes_started;
+        let first_index = progress.next_index;
+        progress.passes.push((pass, first_index));
+        progress.passes_started += 1;
+    }
+
+    /// Number of passes kept so far, for a later `discard_since`
+    pub fn mark(&self) -> usize {
+        self.progress().passes.len()
+    }
+
+    /// Withdraw every pass started after `mark`
+    ///
+    /// The next segment reuses the index of the first withdrawn one.
+    ///
+    /// # Arguments
+    /// * `mark` - Value of `mark` before the passes were started
+    pub fn discard_since(&self, mark: usize) {
+        let discarded: Vec<(u32, i32)> = {
+            let mut progress = self.progress();
+            if mark >= progress.passes.len() {
+                return;
+            }
+            let discarded = progress.passes.split_off(mark);
+            progress.next_index = discarded[0].1;
+            discarded
+        };
+        for (pass, _) in discarded {
+            debug!("Discarding streamed segments of pass {}", pass);
+            (self.emitter)(StreamEvent::Discarded(SegmentsDiscardedEvent {
+                event: "segments_discarded".to_string(),
+                pass,
+            }));
+        }
+    }
+
+    /// Adapt the stream to whisper's new-segment callback
+    ///
+    /// # Returns
+    /// * `impl FnMut(SegmentCallbackData)` - Callback for `set_segment_callback_safe`
+    pub fn callback(self: &Arc<Self>) -> impl FnMut(SegmentCallbackData) + 'static {
+        let stream = Arc::clone(self);
+        move |data: SegmentCallbackData| {
+            let event = {
+                let mut progress = stream.progress();
+                let offset_secs =
+                    (stream.trim_offset_ms + progress.window_offset_ms) as f32 / 1000.0;
+                let index = progress.next_index;
+                progress.next_index += 1;
+                SegmentEvent {
+                    event: "segment".to_string(),
+                    pass: progress.passes.last().map_or(0, |(pass, _)| *pass),
+                    index,
+                    // whisper reports segment times in centiseconds
+                    start: offset_secs + data.start_timestamp as f32 / 100.0,
+                    end: offset_secs + data.end_timestamp as f32 / 100.0,
+                    text: data.text.trim().to_string(),
+                }
+            };
+            (stream.emitter)(StreamEvent::Segment(event))
+        }
+    }
+}
+
+/// Sampling setup a result was actually decoded with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecodingInfo {
+    /// Sampling strategy ("greedy" or "beam_search")
+    pub strategy: String,
+    /// Number of beams (beam search only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beam_size: Option<i32>,
+    /// Number of candidates sampled (greedy only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_of: Option<i32>,
+    /// Temperature of the pass that produced the result
+    pub temperature: f32,
+    /// Whether a temperature fallback or greedy retry produced the result
+    pub fallback_used: bool,
+}
+
+impl DecodingInfo {
+    /// Describe a decoding pass
+    ///
+    /// # Arguments
+    /// * `strategy` - Sampling strategy of the pass
+    /// * `temperature` - Temperature of the pass
+    /// * `fallback_used` - Whether the pass was a fallback
+    pub fn new(strategy: &SamplingStrategy, temperature: f32, fallback_used: bool) -> Self {
+        let (name, beam_size, best_of) = match *strategy {
+            SamplingStrategy::Greedy { best_of } => ("greedy", None, Some(best_of)),
+            SamplingStrategy::BeamSearch { beam_size, .. } => {
+                ("beam_search", Some(beam_size), None)
+            }
+        };
+        Self {
+            strategy: name.to_string(),
+            beam_size,
+            best_of,
+            temperature,
+            fallback_used,
+        }
+    }
+}
+
+/// Beam-search patience used when none is configured
+pub const DEFAULT_PATIENCE: f32 = 1.0;
+
+/// Sampling strategy a configuration decodes with
+///
+/// Deterministic mode and configurations without beam search decode
+/// greedily with a single candidate.
+///
+/// # Arguments
+/// * `config` - Effective configuration for the pass
+///
+/// # Returns
+/// * `SamplingStrategy` - Strategy passed to Whisper
+pub fn sampling_strategy(config: &TranscriptionConfig) -> SamplingStrategy {
+    if config.deterministic || !config.use_beam_search {
+        SamplingStrategy::Greedy { best_of: 1 }
+    } else {
+        SamplingStrategy::BeamSearch {
+            beam_size: config.beam_size.unwrap_or(5),
+            patience: config.patience.unwrap_or(DEFAULT_PATIENCE),
+        }
+    }
+}
+
+/// Number of threads a configuration decodes with
+///
+/// Deterministic mode uses a single thread so floating-point reductions
+/// happen in order; otherwise the configured count is used, falling back to
+/// the available parallelism.
+///
+/// # Arguments
+/// * `config` - Effective configuration for the pass
+///
+/// # Returns
+/// * `usize` - Thread count passed to Whisper
+pub fn thread_count(config: &TranscriptionConfig) -> usize {
+    if config.deterministic {
+        return 1;
+    }
+    config.threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    })
+}
+
+/// Server-side timings of a single request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestMetrics {
+    /// Time spent converting and preprocessing the input audio
+    pub decode_ms: u64,
+    /// Time spent running Whisper on the prepared samples
+    pub inference_ms: u64,
+    /// Time the audio waited in the buffer before transcription started
+    pub queue_wait_ms: u64,
+    /// Duration of the input audio
+    pub audio_duration_ms: u64,
+}
+
+/// Transcription segment with timing information
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranscriptionSegment {
+    /// Start time in seconds
+    pub start: f32,
+    /// End time in seconds
+    pub end: f32,
+    /// Text content of the segment
+    pub text: String,
+    /// Confidence score (0.0 to 1.0)
+    pub confidence: Option<f32>,
+    /// Whether a long pause before the segment suggests a speaker change (with `--speaker-hints`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub likely_new_speaker: Option<bool>,
+}
+
+/// Map a Whisper language id to its language code
+///
+/// # Arguments
+/// * `lang_id` - Language id reported by the Whisper state
+///
+/// # Returns
+/// * `Option<String>` - Language code, or None if the id is not a known language
+pub fn language_from_id(lang_id: i32) -> Option<String> {
+    whisper_rs::get_lang_str(lang_id).map(str::to_string)
+}
+
+/// Transcription error types
+#[derive(Debug)]
+pub enum TranscriptionError {
+    WhisperContextError(String),
+    AudioDataError(String),
+    TranscriptionFailed(String),
+    ConfigurationError(String),
+    /// The request was rejected before transcription
+    InvalidRequest(JsonError),
+    /// Translation was requested but the loaded model is English-only
+    TranslateRequiresMultilingual,
+    /// The request arrived faster than `--max-rps` allows
+    RateLimited,
+    /// No Whisper state could be created even after retrying
+    StateExhausted(String),
+    /// The loaded model lacks a component needed for transcription
+    InvalidModelArchitecture(String),
+    /// The declared duration disagrees with the audio and `--strict-duration` is set
+    DurationMismatch {
+        declared_ms: u64,
+        computed_ms: u64,
+    },
+    /// A client connected while `--max-connections` connections were open
+    TooManyConnections,
+}
+
+impl std::fmt::Display for TranscriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscriptionError::WhisperContextError(e) => write!(f, "Whisper context error: {}", e),
+            TranscriptionError::AudioDataError(e) => write!(f, "Audio data error: {}", e),
+            TranscriptionError::TranscriptionFailed(e) => write!(f, "Transcription failed: {}", e),
+            TranscriptionError::ConfigurationError(e) => write!(f, "Configuration error: {}", e),
+            TranscriptionError::InvalidRequest(e) => write!(f, "{}", e),
+            TranscriptionError::TranslateRequiresMultilingual => write!(
+                f,
+                "Translation requires a multilingual model, but the loaded model is English-only"
+            ),
+            TranscriptionError::RateLimited => write!(f, "Request rate limit exceeded"),
+            TranscriptionError::StateExhausted(e) => {
+                write!(f, "Could not create a Whisper state: {}", e)
+            }
+            TranscriptionError::InvalidModelArchitecture(e) => {
+                write!(f, "Invalid model architecture: {}", e)
+            }
+            TranscriptionError::DurationMismatch {
+                declared_ms,
+                computed_ms,
+            } => write!(
+                f,
+                "Declared duration of {}ms does not match the {}ms of audio received",
+                declared_ms, computed_ms
+            ),
+            TranscriptionError::TooManyConnections => {
+                write!(f, "Too many open connections, try again later")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TranscriptionError {}
+
+impl TranscriptionError {
+    /// Stable machine-readable code for the error
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            TranscriptionError::WhisperContextError(_) => "WHISPER_CONTEXT_ERROR",
+            TranscriptionError::AudioDataError(_) => "AUDIO_DATA_ERROR",
+            TranscriptionError::TranscriptionFailed(_) => "TRANSCRIPTION_FAILED",
+            TranscriptionError::ConfigurationError(_) => "CONFIGURATION_ERROR",
+            TranscriptionError::InvalidRequest(e) => e.error_code(),
+            TranscriptionError::TranslateRequiresMultilingual => "TRANSLATE_REQUIRES_MULTILINGUAL",
+            TranscriptionError::RateLimited => "RATE_LIMITED",
+            TranscriptionError::StateExhausted(_) => "STATE_EXHAUSTED",
+            TranscriptionError::InvalidModelArchitecture(_) => "INVALID_MODEL_ARCHITECTURE",
+            TranscriptionError::DurationMismatch { .. } => "DURATION_MISMATCH",
+            TranscriptionError::TooManyConnections => "TOO_MANY_CONNECTIONS",
+        }
+    }
+}
+
+impl From<JsonError> for TranscriptionError {
+    fn from(error: JsonError) -> Self {
+        match error {
+            JsonError::AudioDataError(e) => TranscriptionError::AudioDataError(e),
+            other => TranscriptionError::InvalidRequest(other),
+        }
+    }
+}
+
+impl From<&TranscriptionError> for TranscriptionResult {
+    /// Build the failed result reported to the client for an error
+    fn from(error: &TranscriptionError) -> Self {
+        TranscriptionResult {
+            success: false,
+            error: Some(error.to_string()),
+            error_code: Some(error.error_code().to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// JSON parsing and validation errors
+#[derive(Debug)]
+#[allow(
+    dead_code,
+    reason = "Enum kept for future JSON validation error handling"
 )]
+pub enum JsonError {
+    /// Invalid JSON format
+    InvalidJson(String),
+    /// Missing required field
+    MissingField(String),
+    /// Invalid field value
+    InvalidFieldValue(String, String),
+    /// Invalid base64 encoding
+    InvalidBase64(String),
+    /// Audio data validation failed
+    AudioDataError(String),
+    /// Audio data decoded to zero bytes
+    EmptyAudio(String),
+    /// Reading the request from stdin failed
+    ReadError(String),
+}
+
+impl JsonError {
+    /// Stable machine-readable code for the error
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            JsonError::InvalidJson(_) => "INVALID_JSON",
+            JsonError::MissingField(_) => "MISSING_FIELD",
+            JsonError::InvalidFieldValue(_, _) => "INVALID_FIELD_VALUE",
+            JsonError::InvalidBase64(_) => "INVALID_BASE64",
+            JsonError::AudioDataError(_) => "AUDIO_DATA_ERROR",
+            JsonError::EmptyAudio(_) => "EMPTY_AUDIO",
+            JsonError::ReadError(_) => "READ_ERROR",
+        }
+    }
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::InvalidJson(e) => write!(f, "Invalid JSON: {}", e),
+            JsonError::MissingField(field) => write!(f, "Missing required field: {}", field),
+            JsonError::InvalidFieldValue(field, value) => {
+                write!(f, "Invalid value for field '{}': {}", field, value)
+            }
+            JsonError::InvalidBase64(e) => write!(f, "Invalid base64 encoding: {}", e),
+            JsonError::AudioDataError(e) => write!(f, "Audio data error: {}", e),
+            JsonError::EmptyAudio(e) => write!(f, "Empty audio data: {}", e),
+            JsonError::ReadError(e) => write!(f, "Read error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(error: serde_json::Error) -> Self {
+        JsonError::InvalidJson(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for JsonError {
+    fn from(error: std::io::Error) -> Self {
+        JsonError::ReadError(error.to_string())
+    }
+}
+
+/// Validation errors for transcription options
+#[derive(Debug)]
+pub struct ValidationError {
+    /// Field name that failed validation
+    pub field: String,
+    /// Validation error message
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Create a new validation error
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Validate transcription options
 pub fn validate_transcription_options(
     options: &TranscriptionOptions,
 ) -> Result<Vec<ValidationError>, JsonError> {
@@ -371,6 +1416,21 @@ pub fn validate_transcription_options(
         ));
     }
 
+    // Validate temperature schedule if specified
+    if let Some(ref temperatures) = options.temperatures {
+        if temperatures.is_empty() {
+            errors.push(ValidationError::new(
+                "temperatures",
+                "Temperature list must not be empty",
+            ));
+        } else if temperatures.iter().any(|t| !(0.0..=1.0).contains(t)) {
+            errors.push(ValidationError::new(
+                "temperatures",
+                "Each temperature must be between 0.0 and 1.0",
+            ));
+        }
+    }
+
     // Validate beam size if specified
     if let Some(beam_size) = options.beam_size
         && beam_size < 1
@@ -381,6 +1441,34 @@ pub fn validate_transcription_options(
         ));
     }
 
+    // Validate beam-search patience if specified
+    if let Some(patience) = options.patience
+        && (patience.is_nan() || patience <= 0.0)
+    {
+        errors.push(ValidationError::new(
+            "patience",
+            "Patience must be greater than 0.0",
+        ));
+    }
+
+    // Validate sample rate if specified
+    if let Some(sample_rate) = options.sample_rate
+        && !audio::INPUT_SAMPLE_RATES.contains(&sample_rate)
+    {
+        errors.push(ValidationError::new(
+            "sample_rate",
+            &audio::sample_rate_error(sample_rate),
+        ));
+    }
+
+    // Validate channel count if specified
+    if options.channels == Some(0) {
+        errors.push(ValidationError::new(
+            "channels",
+            "Channel count must be greater than 0",
+        ));
+    }
+
     // Validate max tokens if specified
     if let Some(max_tokens) = options.max_tokens
         && max_tokens == 0
@@ -391,6 +1479,16 @@ pub fn validate_transcription_options(
         ));
     }
 
+    // Validate grammar if specified
+    if let Some(ref grammar) = options.grammar
+        && let Err(e) = ParsedGrammar::parse(grammar)
+    {
+        errors.push(ValidationError::new(
+            "grammar",
+            &format!("Invalid grammar: {}", e),
+        ));
+    }
+
     if errors.is_empty() {
         Ok(Vec::new())
     } else {
@@ -416,60 +1514,395 @@ pub fn options_to_config(options: TranscriptionOptions) -> TranscriptionConfig {
         include_timestamps: options.include_timestamps.unwrap_or(false),
         max_tokens: options.max_tokens,
         temperature: options.temperature.unwrap_or(0.0),
+        temperatures: options.temperatures,
         use_beam_search: options.use_beam_search.unwrap_or(false),
         beam_size: options.beam_size,
+        patience: options.patience,
         suppress_blank: options.suppress_blank.unwrap_or(true),
         word_timestamps: options.word_timestamps.unwrap_or(false),
+        grammar: options.grammar,
+        ..Default::default()
     }
 }
 
-/// Transcription service using whisper-rs
-pub struct TranscriptionService {
-    context: WhisperContext,
-    config: TranscriptionConfig,
+/// Longest run of words considered when de-duplicating a chunk seam
+const MAX_SEAM_WORDS: usize = 16;
+
+/// Normalise a word for seam comparison (case and surrounding punctuation ignored)
+fn normalize_seam_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
 }
 
-impl std::fmt::Debug for TranscriptionService {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("TranscriptionService")
-            .field("config", &self.config)
-            .field("model_info", &self.model_info())
-            .finish()
+/// Count the leading words of `next` that repeat the trailing words of `previous`
+///
+/// Consecutive chunks share an overlap region, so the words spoken there
+/// appear at the end of one chunk's text and again at the start of the next.
+///
+/// # Arguments
+/// * `previous` - Text transcribed so far
+/// * `next` - Text of the following chunk
+///
+/// # Returns
+/// * `usize` - Number of leading words of `next` to drop
+pub fn seam_overlap_words(previous: &str, next: &str) -> usize {
+    let previous: Vec<String> = previous
+        .split_whitespace()
+        .map(normalize_seam_word)
+        .collect();
+    let next: Vec<String> = next.split_whitespace().map(normalize_seam_word).collect();
+    let longest = previous.len().min(next.len()).min(MAX_SEAM_WORDS);
+
+    (1..=longest)
+        .rev()
+        .find(|&count| previous[previous.len() - count..] == next[..count])
+        .unwrap_or(0)
+}
+
+/// Remove the first `count` words from a list of segments, dropping segments
+/// that become empty
+fn drop_leading_segment_words(segments: &mut Vec<TranscriptionSegment>, mut count: usize) {
+    while count > 0 && !segments.is_empty() {
+        let words: Vec<&str> = segments[0].text.split_whitespace().collect();
+        if words.len() <= count {
+            count -= words.len();
+            segments.remove(0);
+        } else {
+            segments[0].text = words[count..].join(" ");
+            count = 0;
+        }
     }
 }
 
-impl TranscriptionService {
-    /// Create a new transcription service with the given Whisper context and configuration
-    ///
-    /// # Arguments
-    /// * `context` - The loaded Whisper context
-    /// * `config` - Transcription configuration
-    ///
-    /// # Returns
-    /// * `Result<Self, TranscriptionError>` - New transcription service on success
-    pub fn new(
-        context: WhisperContext,
-        config: TranscriptionConfig,
-    ) -> Result<Self, TranscriptionError> {
-        debug!("Creating transcription service with config: {:?}", config);
-        Ok(Self { context, config })
+/// Join segment texts with single spaces in one pass
+///
+/// # Arguments
+/// * `segments` - Segments in time order
+///
+/// # Returns
+/// * `String` - The combined text
+pub fn join_segment_text(segments: &[TranscriptionSegment]) -> String {
+    let capacity = segments.iter().map(|segment| segment.text.len() + 1).sum();
+    let mut text = String::with_capacity(capacity);
+    for segment in segments {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&segment.text);
     }
+    text
+}
 
-    /// Perform transcription on audio data
-    ///
-    /// # Arguments
-    /// * `audio_data` - Raw audio data bytes (16kHz mono PCM)
-    ///
-    /// # Returns
-    /// * `Result<TranscriptionResult, TranscriptionError>` - Transcription result
-    pub fn transcribe(&self, audio_data: &[u8]) -> Result<TranscriptionResult, TranscriptionError> {
-        let start_time = std::time::Instant::now();
+/// Keep at most `max_segments` segments
+///
+/// # Arguments
+/// * `segments` - Segments in time order, truncated in place
+/// * `max_segments` - Most segments kept
+///
+/// # Returns
+/// * `Option<usize>` - Number of segments dropped, None if nothing was dropped
+pub fn cap_segments(
+    segments: &mut Vec<TranscriptionSegment>,
+    max_segments: usize,
+) -> Option<usize> {
+    let dropped = segments
+        .len()
+        .checked_sub(max_segments)
+        .filter(|&n| n > 0)?;
+    segments.truncate(max_segments);
+    Some(dropped)
+}
+
+/// Drop segments whose text is empty after trimming
+///
+/// # Arguments
+/// * `segments` - Segments as produced by Whisper
+///
+/// # Returns
+/// * `Vec<TranscriptionSegment>` - Segments that carry text
+pub fn keep_non_empty_segments(segments: Vec<TranscriptionSegment>) -> Vec<TranscriptionSegment> {
+    segments
+        .into_iter()
+        .filter(|segment| !segment.text.trim().is_empty())
+        .collect()
+}
+
+/// Filter out segments shorter than a minimum duration
+///
+/// Very short segments are usually noise artifacts. Their text is either
+/// discarded or, with `merge`, appended to the previous segment (or
+/// prepended to the next one when no earlier segment exists).
+///
+/// # Arguments
+/// * `segments` - Segments in time order
+/// * `min_duration_ms` - Shortest segment kept, in milliseconds
+/// * `merge` - Whether short segments' text is merged into a neighbour
+///
+/// # Returns
+/// * `(Vec<TranscriptionSegment>, usize)` - Remaining segments and the number filtered out
+pub fn filter_short_segments(
+    segments: Vec<TranscriptionSegment>,
+    min_duration_ms: u64,
+    merge: bool,
+) -> (Vec<TranscriptionSegment>, usize) {
+    let is_short = |segment: &TranscriptionSegment| {
+        (segment.end - segment.start) * 1000.0 < min_duration_ms as f32
+    };
+    let mut kept: Vec<TranscriptionSegment> = Vec::with_capacity(segments.len());
+    // Short segments seen before the first kept one, waiting to be merged forward
+    let mut pending: Option<TranscriptionSegment> = None;
+    let mut filtered = 0;
+
+    for mut segment in segments {
+        if !is_short(&segment) {
+            if let Some(short) = pending.take() {
+                segment.text = format!("{} {}", short.text, segment.text);
+                segment.start = short.start;
+            }
+            kept.push(segment);
+            continue;
+        }
+
+        filtered += 1;
+        if !merge {
+            continue;
+        }
+        match kept.last_mut().or(pending.as_mut()) {
+            Some(previous) => {
+                previous.text.push(' ');
+                previous.text.push_str(&segment.text);
+                previous.end = segment.end;
+            }
+            None => pending = Some(segment),
+        }
+    }
+
+    // Every segment was short: keep their merged text as one segment
+    if let Some(short) = pending {
+        kept.push(short);
+        filtered -= 1;
+    }
+    (kept, filtered)
+}
+
+/// Add two optional segment counts, keeping whichever is present
+fn add_counts(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Average segment log probabilities weighted by their token counts
+///
+/// # Arguments
+/// * `segments` - Average log probability and token count of each segment
+///
+/// # Returns
+/// * `Option<f32>` - Weighted average, or None if there are no tokens
+pub fn weighted_avg_logprob(segments: impl IntoIterator<Item = (f32, usize)>) -> Option<f32> {
+    let (sum, tokens) =
+        segments
+            .into_iter()
+            .fold((0.0f64, 0usize), |(sum, tokens), (avg_logprob, count)| {
+                (sum + avg_logprob as f64 * count as f64, tokens + count)
+            });
+    (tokens > 0).then(|| (sum / tokens as f64) as f32)
+}
+
+/// Append the result of the next chunk onto the merged result
+///
+/// # Arguments
+/// * `merged` - Result accumulated from earlier chunks
+/// * `next` - Result of the following chunk
+/// * `offset_secs` - Start of the following chunk within the full audio
+/// * `dedup_seam` - Whether the chunks overlap and repeated words should be dropped
+pub fn merge_window_result(
+    merged: &mut TranscriptionResult,
+    mut next: TranscriptionResult,
+    offset_secs: f32,
+    dedup_seam: bool,
+) {
+    let repeated = if dedup_seam {
+        seam_overlap_words(&merged.text, &next.text)
+    } else {
+        0
+    };
+    if repeated > 0 {
+        debug!("Dropping {} repeated words at chunk seam", repeated);
+    }
+
+    let next_text = next
+        .text
+        .split_whitespace()
+        .skip(repeated)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !next_text.is_empty() {
+        if !merged.text.is_empty() {
+            merged.text.push(' ');
+        }
+        merged.text.push_str(&next_text);
+    }
+
+    if let Some(mut segments) = next.segments.take() {
+        drop_leading_segment_words(&mut segments, repeated);
+        for segment in &mut segments {
+            segment.start += offset_secs;
+            segment.end += offset_secs;
+        }
+        merged
+            .segments
+            .get_or_insert_with(Vec::new)
+            .extend(segments);
+    }
+
+    if merged.language.is_none() {
+        merged.language = next.language;
+    }
+    merged.segments_total = add_counts(merged.segments_total, next.segments_total);
+    merged.segments_emitted = add_counts(merged.segments_emitted, next.segments_emitted);
+    merged.segments_too_short = add_counts(merged.segments_too_short, next.segments_too_short);
+    let logprobs = [
+        (merged.avg_logprob, merged.logprob_tokens),
+        (next.avg_logprob, next.logprob_tokens),
+    ];
+    merged.avg_logprob =
+        weighted_avg_logprob(logprobs.into_iter().filter_map(|(avg_logprob, tokens)| {
+            avg_logprob.map(|avg_logprob| (avg_logprob, tokens))
+        }));
+    merged.logprob_tokens += next.logprob_tokens;
+    if let (Some(merged), Some(next)) = (&mut merged.decoding, next.decoding) {
+        // Report the hottest pass any chunk needed
+        merged.fallback_used |= next.fallback_used;
+        merged.temperature = merged.temperature.max(next.temperature);
+    }
+}
+
+/// Silence decoded by a keepalive inference (100 ms at 16 kHz)
+const KEEPALIVE_SAMPLES: usize = 1600;
+
+/// Run a tiny silent inference so the model's pages stay resident
+///
+/// # Arguments
+/// * `context` - Loaded Whisper context
+///
+/// # Returns
+/// * `Result<(), TranscriptionError>` - Ok if the inference ran
+pub fn warm_model(context: &WhisperContext) -> Result<(), TranscriptionError> {
+    let mut state = context
+        .create_state()
+        .map_err(|e| TranscriptionError::WhisperContextError(e.to_string()))?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads(1);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    state
+        .full(params, &[0.0; KEEPALIVE_SAMPLES])
+        .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?;
+    Ok(())
+}
+
+/// Transcription service using whisper-rs
+pub struct TranscriptionService {
+    context: Arc<WhisperContext>,
+    config: TranscriptionConfig,
+    state_pool: StatePool<WhisperState>,
+    segment_emitter: Option<SegmentEmitter>,
+}
+
+impl std::fmt::Debug for TranscriptionService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranscriptionService")
+            .field("config", &self.config)
+            .field("model_info", &self.model_info())
+            .finish()
+    }
+}
+
+impl TranscriptionService {
+    /// Create a new transcription service with the given Whisper context and configuration
+    ///
+    /// # Arguments
+    /// * `context` - The loaded Whisper context
+    /// * `config` - Transcription configuration
+    ///
+    /// # Returns
+    /// * `Result<Self, TranscriptionError>` - New transcription service on success
+    pub fn new(
+        context: WhisperContext,
+        config: TranscriptionConfig,
+    ) -> Result<Self, TranscriptionError> {
+        debug!("Creating transcription service with config: {:?}", config);
+        let state_pool = StatePool::new(config.state_pool_size);
+        Ok(Self {
+            context: Arc::new(context),
+            config,
+            state_pool,
+            segment_emitter: None,
+        })
+    }
+
+    /// Emit each segment as soon as it is decoded, before the pass finishes
+    ///
+    /// Segments of passes that are later retried (temperature or greedy
+    /// fallback) are withdrawn with a discard event once the retry starts.
+    /// The translation pass of `with_translation` is not streamed.
+    ///
+    /// # Arguments
+    /// * `emitter` - Receiver for decoded segments, None to stop emitting
+    pub fn set_segment_emitter(&mut self, emitter: Option<SegmentEmitter>) {
+        self.segment_emitter = emitter;
+    }
+
+    /// Run a keepalive inference on the loaded model
+    ///
+    /// # Returns
+    /// * `Result<(), TranscriptionError>` - Ok if the inference ran
+    pub fn warm(&self) -> Result<(), TranscriptionError> {
+        warm_model(&self.context)
+    }
+
+    /// Perform transcription on audio data with the service's configuration
+    ///
+    /// # Arguments
+    /// * `audio_data` - Raw audio data bytes (16kHz mono PCM)
+    ///
+    /// # Returns
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Transcription result
+    #[allow(
+        dead_code,
+        reason = "Convenience kept for callers without per-request options"
+    )]
+    pub fn transcribe(&self, audio_data: &[u8]) -> Result<TranscriptionResult, TranscriptionError> {
+        self.transcribe_with_config(audio_data, &audio::InputAudioInfo::default(), &self.config)
+    }
+
+    /// Perform transcription on audio data with a per-request configuration
+    ///
+    /// # Arguments
+    /// * `audio_data` - Raw audio data bytes (16kHz mono PCM)
+    /// * `input_audio` - Format the audio was received in, echoed in the result
+    /// * `config` - Configuration for this request, usually from `request_config`
+    ///
+    /// # Returns
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Transcription result
+    pub fn transcribe_with_config(
+        &self,
+        audio_data: &[u8],
+        input_audio: &audio::InputAudioInfo,
+        config: &TranscriptionConfig,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        let start_time = std::time::Instant::now();
 
         debug!(
             "Starting transcription on {} bytes of audio data",
             audio_data.len()
         );
 
+        check_translation_supported(config, self.context.is_multilingual())?;
+
         // Validate audio data
         if audio_data.is_empty() {
             debug!("Audio data validation failed: empty data");
@@ -478,15 +1911,285 @@ impl TranscriptionService {
             ));
         }
 
-        // Create full parameters for transcription
-        debug!("Creating transcription parameters with beam search");
-        let mut params = FullParams::new(SamplingStrategy::BeamSearch {
-            beam_size: self.config.beam_size.unwrap_or(5),
-            patience: 1.0,
+        // Clipping is measured on the converted samples Whisper sees
+        let mut input_audio = input_audio.clone();
+
+        if !audio_data.len().is_multiple_of(2) {
+            warn!("Audio data has an odd number of bytes, dropping the trailing byte");
+        }
+        let audio_duration_ms = audio::samples_to_ms(audio_data.len() / 2);
+        input_audio.clipping_ratio = audio::clipping_ratio(audio::pcm16_samples(audio_data));
+        if input_audio.clipping_ratio > audio::CLIPPING_WARNING_RATIO {
+            warn!(
+                "{:.1}% of input samples are clipped",
+                input_audio.clipping_ratio * 100.0
+            );
+        }
+
+        if skips_as_silent(config, audio_data) {
+            info!("Skipping inference: no speech detected");
+            let elapsed_ms = start_time.elapsed().as_millis() as u64;
+            return Ok(TranscriptionResult {
+                success: true,
+                duration_ms: Some(elapsed_ms),
+                input_audio: Some(input_audio),
+                metrics: Some(RequestMetrics {
+                    decode_ms: elapsed_ms,
+                    inference_ms: 0,
+                    queue_wait_ms: 0,
+                    audio_duration_ms,
+                }),
+                no_speech: Some(true),
+                reason: Some(NO_SPEECH_DETECTED.to_string()),
+                ..Default::default()
+            });
+        }
+
+        let low_memory = config.low_memory && config.chunk_ms.is_some();
+        let mut audio_data_f32 = Vec::new();
+        let mut silence_trim = None;
+        if !low_memory {
+            debug!("Converting audio data to f32 format");
+            // Convert audio data to f32 (whisper-rs expects f32 samples)
+            audio_data_f32 = audio::pcm16_samples(audio_data).collect();
+            debug!(
+                "Converted {} bytes to {} f32 samples",
+                audio_data.len(),
+                audio_data_f32.len()
+            );
+
+            let mut pipeline = audio::AudioPipeline::new(&config.preprocess_order);
+            if config.trim_silence {
+                pipeline = pipeline.with_trim_silence(audio::DEFAULT_SILENCE_THRESHOLD);
+            }
+            if let Some(coef) = config.pre_emphasis {
+                pipeline = pipeline.with_pre_emphasis(coef);
+            }
+            if let Some(settings) = config.agc {
+                pipeline = pipeline.with_agc(settings);
+            }
+            silence_trim = pipeline.run(&mut audio_data_f32);
+        }
+        let source = if low_memory {
+            debug!("Converting audio data to f32 one window at a time");
+            SampleSource::Pcm16 {
+                bytes: audio_data,
+                pre_emphasis: config.pre_emphasis,
+            }
+        } else {
+            SampleSource::Converted(&audio_data_f32)
+        };
+        let decode_time = start_time.elapsed();
+
+        // Perform the transcription
+        debug!("Checking out Whisper state for transcription");
+        let create_state = || {
+            debug!("Creating new Whisper state");
+            self.context.create_state()
+        };
+        let mut state = match self.state_pool.checkout_with_retry(
+            create_state,
+            STATE_CREATE_RETRIES,
+            STATE_CREATE_BACKOFF,
+        ) {
+            Ok(state) => {
+                debug!("Whisper state ready");
+                state
+            }
+            Err(e) => {
+                error!("Failed to create Whisper state after retrying: {}", e);
+                return Err(TranscriptionError::StateExhausted(e.to_string()));
+            }
+        };
+
+        // Apply per-language overrides once the spoken language is known
+        let detection_len = match (low_memory, config.chunk_ms) {
+            (true, Some(chunk_ms)) => source.len().min(audio::ms_to_samples(chunk_ms)),
+            _ => source.len(),
+        };
+        let config = self.resolve_config(config, &mut state, &source.window(0..detection_len));
+
+        let stream = self.segment_emitter.as_ref().map(|emitter| {
+            let trim_offset_ms = silence_trim.map_or(0, |trim| trim.leading_ms());
+            Arc::new(SegmentStream::new(Arc::clone(emitter), trim_offset_ms))
         });
 
+        debug!("Starting audio processing with Whisper");
+        let mut decode = |pass: &TranscriptionConfig| {
+            // Only the transcription is streamed, not the added translation
+            let stream = stream
+                .as_ref()
+                .filter(|_| !(pass.with_translation && pass.translate_to_english));
+            if pass.greedy_fallback {
+                let mark = stream.map(|stream| stream.mark());
+                retry_empty_with_greedy(pass, |attempt| {
+                    if let (Some(stream), Some(mark)) = (stream, mark) {
+                        stream.discard_since(mark);
+                    }
+                    self.decode_pass(&mut state, attempt, &source, start_time, stream)
+                })
+            } else {
+                self.decode_pass(&mut state, pass, &source, start_time, stream)
+            }
+        };
+        let mut outcome = if config.with_translation {
+            transcribe_with_translation(&config, &mut decode)
+        } else {
+            decode(&config)
+        };
+
+        if let (Ok(result), Some(trim)) = (&mut outcome, silence_trim) {
+            result.trimmed_leading_ms = Some(trim.leading_ms());
+            result.trimmed_trailing_ms = Some(trim.trailing_ms());
+        }
+        if let Ok(ref mut result) = outcome {
+            result.input_audio = Some(input_audio);
+            result.metrics = Some(RequestMetrics {
+                decode_ms: decode_time.as_millis() as u64,
+                inference_ms: (start_time.elapsed() - decode_time).as_millis() as u64,
+                queue_wait_ms: 0,
+                audio_duration_ms,
+            });
+            if let Some(ref mut segments) = result.segments {
+                if let Some(trim) = silence_trim {
+                    offset_segment_timestamps(segments, trim.leading_ms());
+                }
+                if let Some(min_gap_ms) = config.speaker_gap_ms {
+                    annotate_speaker_hints(segments, min_gap_ms);
+                }
+                round_segment_timestamps(segments, config.timestamp_decimals);
+                if let Some(max_segments) = config.max_segments {
+                    result.segments_truncated = cap_segments(segments, max_segments);
+                    if let Some(dropped) = result.segments_truncated {
+                        warn!(
+                            "Dropped {} segments beyond the limit of {}",
+                            dropped, max_segments
+                        );
+                    }
+                }
+            }
+        }
+        match outcome {
+            Ok(_) => {
+                self.state_pool.checkin(state);
+                debug!(
+                    "Whisper state returned to pool ({} idle)",
+                    self.state_pool.idle_count()
+                );
+            }
+            Err(ref e) => {
+                // A failed decode may leave the state unusable, so it is recreated next time
+                error!("Transcription failed: {}", e);
+                debug!("Discarding Whisper state after failure");
+            }
+        }
+        outcome
+    }
+
+    /// Run one decoding pass over the prepared samples
+    ///
+    /// # Arguments
+    /// * `state` - Whisper state to decode with
+    /// * `config` - Effective configuration for this pass
+    /// * `samples` - Audio samples of the request
+    /// * `start_time` - When the request started, for the reported duration
+    /// * `stream` - Stream receiving segments as they are decoded, if any
+    ///
+    /// # Returns
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Result of the pass
+    fn decode_pass(
+        &self,
+        state: &mut WhisperState,
+        config: &TranscriptionConfig,
+        samples: &SampleSource,
+        start_time: std::time::Instant,
+        stream: Option<&Arc<SegmentStream>>,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        let mut params = Self::build_params(config)?;
+        if let Some(stream) = stream {
+            params.set_segment_callback_safe(stream.callback());
+            stream.set_window_offset(0);
+        }
+        let window_samples = config.chunk_ms.map(audio::ms_to_samples).unwrap_or(0);
+        if window_samples > 0 && samples.len() > window_samples {
+            self.transcribe_windows(
+                state,
+                config,
+                &params,
+                samples,
+                window_samples,
+                start_time,
+                stream,
+            )
+        } else {
+            let samples = samples.window(0..samples.len());
+            self.decode_with_fallback(state, config, &params, &samples, start_time, stream)
+        }
+    }
+
+    /// Resolve the configuration for a request
+    ///
+    /// When the language is auto-detected and per-language overrides exist,
+    /// the language is detected up front so its override can be applied.
+    /// This runs an extra mel conversion and detection pass over the samples,
+    /// so it is skipped when no overrides are configured. Detection failures
+    /// fall back to the unmodified configuration.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration of the request before overrides
+    /// * `state` - Whisper state used for detection
+    /// * `samples` - Audio samples of the request
+    ///
+    /// # Returns
+    /// * `TranscriptionConfig` - Effective configuration for this request
+    fn resolve_config(
+        &self,
+        config: &TranscriptionConfig,
+        state: &mut WhisperState,
+        samples: &[f32],
+    ) -> TranscriptionConfig {
+        let auto_detect = matches!(config.language.as_deref(), None | Some("auto"));
+        if !auto_detect || config.language_overrides.is_empty() {
+            return config.clone();
+        }
+
+        let threads = thread_count(config);
+        let detected = state
+            .pcm_to_mel(samples, threads)
+            .and_then(|_| state.lang_detect(0, threads));
+        match detected.map(|(lang_id, _)| whisper_rs::get_lang_str(lang_id)) {
+            Ok(Some(language)) => {
+                debug!("Detected language {} before decoding", language);
+                config_for_detected_language(config, language)
+            }
+            Ok(None) => {
+                warn!("Language detection returned an unknown language id");
+                config.clone()
+            }
+            Err(e) => {
+                warn!("Language detection failed, skipping overrides: {}", e);
+                config.clone()
+            }
+        }
+    }
+
+    /// Build the decoding parameters for a configuration
+    ///
+    /// # Arguments
+    /// * `config` - Effective configuration for this request
+    ///
+    /// # Returns
+    /// * `Result<FullParams, TranscriptionError>` - Parameters on success, error if the grammar is invalid
+    fn build_params(
+        config: &TranscriptionConfig,
+    ) -> Result<FullParams<'_, '_>, TranscriptionError> {
+        // Create full parameters for transcription
+        let strategy = sampling_strategy(config);
+        debug!("Creating transcription parameters with {:?}", strategy);
+        let mut params = FullParams::new(strategy);
+
         // Set language if specified
-        if let Some(ref lang) = self.config.language {
+        if let Some(ref lang) = config.language {
             debug!("Setting language to: {}", lang);
             params.set_language(Some(lang.as_str()));
         } else {
@@ -494,200 +2197,348 @@ impl TranscriptionService {
         }
 
         // Set translation to English if requested
-        if self.config.translate_to_english {
+        if config.translate_to_english {
             debug!("Translation to English enabled");
             params.set_translate(true);
         }
 
-        // Set temperature
-        debug!("Setting temperature to: {}", self.config.temperature);
-        params.set_temperature(self.config.temperature);
-
         // Set token suppression
-        debug!("Setting suppress_blank to: {}", self.config.suppress_blank);
-        params.set_suppress_blank(self.config.suppress_blank);
+        debug!("Setting suppress_blank to: {}", config.suppress_blank);
+        params.set_suppress_blank(config.suppress_blank);
 
         // Set word timestamps if enabled
-        if self.config.word_timestamps {
+        if config.word_timestamps {
             info!("Word timestamps enabled");
             params.set_no_timestamps(false);
         }
 
         // Set max tokens if specified
-        if let Some(max_tokens) = self.config.max_tokens {
+        if let Some(max_tokens) = config.max_tokens {
             debug!("Setting max tokens to: {}", max_tokens);
             params.set_max_tokens(max_tokens as i32);
         }
 
-        // Set number of threads (use system optimal if not specified)
-        let num_threads = 4; // Default to 4 threads
+        // Phrase grouping needs per-token timings
+        if config.phrase_gap_ms.is_some() {
+            debug!("Enabling token timestamps for phrase grouping");
+            params.set_token_timestamps(true);
+        }
+
+        // Cap segment length (requires token-level timestamps)
+        if let Some(max_segment_chars) = config.max_segment_chars {
+            debug!("Limiting segments to {} characters", max_segment_chars);
+            params.set_token_timestamps(true);
+            params.set_max_len(max_segment_chars);
+        }
+
+        // Constrain decoding to the grammar if one is configured
+        if let Some(ref source) = config.grammar {
+            let grammar = ParsedGrammar::parse(source).map_err(|e| {
+                TranscriptionError::ConfigurationError(format!("Invalid grammar: {}", e))
+            })?;
+            debug!("Using grammar with {} rules", grammar.rule_count());
+            params.set_grammar(Some(&grammar.elements()));
+            params.set_start_rule(grammar.start_rule());
+        }
+
+        // Set number of threads
+        let num_threads = thread_count(config);
         debug!("Using {} threads for transcription", num_threads);
-        params.set_n_threads(num_threads);
+        params.set_n_threads(num_threads as i32);
 
         // Log the parameters
         debug!("Transcription parameters:");
-        debug!("  Language: {:?}", self.config.language);
-        debug!(
-            "  Translate to English: {}",
-            self.config.translate_to_english
-        );
-        debug!("  Temperature: {}", self.config.temperature);
-        debug!("  Beam search: {}", self.config.use_beam_search);
-        debug!("  Suppress blank: {}", self.config.suppress_blank);
-        debug!("  Word timestamps: {}", self.config.word_timestamps);
-
-        debug!("Converting audio data to f32 format");
-        // Convert audio data to f32 (whisper-rs expects f32 samples)
-        let audio_data_f32: Vec<f32> = audio_data
-            .chunks_exact(2) // 16-bit samples are 2 bytes (little endian order)
-            .map(|chunk| {
-                if let [low, high] = chunk {
-                    ((i16::from(*high) << 8) | i16::from(*low)) as f32 / 32768.0
-                } else {
-                    0.0 // Handle incomplete chunks
-                }
-            })
-            .collect();
+        debug!("  Language: {:?}", config.language);
+        debug!("  Translate to English: {}", config.translate_to_english);
+        debug!("  Temperature: {}", config.temperature);
+        debug!("  Temperature schedule: {:?}", config.temperatures);
+        debug!("  Beam search: {}", config.use_beam_search);
+        debug!("  Suppress blank: {}", config.suppress_blank);
+        debug!("  Word timestamps: {}", config.word_timestamps);
+
+        Ok(params)
+    }
+
+    /// Transcribe long audio as a series of overlapping windows
+    ///
+    /// Each window is decoded separately, its segment times are shifted to the
+    /// window's position, and words repeated across the overlap are dropped
+    /// when the results are merged.
+    ///
+    /// # Arguments
+    /// * `state` - Whisper state to decode with
+    /// * `config` - Effective configuration for this request
+    /// * `params` - Decoding parameters shared by every window
+    /// * `samples` - Complete audio samples, converted per window if not already
+    /// * `window_samples` - Window length in samples
+    /// * `start_time` - When the request started (for the reported duration)
+    /// * `stream` - Stream receiving segments as they are decoded, if any
+    ///
+    /// # Returns
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Merged result
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "Every window shares the pass's state, parameters and stream"
+    )]
+    fn transcribe_windows(
+        &self,
+        state: &mut WhisperState,
+        config: &TranscriptionConfig,
+        params: &FullParams,
+        samples: &SampleSource,
+        window_samples: usize,
+        start_time: std::time::Instant,
+        stream: Option<&Arc<SegmentStream>>,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        let overlap_samples = audio::ms_to_samples(config.chunk_overlap_ms);
+        let windows = audio::split_windows(samples.len(), window_samples, overlap_samples);
         debug!(
-            "Converted {} bytes to {} f32 samples",
-            audio_data.len(),
-            audio_data_f32.len()
+            "Transcribing {} samples in {} windows",
+            samples.len(),
+            windows.len()
         );
 
-        // Perform the transcription
-        debug!("Creating Whisper state for transcription");
-        let mut state = match self.context.create_state() {
-            Ok(state) => {
-                debug!("Whisper state created successfully");
-                state
-            }
-            Err(e) => {
-                error!("Failed to create Whisper state: {}", e);
-                return Err(TranscriptionError::WhisperContextError(e.to_string()));
-            }
-        };
-
-        debug!("Starting audio processing with Whisper");
-        // Process the audio data
-        match state.full(params, &audio_data_f32) {
-            Ok(_) => {
-                debug!("Transcription completed successfully");
-
-                // Extract the results
-                debug!("Extracting transcription results");
-                let result = self.extract_transcription_result(&state, start_time.elapsed())?;
-
-                Ok(result)
+        let mut merged: Option<TranscriptionResult> = None;
+        for window in windows {
+            let offset_secs = window.start as f32 / audio::SAMPLE_RATE as f32;
+            if let Some(stream) = stream {
+                stream.set_window_offset(audio::samples_to_ms(window.start));
             }
-            Err(e) => {
-                debug!("Whisper processing failed: {}", e);
-                error!("Transcription failed: {}", e);
-                Err(TranscriptionError::TranscriptionFailed(e.to_string()))
+            let window_samples = samples.window(window);
+            let result = self.decode_with_fallback(
+                state,
+                config,
+                params,
+                &window_samples,
+                start_time,
+                stream,
+            )?;
+            match merged {
+                Some(ref mut merged) => {
+                    merge_window_result(merged, result, offset_secs, overlap_samples > 0)
+                }
+                None => merged = Some(result),
             }
         }
+
+        let mut merged = merged.unwrap_or_default();
+        merged.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+        Ok(merged)
     }
 
-    /// Extract transcription results from the Whisper state
+    /// Decode samples, falling back through the temperature schedule until a
+    /// pass produces text
     ///
     /// # Arguments
-    /// * `state` - The Whisper state containing the results
-    /// * `duration` - Time taken for transcription
+    /// * `state` - Whisper state to decode with
+    /// * `config` - Effective configuration for this request
+    /// * `params` - Decoding parameters (temperature is set per attempt)
+    /// * `samples` - Audio samples to decode
+    /// * `start_time` - When the request started (for the reported duration)
+    /// * `stream` - Stream receiving segments as they are decoded, if any
     ///
     /// # Returns
-    /// * `Result<TranscriptionResult, TranscriptionError>` - Extracted result
-    fn extract_transcription_result(
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Result of the last attempt
+    fn decode_with_fallback(
         &self,
-        state: &whisper_rs::WhisperState,
-        duration: Duration,
+        state: &mut WhisperState,
+        config: &TranscriptionConfig,
+        params: &FullParams,
+        samples: &[f32],
+        start_time: std::time::Instant,
+        stream: Option<&Arc<SegmentStream>>,
     ) -> Result<TranscriptionResult, TranscriptionError> {
-        debug!("Starting transcription result extraction");
-        let mut text = String::new();
-        let mut segments = Vec::new();
-        // Get the language if available
-        debug!("Extracting language from Whisper state");
-        let lang_id = state.full_lang_id_from_state();
-        let lang_code = match lang_id {
-            0 => "en",
-            1 => "zh",
-            2 => "de",
-            3 => "es",
-            4 => "ru",
-            5 => "ko",
-            6 => "fr",
-            7 => "ja",
-            8 => "pt",
-            9 => "tr",
-            10 => "pl",
-            11 => "ca",
-            _ => "unknown",
-        };
-        debug!("Detected language ID: {} -> {}", lang_id, lang_code);
-        debug!("Detected language: {}", lang_code);
+        let mut outcome = Err(TranscriptionError::ConfigurationError(
+            "Temperature schedule is empty".to_string(),
+        ));
+        let mark = stream.map(|stream| stream.mark());
+        for (attempt, temperature) in temperature_schedule(config).into_iter().enumerate() {
+            if let (Some(stream), Some(mark)) = (stream, mark) {
+                // Reaching another attempt means the previous one was not kept
+                stream.discard_since(mark);
+                stream.begin_pass();
+            }
+            debug!("Setting temperature to: {}", temperature);
+            let mut attempt_params = params.clone();
+            attempt_params.set_temperature(temperature);
+            if config.temperatures.is_some() || config.deterministic {
+                // The explicit schedule replaces whisper's own temperature increments
+                attempt_params.set_temperature_inc(0.0);
+            }
+
+            match state.full(attempt_params, samples) {
+                Ok(_) => {
+                    debug!("Transcription completed successfully");
+
+                    // Extract the results
+                    debug!("Extracting transcription results");
+                    let mut result =
+                        self.extract_transcription_result(state, config, start_time.elapsed())?;
+                    let decoding =
+                        DecodingInfo::new(&sampling_strategy(config), temperature, attempt > 0);
+                    info!(
+                        "Decoded with {} (beam_size {:?}, best_of {:?}) at temperature {}{}",
+                        decoding.strategy,
+                        decoding.beam_size,
+                        decoding.best_of,
+                        decoding.temperature,
+                        if decoding.fallback_used {
+                            " after fallback"
+                        } else {
+                            ""
+                        }
+                    );
+                    result.decoding = Some(decoding);
+                    let has_text = !result.text.is_empty();
+                    outcome = Ok(result);
+                    if has_text {
+                        break;
+                    }
+                    debug!("No text produced at temperature {}", temperature);
+                }
+                Err(e) => {
+                    debug!("Whisper processing failed: {}", e);
+                    warn!("Transcription failed at temperature {}: {}", temperature, e);
+                    outcome = Err(TranscriptionError::TranscriptionFailed(e.to_string()));
+                }
+            }
+        }
+
+        outcome
+    }
+
+    /// Extract transcription results from the Whisper state
+    ///
+    /// # Arguments
+    /// * `state` - The Whisper state containing the results
+    /// * `config` - Effective configuration for this request
+    /// * `duration` - Time taken for transcription
+    ///
+    /// # Returns
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Extracted result
+    fn extract_transcription_result(
+        &self,
+        state: &WhisperState,
+        config: &TranscriptionConfig,
+        duration: Duration,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        debug!("Starting transcription result extraction");
+        // Get the language if available
+        debug!("Extracting language from Whisper state");
+        let lang_id = state.full_lang_id_from_state();
+        let language = language_from_id(lang_id);
+        debug!("Detected language ID: {} -> {:?}", lang_id, language);
 
         // Get the number of segments
         let num_segments = state.full_n_segments();
         debug!("Transcription produced {} segments", num_segments);
 
-        // Extract segments if enabled
-        debug!(
-            "Extracting segments with timestamps: {}",
-            self.config.include_timestamps || self.config.word_timestamps
-        );
-        if self.config.include_timestamps || self.config.word_timestamps {
-            for i in 0..num_segments {
-                debug!("Processing segment {}", i);
-                if let Some(segment) = state.get_segment(i) {
-                    match segment.to_str() {
-                        Ok(segment_text) => {
-                            let segment_text = segment_text.trim().to_string();
-                            if !segment_text.is_empty() {
-                                debug!("Segment {} text: \"{}\"", i, segment_text);
-                                let trans_segment = TranscriptionSegment {
-                                    start: segment.start_timestamp() as f32 / 100.0, // Convert from centiseconds to seconds
-                                    end: segment.end_timestamp() as f32 / 100.0,
-                                    text: segment_text.clone(),
-                                    confidence: None, // API doesn't provide confidence in this version
-                                };
-                                segments.push(trans_segment.clone());
-                                text.push_str(&segment_text);
-                                text.push(' ');
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to get segment text {}: {}", i, e);
+        // Read every segment, keeping empty ones so they can be counted
+        let mut raw_segments = Vec::new();
+        let mut segment_logprobs = Vec::new();
+        let mut words = Vec::new();
+        let token_eot = self.context.token_eot();
+        for i in 0..num_segments {
+            debug!("Processing segment {}", i);
+            if let Some(segment) = state.get_segment(i) {
+                match segment.to_str() {
+                    Ok(segment_text) => {
+                        debug!("Segment {} text: \"{}\"", i, segment_text.trim());
+                        // Average over text tokens only; special and timestamp tokens follow EOT
+                        let logprobs = (0..segment.n_tokens())
+                            .filter_map(|t| segment.get_token(t))
+                            .map(|token| token.token_data())
+                            .filter(|data| data.id < token_eot)
+                            .map(|data| data.plog)
+                            .collect::<Vec<_>>();
+                        if !logprobs.is_empty() && !segment_text.trim().is_empty() {
+                            let avg_logprob = logprobs.iter().sum::<f32>() / logprobs.len() as f32;
+                            segment_logprobs.push((avg_logprob, logprobs.len()));
                         }
-                    }
-                } else {
-                    warn!("Failed to get segment {}", i);
-                }
-            }
-        } else {
-            // Just get the full text without segments
-            // Get the full text by concatenating all segments
-            debug!("Extracting full text without segments");
-            if num_segments > 0 {
-                for i in 0..num_segments {
-                    if let Some(segment) = state.get_segment(i) {
-                        match segment.to_str() {
-                            Ok(segment_text) => {
-                                let segment_text = segment_text.trim().to_string();
-                                if !segment_text.is_empty() {
-                                    text.push_str(&segment_text);
-                                    text.push(' ');
+                        if config.phrase_gap_ms.is_some() {
+                            for token in
+                                (0..segment.n_tokens()).filter_map(|t| segment.get_token(t))
+                            {
+                                let data = token.token_data();
+                                if data.id >= token_eot {
+                                    continue;
+                                }
+                                if let Ok(piece) = token.to_str_lossy() {
+                                    // Token times are in centiseconds
+                                    push_word_piece(
+                                        &mut words,
+                                        &piece,
+                                        data.t0 as f32 / 100.0,
+                                        data.t1 as f32 / 100.0,
+                                    );
                                 }
-                            }
-                            Err(e) => {
-                                warn!("Failed to get segment text {}: {}", i, e);
                             }
                         }
-                    } else {
-                        warn!("Failed to get segment {}", i);
+                        raw_segments.push(TranscriptionSegment {
+                            start: segment.start_timestamp() as f32 / 100.0, // Convert from centiseconds to seconds
+                            end: segment.end_timestamp() as f32 / 100.0,
+                            text: segment_text.trim().to_string(),
+                            confidence: None, // API doesn't provide confidence in this version
+                            likely_new_speaker: None,
+                        });
+                    }
+                    Err(e) => {
+                        warn!("Failed to get segment text {}: {}", i, e);
                     }
                 }
             } else {
-                warn!("No segments available for transcription");
+                warn!("Failed to get segment {}", i);
             }
         }
+        if num_segments == 0 {
+            warn!("No segments available for transcription");
+        }
+        if let Some(phrase_gap_ms) = config.phrase_gap_ms {
+            raw_segments = group_words_into_phrases(&words, phrase_gap_ms);
+            debug!(
+                "Re-grouped {} words into {} phrases",
+                words.len(),
+                raw_segments.len()
+            );
+        }
+
+        let mut segments = keep_non_empty_segments(raw_segments);
+        let segments_too_short = config.min_segment_ms.map(|min_segment_ms| {
+            let (kept, filtered) = filter_short_segments(
+                std::mem::take(&mut segments),
+                min_segment_ms,
+                config.merge_short_segments,
+            );
+            segments = kept;
+            debug!(
+                "Filtered {} segments shorter than {} ms",
+                filtered, min_segment_ms
+            );
+            filtered
+        });
+        let segments_emitted = segments.len();
+        let logprob_tokens = segment_logprobs.iter().map(|(_, tokens)| tokens).sum();
+        let avg_logprob = weighted_avg_logprob(segment_logprobs);
+        debug!(
+            "Average log probability over {} tokens: {:?}",
+            logprob_tokens, avg_logprob
+        );
+        debug!(
+            "Kept {} of {} segments after dropping empty ones",
+            segments_emitted, num_segments
+        );
+        let mut text = join_segment_text(&segments);
+
+        // Only report segments when timestamps were requested
+        debug!(
+            "Extracting segments with timestamps: {}",
+            config.include_timestamps || config.word_timestamps
+        );
+        let segments = if config.include_timestamps || config.word_timestamps {
+            segments
+        } else {
+            Vec::new()
+        };
 
         // Clean up the text
         debug!("Cleaning up transcribed text");
@@ -700,7 +2551,7 @@ impl TranscriptionService {
 
         Ok(TranscriptionResult {
             text,
-            language: None,
+            language,
             segments: if segments.is_empty() {
                 None
             } else {
@@ -709,9 +2560,27 @@ impl TranscriptionService {
             success: true,
             error: None,
             duration_ms: Some(duration_ms),
+            segments_total: Some(num_segments.max(0) as usize),
+            segments_emitted: Some(segments_emitted),
+            segments_too_short,
+            avg_logprob,
+            logprob_tokens,
+            ..Default::default()
         })
     }
 
+    /// Replace the Whisper context with a newly loaded model
+    ///
+    /// Pooled states belong to the old model and are dropped with it.
+    ///
+    /// # Arguments
+    /// * `context` - Context of the new model
+    pub fn replace_context(&mut self, context: WhisperContext) {
+        debug!("Replacing Whisper context");
+        self.state_pool = StatePool::new(self.config.state_pool_size);
+        self.context = Arc::new(context);
+    }
+
     /// Update the transcription configuration
     ///
     /// # Arguments
@@ -734,13 +2603,14 @@ impl TranscriptionService {
     /// # Returns
     /// * `ModelInfo` - Model information
     pub fn model_info(&self) -> ModelInfo {
+        let (has_encoder, has_decoder) = model_components(&self.context);
         ModelInfo {
             sampling_rate: 16000, // Default sampling rate for Whisper
             n_text_ctx: 448,      // Default text context size
             n_mels: 80,           // Default number of mel bins
             multilingual: true,   // Most Whisper models are multilingual
-            has_encoder: true,    // Whisper has an encoder
-            has_decoder: true,    // Whisper has a decoder
+            has_encoder,
+            has_decoder,
         }
     }
 }
@@ -793,6 +2663,7 @@ mod tests {
             beam_size: Some(5),
             suppress_blank: false,
             word_timestamps: true,
+            ..Default::default()
         };
 
         assert_eq!(config.language, Some("en".to_string()));
@@ -815,6 +2686,7 @@ mod tests {
             success: true,
             error: None,
             duration_ms: Some(1000),
+            ..Default::default()
         };
 
         assert_eq!(result.text, "Hello world");
@@ -832,6 +2704,7 @@ mod tests {
             end: 1.0,
             text: "Hello".to_string(),
             confidence: Some(0.95),
+            likely_new_speaker: None,
         };
 
         assert_eq!(segment.start, 0.0);
@@ -977,8 +2850,10 @@ mod tests {
             temperature: Some(0.7),
             use_beam_search: Some(true),
             beam_size: Some(10),
+            patience: Some(1.5),
             suppress_blank: Some(false),
             word_timestamps: Some(true),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&options).unwrap();
@@ -994,261 +2869,1161 @@ mod tests {
         assert_eq!(deserialized.temperature, options.temperature);
         assert_eq!(deserialized.use_beam_search, options.use_beam_search);
         assert_eq!(deserialized.beam_size, options.beam_size);
+        assert_eq!(deserialized.patience, options.patience);
         assert_eq!(deserialized.suppress_blank, options.suppress_blank);
         assert_eq!(deserialized.word_timestamps, options.word_timestamps);
     }
 
     #[test]
-    fn test_transcription_options_default() {
-        let options = TranscriptionOptions::default();
+    fn test_transcription_options_default() {
+        let options = TranscriptionOptions::default();
+
+        assert!(options.language.is_none());
+        assert_eq!(options.translate_to_english, Some(false));
+        assert_eq!(options.include_timestamps, Some(true));
+        assert!(options.max_tokens.is_none());
+        assert_eq!(options.temperature, Some(0.0));
+        assert_eq!(options.use_beam_search, Some(true)); // Updated to match new default
+        assert_eq!(options.beam_size, Some(5)); // Updated to match new default
+        assert_eq!(options.suppress_blank, Some(true));
+        assert_eq!(options.word_timestamps, Some(false));
+    }
+
+    #[test]
+    fn test_extract_audio_data_base64() {
+        let request = TranscriptionRequest {
+            audio_data: AudioDataFormat::Base64 {
+                data: "SGVsbG8gV29ybGQ=".to_string(),
+                format: None,
+            },
+            options: None,
+            seq: None,
+            duration_ms: None,
+        };
+
+        let result = extract_audio_data(&request).unwrap();
+        assert_eq!(result, b"Hello World".to_vec());
+    }
+
+    #[test]
+    fn test_extract_audio_data_binary() {
+        let request = TranscriptionRequest {
+            audio_data: AudioDataFormat::Binary {
+                data: vec![72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100],
+                format: None,
+            },
+            options: None,
+            seq: None,
+            duration_ms: None,
+        };
+
+        let result = extract_audio_data(&request).unwrap();
+        assert_eq!(
+            result,
+            vec![72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100]
+        );
+    }
+
+    #[test]
+    fn test_extract_audio_data_empty_binary() {
+        let request = TranscriptionRequest {
+            audio_data: AudioDataFormat::Binary {
+                data: vec![],
+                format: None,
+            },
+            options: None,
+            seq: None,
+            duration_ms: None,
+        };
+
+        let result = extract_audio_data(&request);
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert_eq!(error.error_code(), "EMPTY_AUDIO");
+        assert!(error.to_string().contains("Binary audio data is empty"));
+    }
+
+    #[test]
+    fn test_extract_audio_data_empty_base64() {
+        let request = TranscriptionRequest {
+            audio_data: AudioDataFormat::Base64 {
+                data: String::new(),
+                format: None,
+            },
+            options: None,
+            seq: None,
+            duration_ms: None,
+        };
+
+        let error = extract_audio_data(&request).unwrap_err();
+        assert_eq!(error.error_code(), "EMPTY_AUDIO");
+        assert!(
+            error
+                .to_string()
+                .contains("Decoded base64 audio data is empty")
+        );
+    }
+
+    #[test]
+    fn test_extract_audio_data_invalid_base64() {
+        let request = TranscriptionRequest {
+            audio_data: AudioDataFormat::Base64 {
+                data: "invalid_base64!@#".to_string(),
+                format: None,
+            },
+            options: None,
+            seq: None,
+            duration_ms: None,
+        };
+
+        let result = extract_audio_data(&request);
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert_eq!(error.error_code(), "INVALID_BASE64");
+        assert!(error.to_string().contains("Invalid base64 encoding"));
+    }
+
+    #[test]
+    fn test_validate_transcription_options_valid() {
+        let options = TranscriptionOptions {
+            language: Some("en".to_string()),
+            temperature: Some(0.5),
+            beam_size: Some(5),
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+
+        let result = validate_transcription_options(&options).unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_language() {
+        let options = TranscriptionOptions {
+            language: Some("invalid_lang".to_string()),
+            ..Default::default()
+        };
+
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].field, "language");
+        assert!(result[0].message.contains("Invalid language code"));
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_temperature() {
+        let options = TranscriptionOptions {
+            temperature: Some(2.0),
+            ..Default::default()
+        };
+
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].field, "temperature");
+        assert!(result[0].message.contains("between 0.0 and 1.0"));
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_beam_size() {
+        let options = TranscriptionOptions {
+            beam_size: Some(0),
+            ..Default::default()
+        };
+
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].field, "beam_size");
+        assert!(result[0].message.contains("greater than 0"));
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_patience() {
+        for patience in [0.0, -1.0, f32::NAN] {
+            let options = TranscriptionOptions {
+                patience: Some(patience),
+                ..Default::default()
+            };
+
+            let result = validate_transcription_options(&options).unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].field, "patience");
+            assert!(result[0].message.contains("greater than 0.0"));
+        }
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_sample_rate() {
+        for sample_rate in [0, 1, 7999, 192001] {
+            let options = TranscriptionOptions {
+                sample_rate: Some(sample_rate),
+                ..Default::default()
+            };
+
+            let result = validate_transcription_options(&options).unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].field, "sample_rate");
+        }
+        let options = TranscriptionOptions {
+            sample_rate: Some(44100),
+            ..Default::default()
+        };
+        assert!(validate_transcription_options(&options).unwrap().is_empty());
+
+        let options = TranscriptionOptions {
+            channels: Some(0),
+            ..Default::default()
+        };
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].field, "channels");
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_max_tokens() {
+        let options = TranscriptionOptions {
+            max_tokens: Some(0),
+            ..Default::default()
+        };
+
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].field, "max_tokens");
+        assert!(result[0].message.contains("greater than 0"));
+    }
+
+    #[test]
+    fn test_validate_transcription_options_multiple_errors() {
+        let options = TranscriptionOptions {
+            language: Some("invalid_lang".to_string()),
+            temperature: Some(2.0),
+            beam_size: Some(0),
+            max_tokens: Some(0),
+            ..Default::default()
+        };
+
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 4);
+    }
+
+    #[test]
+    fn test_options_to_config() {
+        let options = TranscriptionOptions {
+            language: Some("en".to_string()),
+            translate_to_english: Some(true),
+            include_timestamps: Some(false),
+            max_tokens: Some(500),
+            temperature: Some(0.7),
+            use_beam_search: Some(true),
+            beam_size: Some(10),
+            patience: Some(1.5),
+            suppress_blank: Some(false),
+            word_timestamps: Some(true),
+            ..Default::default()
+        };
+
+        let config = options_to_config(options);
+
+        assert_eq!(config.language, Some("en".to_string()));
+        assert!(config.translate_to_english);
+        assert!(!config.include_timestamps);
+        assert_eq!(config.max_tokens, Some(500));
+        assert_eq!(config.temperature, 0.7);
+        assert!(config.use_beam_search);
+        assert_eq!(config.beam_size, Some(10));
+        assert_eq!(config.patience, Some(1.5));
+        assert!(!config.suppress_blank);
+        assert!(config.word_timestamps);
+    }
+
+    #[test]
+    fn test_update_config_from_options() {
+        let base_config = TranscriptionConfig {
+            language: Some("fr".to_string()),
+            translate_to_english: false,
+            include_timestamps: true,
+            max_tokens: None,
+            temperature: 0.0,
+            use_beam_search: false,
+            beam_size: None,
+            suppress_blank: true,
+            word_timestamps: false,
+            ..Default::default()
+        };
+
+        let options = TranscriptionOptions {
+            language: Some("en".to_string()),
+            translate_to_english: Some(true),
+            include_timestamps: Some(false),
+            max_tokens: Some(500),
+            temperature: Some(0.7),
+            use_beam_search: Some(true),
+            beam_size: Some(10),
+            patience: Some(1.5),
+            suppress_blank: Some(false),
+            word_timestamps: Some(true),
+            ..Default::default()
+        };
+
+        let updated_config = update_config_from_options(&base_config, &options);
+
+        // Should update from options
+        assert_eq!(updated_config.language, Some("en".to_string()));
+        assert!(updated_config.translate_to_english);
+        assert!(!updated_config.include_timestamps);
+        assert_eq!(updated_config.max_tokens, Some(500));
+        assert_eq!(updated_config.temperature, 0.7);
+        assert!(updated_config.use_beam_search);
+        assert_eq!(updated_config.beam_size, Some(10));
+        assert_eq!(updated_config.patience, Some(1.5));
+        assert!(!updated_config.suppress_blank);
+        assert!(updated_config.word_timestamps);
+    }
+
+    #[test]
+    fn test_json_error_display() {
+        let error = JsonError::InvalidJson("test error".to_string());
+        assert_eq!(format!("{}", error), "Invalid JSON: test error");
+
+        let error = JsonError::MissingField("audio_data".to_string());
+        assert_eq!(format!("{}", error), "Missing required field: audio_data");
+
+        let error = JsonError::InvalidFieldValue("language".to_string(), "invalid".to_string());
+        assert_eq!(
+            format!("{}", error),
+            "Invalid value for field 'language': invalid"
+        );
+
+        let error = JsonError::InvalidBase64("decode error".to_string());
+        assert_eq!(
+            format!("{}", error),
+            "Invalid base64 encoding: decode error"
+        );
+
+        let error = JsonError::AudioDataError("empty data".to_string());
+        assert_eq!(format!("{}", error), "Audio data error: empty data");
+    }
+
+    #[test]
+    fn test_validation_error() {
+        let error = ValidationError::new("field_name", "error message");
+        assert_eq!(error.field, "field_name");
+        assert_eq!(error.message, "error message");
+    }
+
+    #[test]
+    fn test_validate_transcription_options_temperatures() {
+        let options = TranscriptionOptions {
+            temperatures: Some(vec![0.0, 0.2, 0.4]),
+            ..Default::default()
+        };
+        assert!(validate_transcription_options(&options).unwrap().is_empty());
+
+        let options = TranscriptionOptions {
+            temperatures: Some(vec![0.0, 1.5]),
+            ..Default::default()
+        };
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].field, "temperatures");
+
+        let options = TranscriptionOptions {
+            temperatures: Some(vec![]),
+            ..Default::default()
+        };
+        let result = validate_transcription_options(&options).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].message.contains("must not be empty"));
+    }
+
+    #[test]
+    fn test_temperature_schedule_overrides_scalar() {
+        let config = TranscriptionConfig {
+            temperature: 0.7,
+            ..Default::default()
+        };
+        assert_eq!(temperature_schedule(&config), vec![0.7]);
+
+        let options = TranscriptionOptions {
+            temperature: Some(0.7),
+            temperatures: Some(vec![0.0, 0.2, 0.4]),
+            ..Default::default()
+        };
+        let config = update_config_from_options(&config, &options);
+        assert_eq!(temperature_schedule(&config), vec![0.0, 0.2, 0.4]);
+    }
+
+    #[test]
+    fn test_seam_overlap_words() {
+        assert_eq!(
+            seam_overlap_words("the quick brown fox", "Brown fox, jumps over"),
+            2
+        );
+        assert_eq!(seam_overlap_words("the quick brown fox", "jumps over"), 0);
+        assert_eq!(seam_overlap_words("", "jumps over"), 0);
+    }
+
+    #[test]
+    fn test_merge_window_result_dedups_seam() {
+        let segment = |start, end, text: &str| TranscriptionSegment {
+            start,
+            end,
+            text: text.to_string(),
+            confidence: None,
+            likely_new_speaker: None,
+        };
+        let mut merged = TranscriptionResult {
+            text: "the quick brown fox".to_string(),
+            segments: Some(vec![segment(0.0, 2.0, "the quick brown fox")]),
+            success: true,
+            ..Default::default()
+        };
+        let next = TranscriptionResult {
+            text: "brown fox jumps over".to_string(),
+            segments: Some(vec![
+                segment(0.0, 0.5, "brown fox"),
+                segment(0.5, 1.5, "jumps over"),
+            ]),
+            success: true,
+            ..Default::default()
+        };
+
+        merge_window_result(&mut merged, next, 1.5, true);
+
+        assert_eq!(merged.text, "the quick brown fox jumps over");
+        let segments = merged.segments.unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].text, "jumps over");
+        assert_eq!(segments[1].start, 2.0);
+        assert_eq!(segments[1].end, 3.0);
+    }
+
+    #[test]
+    fn test_merge_window_result_without_overlap_keeps_repeats() {
+        let mut merged = TranscriptionResult {
+            text: "no".to_string(),
+            ..Default::default()
+        };
+        let next = TranscriptionResult {
+            text: "no".to_string(),
+            ..Default::default()
+        };
+
+        merge_window_result(&mut merged, next, 1.0, false);
+        assert_eq!(merged.text, "no no");
+    }
+
+    #[test]
+    fn test_keep_non_empty_segments_counts() {
+        let segment = |text: &str| TranscriptionSegment {
+            start: 0.0,
+            end: 1.0,
+            text: text.to_string(),
+            confidence: None,
+            likely_new_speaker: None,
+        };
+        let raw = vec![
+            segment("hello"),
+            segment(""),
+            segment("  "),
+            segment("world"),
+        ];
+        let total = raw.len();
+
+        let kept = keep_non_empty_segments(raw);
+        assert_eq!(total, 4);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].text, "hello");
+        assert_eq!(kept[1].text, "world");
+    }
+
+    #[test]
+    fn test_grammar_option_plumbing() {
+        let options = TranscriptionOptions {
+            grammar: Some(r#"root ::= "yes" | "no""#.to_string()),
+            ..Default::default()
+        };
+        assert!(validate_transcription_options(&options).unwrap().is_empty());
+
+        let config = options_to_config(options.clone());
+        assert_eq!(config.grammar, options.grammar);
+
+        let updated = update_config_from_options(&TranscriptionConfig::default(), &options);
+        assert_eq!(updated.grammar, options.grammar);
+    }
+
+    #[test]
+    fn test_validate_transcription_options_invalid_grammar() {
+        let options = TranscriptionOptions {
+            grammar: Some("root ::= undefined-rule".to_string()),
+            ..Default::default()
+        };
+
+        let errors = validate_transcription_options(&options).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "grammar");
+    }
+
+    #[test]
+    fn test_language_from_id() {
+        assert_eq!(language_from_id(0).as_deref(), Some("en"));
+        // Ids past the first dozen languages resolve through whisper too
+        assert_eq!(language_from_id(12).as_deref(), Some("nl"));
+        assert_eq!(language_from_id(20).as_deref(), Some("he"));
+        assert_eq!(language_from_id(-1), None);
+    }
+
+    #[test]
+    fn test_detected_language_triggers_override() {
+        let mut config = TranscriptionConfig {
+            beam_size: Some(5),
+            language_overrides: builtin_language_overrides(),
+            ..Default::default()
+        };
+        config.language_overrides.insert(
+            "de".to_string(),
+            LanguageOverride {
+                beam_size: Some(8),
+                ..Default::default()
+            },
+        );
+
+        let german = config_for_detected_language(&config, "de");
+        assert_eq!(german.language.as_deref(), Some("de"));
+        assert_eq!(german.beam_size, Some(8));
+
+        let japanese = config_for_detected_language(&config, "ja");
+        assert_eq!(japanese.max_segment_chars, Some(30));
+        assert_eq!(japanese.beam_size, Some(5));
+
+        let english = config_for_detected_language(&config, "en");
+        assert_eq!(english.beam_size, Some(5));
+        assert!(english.max_segment_chars.is_none());
+
+        // No overrides (and so no detection pass) unless they are configured
+        assert!(TranscriptionConfig::default().language_overrides.is_empty());
+    }
+
+    #[test]
+    fn test_round_segment_timestamps() {
+        let mut segments = vec![TranscriptionSegment {
+            start: 1.5 + 0.73,
+            end: 2.0 + 1.0004999,
+            text: "hello".to_string(),
+            confidence: None,
+            likely_new_speaker: None,
+        }];
+
+        round_segment_timestamps(&mut segments, 3);
+        assert_eq!(serde_json::to_string(&segments[0].start).unwrap(), "2.23");
+        assert_eq!(serde_json::to_string(&segments[0].end).unwrap(), "3.0");
+
+        assert_eq!(round_timestamp(1.23456, 1), 1.2);
+        assert_eq!(round_timestamp(1.5, 0), 2.0);
+    }
+
+    #[test]
+    fn test_json_error_into_transcription_error() {
+        let error = TranscriptionError::from(JsonError::EmptyAudio("no samples".to_string()));
+        assert_eq!(error.to_string(), "Empty audio data: no samples");
+        assert_eq!(error.error_code(), "EMPTY_AUDIO");
+
+        // The overlapping audio data variant maps onto its counterpart
+        let error = TranscriptionError::from(JsonError::AudioDataError("bad".to_string()));
+        assert!(matches!(error, TranscriptionError::AudioDataError(ref e) if e == "bad"));
+        assert_eq!(error.to_string(), "Audio data error: bad");
+    }
+
+    #[test]
+    fn test_source_errors_into_json_error() {
+        let parse_error = serde_json::from_str::<TranscriptionRequest>("{").unwrap_err();
+        let message = parse_error.to_string();
+        let error = JsonError::from(parse_error);
+        assert_eq!(error.error_code(), "INVALID_JSON");
+        assert_eq!(error.to_string(), format!("Invalid JSON: {}", message));
+
+        let io_error = std::io::Error::other("stdin closed");
+        let error = JsonError::from(io_error);
+        assert_eq!(error.error_code(), "READ_ERROR");
+        assert_eq!(error.to_string(), "Read error: stdin closed");
+    }
+
+    #[test]
+    fn test_error_result_from_transcription_error() {
+        let error = TranscriptionError::from(JsonError::InvalidBase64("bad padding".to_string()));
+        let result = TranscriptionResult::from(&error);
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Invalid base64 encoding: bad padding")
+        );
+        assert_eq!(result.error_code.as_deref(), Some("INVALID_BASE64"));
+    }
+
+    #[test]
+    fn test_transcribe_with_translation_populates_both_texts() {
+        let config = TranscriptionConfig {
+            translate_to_english: true,
+            with_translation: true,
+            ..Default::default()
+        };
+        let mut passes = Vec::new();
+
+        // Stub decoder standing in for the model
+        let result = transcribe_with_translation(&config, |pass| {
+            passes.push(pass.translate_to_english);
+            let text = if pass.translate_to_english {
+                "Good morning"
+            } else {
+                "Guten Morgen"
+            };
+            Ok(TranscriptionResult {
+                text: text.to_string(),
+                language: Some("de".to_string()),
+                success: true,
+                duration_ms: Some(100 * passes.len() as u64),
+                ..Default::default()
+            })
+        })
+        .unwrap();
+
+        assert_eq!(passes, vec![false, true]);
+        assert_eq!(result.text, "Guten Morgen");
+        assert_eq!(result.translated_text.as_deref(), Some("Good morning"));
+        assert_eq!(result.language.as_deref(), Some("de"));
+        assert_eq!(result.duration_ms, Some(200));
+    }
+
+    #[test]
+    fn test_filter_short_segments() {
+        let segment = |start: f32, end: f32, text: &str| TranscriptionSegment {
+            start,
+            end,
+            text: text.to_string(),
+            confidence: None,
+            likely_new_speaker: None,
+        };
+        let segments = vec![
+            segment(0.0, 1.5, "Hello there"),
+            segment(1.5, 1.55, "uh"),
+            segment(1.6, 3.0, "general Kenobi"),
+        ];
 
-        assert!(options.language.is_none());
-        assert_eq!(options.translate_to_english, Some(false));
-        assert_eq!(options.include_timestamps, Some(true));
-        assert!(options.max_tokens.is_none());
-        assert_eq!(options.temperature, Some(0.0));
-        assert_eq!(options.use_beam_search, Some(true)); // Updated to match new default
-        assert_eq!(options.beam_size, Some(5)); // Updated to match new default
-        assert_eq!(options.suppress_blank, Some(true));
-        assert_eq!(options.word_timestamps, Some(false));
+        let (kept, filtered) = filter_short_segments(segments.clone(), 100, false);
+        assert_eq!(filtered, 1);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1].text, "general Kenobi");
+
+        let (kept, filtered) = filter_short_segments(segments, 100, true);
+        assert_eq!(filtered, 1);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].text, "Hello there uh");
+        assert_eq!(kept[0].end, 1.55);
+
+        // A leading short segment is merged into the next one
+        let segments = vec![segment(0.0, 0.05, "So"), segment(0.1, 1.0, "anyway")];
+        let (kept, filtered) = filter_short_segments(segments, 100, true);
+        assert_eq!(filtered, 1);
+        assert_eq!(kept[0].text, "So anyway");
+        assert_eq!(kept[0].start, 0.0);
     }
 
     #[test]
-    fn test_extract_audio_data_base64() {
-        let request = TranscriptionRequest {
-            audio_data: AudioDataFormat::Base64 {
-                data: "SGVsbG8gV29ybGQ=".to_string(),
-                _format: None,
-            },
-            options: None,
+    fn test_deterministic_temperature_schedule() {
+        let config = TranscriptionConfig {
+            temperature: 0.4,
+            temperatures: Some(vec![0.2, 0.6]),
+            deterministic: true,
+            ..Default::default()
         };
-
-        let result = extract_audio_data(&request).unwrap();
-        assert_eq!(result, b"Hello World".to_vec());
+        assert_eq!(temperature_schedule(&config), vec![0.0]);
     }
 
     #[test]
-    fn test_extract_audio_data_binary() {
-        let request = TranscriptionRequest {
-            audio_data: AudioDataFormat::Binary {
-                data: vec![72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100],
-                _format: None,
-            },
-            options: None,
+    fn test_translation_rejected_on_english_only_model() {
+        let translate = TranscriptionConfig {
+            translate_to_english: true,
+            ..Default::default()
         };
+        let error = check_translation_supported(&translate, false).unwrap_err();
+        assert_eq!(error.error_code(), "TRANSLATE_REQUIRES_MULTILINGUAL");
+        assert!(check_translation_supported(&translate, true).is_ok());
 
-        let result = extract_audio_data(&request).unwrap();
-        assert_eq!(
-            result,
-            vec![72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100]
-        );
+        let bilingual = TranscriptionConfig {
+            with_translation: true,
+            ..Default::default()
+        };
+        assert!(check_translation_supported(&bilingual, false).is_err());
+        assert!(check_translation_supported(&TranscriptionConfig::default(), false).is_ok());
     }
 
     #[test]
-    fn test_extract_audio_data_empty_binary() {
-        let request = TranscriptionRequest {
-            audio_data: AudioDataFormat::Binary {
-                data: vec![],
-                _format: None,
-            },
-            options: None,
+    fn test_annotate_speaker_hints_after_long_gap() {
+        let segment = |start: f32, end: f32| TranscriptionSegment {
+            start,
+            end,
+            text: "words".to_string(),
+            confidence: None,
+            likely_new_speaker: None,
         };
+        let mut segments = vec![segment(0.0, 2.0), segment(2.3, 4.0), segment(6.0, 7.0)];
 
-        let result = extract_audio_data(&request);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Binary audio data is empty"));
+        annotate_speaker_hints(&mut segments, 1500);
+        let hints: Vec<_> = segments.iter().map(|s| s.likely_new_speaker).collect();
+        assert_eq!(hints, vec![Some(false), Some(false), Some(true)]);
     }
 
     #[test]
-    fn test_extract_audio_data_invalid_base64() {
-        let request = TranscriptionRequest {
-            audio_data: AudioDataFormat::Base64 {
-                data: "invalid_base64!@#".to_string(),
-                _format: None,
-            },
-            options: None,
+    fn test_greedy_retry_when_beam_search_is_empty() {
+        let config = TranscriptionConfig {
+            use_beam_search: true,
+            greedy_fallback: true,
+            ..Default::default()
         };
+        // Stub decoder: beam search finds nothing, greedy finds speech
+        let mut passes = Vec::new();
+        let result = retry_empty_with_greedy(&config, |pass| {
+            passes.push(pass.use_beam_search);
+            Ok(TranscriptionResult {
+                text: if pass.use_beam_search {
+                    String::new()
+                } else {
+                    "hello".to_string()
+                },
+                success: true,
+                ..Default::default()
+            })
+        })
+        .unwrap();
 
-        let result = extract_audio_data(&request);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Failed to decode base64"));
+        assert_eq!(passes, vec![true, false]);
+        assert_eq!(result.text, "hello");
+        assert_eq!(result.fallback_used, Some(true));
     }
 
     #[test]
-    fn test_validate_transcription_options_valid() {
-        let options = TranscriptionOptions {
-            language: Some("en".to_string()),
-            temperature: Some(0.5),
-            beam_size: Some(5),
-            max_tokens: Some(100),
+    fn test_no_greedy_retry_when_beam_search_has_text() {
+        let config = TranscriptionConfig {
+            use_beam_search: true,
+            greedy_fallback: true,
             ..Default::default()
         };
+        let mut passes = 0;
+        let result = retry_empty_with_greedy(&config, |_| {
+            passes += 1;
+            Ok(TranscriptionResult {
+                text: "hello".to_string(),
+                success: true,
+                ..Default::default()
+            })
+        })
+        .unwrap();
 
-        let result = validate_transcription_options(&options).unwrap();
-        assert!(result.is_empty());
+        assert_eq!(passes, 1);
+        assert_eq!(result.fallback_used, None);
     }
 
     #[test]
-    fn test_validate_transcription_options_invalid_language() {
-        let options = TranscriptionOptions {
-            language: Some("invalid_lang".to_string()),
+    fn test_weighted_avg_logprob() {
+        // A long confident segment outweighs a short uncertain one
+        let avg = weighted_avg_logprob([(-0.2, 30), (-1.4, 10)]).unwrap();
+        assert!((avg - -0.5).abs() < 1e-6);
+
+        assert_eq!(weighted_avg_logprob([(-0.3, 5)]), Some(-0.3));
+        assert_eq!(weighted_avg_logprob([]), None);
+        assert_eq!(weighted_avg_logprob([(-0.3, 0)]), None);
+    }
+
+    #[test]
+    fn test_merge_window_result_weights_avg_logprob() {
+        let mut merged = TranscriptionResult {
+            text: "first".to_string(),
+            avg_logprob: Some(-0.2),
+            logprob_tokens: 30,
+            ..Default::default()
+        };
+        let next = TranscriptionResult {
+            text: "second".to_string(),
+            avg_logprob: Some(-1.4),
+            logprob_tokens: 10,
             ..Default::default()
         };
+        merge_window_result(&mut merged, next, 30.0, false);
+        assert!((merged.avg_logprob.unwrap() - -0.5).abs() < 1e-6);
+        assert_eq!(merged.logprob_tokens, 40);
+    }
 
-        let result = validate_transcription_options(&options).unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].field, "language");
-        assert!(result[0].message.contains("Invalid language code"));
+    fn timed_word(start: f32, end: f32, text: &str) -> TimedWord {
+        TimedWord {
+            start,
+            end,
+            text: text.to_string(),
+        }
     }
 
     #[test]
-    fn test_validate_transcription_options_invalid_temperature() {
-        let options = TranscriptionOptions {
-            temperature: Some(2.0),
-            ..Default::default()
-        };
+    fn test_group_words_into_phrases_at_sentence_boundaries() {
+        let words = vec![
+            timed_word(0.0, 0.4, "Hello"),
+            timed_word(0.4, 0.8, "there."),
+            timed_word(0.9, 1.1, "How"),
+            timed_word(1.1, 1.3, "are"),
+            timed_word(1.3, 1.6, "you?"),
+            timed_word(1.7, 2.0, "Fine"),
+        ];
 
-        let result = validate_transcription_options(&options).unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].field, "temperature");
-        assert!(result[0].message.contains("between 0.0 and 1.0"));
+        let phrases = group_words_into_phrases(&words, DEFAULT_PHRASE_GAP_MS);
+        let texts: Vec<&str> = phrases.iter().map(|phrase| phrase.text.as_str()).collect();
+        assert_eq!(texts, vec!["Hello there.", "How are you?", "Fine"]);
+        assert_eq!((phrases[1].start, phrases[1].end), (0.9, 1.6));
     }
 
     #[test]
-    fn test_validate_transcription_options_invalid_beam_size() {
-        let options = TranscriptionOptions {
-            beam_size: Some(0),
-            ..Default::default()
-        };
+    fn test_group_words_into_phrases_splits_on_pauses() {
+        let words = vec![
+            timed_word(0.0, 0.3, "one"),
+            timed_word(0.3, 0.6, "two"),
+            timed_word(1.6, 1.9, "three"),
+        ];
 
-        let result = validate_transcription_options(&options).unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].field, "beam_size");
-        assert!(result[0].message.contains("greater than 0"));
+        let phrases = group_words_into_phrases(&words, 500);
+        let texts: Vec<&str> = phrases.iter().map(|phrase| phrase.text.as_str()).collect();
+        assert_eq!(texts, vec!["one two", "three"]);
+        assert!(group_words_into_phrases(&[], 500).is_empty());
     }
 
     #[test]
-    fn test_validate_transcription_options_invalid_max_tokens() {
-        let options = TranscriptionOptions {
-            max_tokens: Some(0),
-            ..Default::default()
-        };
+    fn test_push_word_piece_joins_subword_tokens() {
+        let mut words = Vec::new();
+        push_word_piece(&mut words, " Trans", 0.0, 0.2);
+        push_word_piece(&mut words, "cription", 0.2, 0.5);
+        push_word_piece(&mut words, ".", 0.5, 0.6);
+        push_word_piece(&mut words, " ", 0.6, 0.6);
+        push_word_piece(&mut words, " Done", 0.7, 1.0);
 
-        let result = validate_transcription_options(&options).unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].field, "max_tokens");
-        assert!(result[0].message.contains("greater than 0"));
+        assert_eq!(
+            words,
+            vec![
+                timed_word(0.0, 0.6, "Transcription."),
+                timed_word(0.7, 1.0, "Done"),
+            ]
+        );
     }
 
     #[test]
-    fn test_validate_transcription_options_multiple_errors() {
-        let options = TranscriptionOptions {
-            language: Some("invalid_lang".to_string()),
-            temperature: Some(2.0),
-            beam_size: Some(0),
-            max_tokens: Some(0),
+    fn test_decoding_info_reflects_sampling_strategy() {
+        let greedy = TranscriptionConfig::default();
+        let info = DecodingInfo::new(&sampling_strategy(&greedy), 0.0, false);
+        assert_eq!(info.strategy, "greedy");
+        assert_eq!(info.best_of, Some(1));
+        assert_eq!(info.beam_size, None);
+
+        let beam = TranscriptionConfig {
+            use_beam_search: true,
+            beam_size: Some(3),
             ..Default::default()
         };
+        let info = DecodingInfo::new(&sampling_strategy(&beam), 0.4, true);
+        assert_eq!(info.strategy, "beam_search");
+        assert_eq!(info.beam_size, Some(3));
+        assert_eq!(info.best_of, None);
+        assert_eq!(info.temperature, 0.4);
+        assert!(info.fallback_used);
+
+        // Deterministic mode always decodes greedily
+        let deterministic = TranscriptionConfig {
+            deterministic: true,
+            ..beam
+        };
+        let info = DecodingInfo::new(&sampling_strategy(&deterministic), 0.0, false);
+        assert_eq!(info.strategy, "greedy");
 
-        let result = validate_transcription_options(&options).unwrap();
-        assert_eq!(result.len(), 4);
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json.get("beam_size").is_none());
+        assert_eq!(json["best_of"], 1);
     }
 
     #[test]
-    fn test_options_to_config() {
-        let options = TranscriptionOptions {
-            language: Some("en".to_string()),
-            translate_to_english: Some(true),
-            include_timestamps: Some(false),
-            max_tokens: Some(500),
-            temperature: Some(0.7),
-            use_beam_search: Some(true),
-            beam_size: Some(10),
-            suppress_blank: Some(false),
-            word_timestamps: Some(true),
+    fn test_segment_stream_places_segments_in_the_request() {
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&emitted);
+        let emitter: SegmentEmitter = Arc::new(move |event| sink.lock().unwrap().push(event));
+        let segment = |segment, start_timestamp, end_timestamp, text: &str| SegmentCallbackData {
+            segment,
+            start_timestamp,
+            end_timestamp,
+            text: format!(" {}", text),
         };
 
-        let config = options_to_config(options);
+        // Half a second of leading silence was trimmed before decoding
+        let stream = Arc::new(SegmentStream::new(emitter, 500));
+        let mut callback = stream.callback();
+        let mark = stream.mark();
+
+        // First window: a pass that is retried at a higher temperature
+        stream.set_window_offset(0);
+        stream.begin_pass();
+        callback(segment(0, 0, 150, "Hello word."));
+        stream.discard_since(mark);
+        stream.begin_pass();
+        callback(segment(0, 0, 150, "Hello world."));
+        callback(segment(1, 150, 320, "Bye."));
+
+        // Second window, 30 seconds into the decoded samples
+        let mark = stream.mark();
+        stream.set_window_offset(30_000);
+        stream.discard_since(mark);
+        stream.begin_pass();
+        callback(segment(0, 20, 100, "Again."));
+
+        let expected_segment = |pass, index, start, end, text: &str| {
+            StreamEvent::Segment(SegmentEvent {
+                event: "segment".to_string(),
+                pass,
+                index,
+                start,
+                end,
+                text: text.to_string(),
+            })
+        };
+        let emitted = emitted.lock().unwrap();
+        assert_eq!(
+            *emitted,
+            vec![
+                expected_segment(0, 0, 0.5, 2.0, "Hello word."),
+                StreamEvent::Discarded(SegmentsDiscardedEvent {
+                    event: "segments_discarded".to_string(),
+                    pass: 0,
+                }),
+                expected_segment(1, 0, 0.5, 2.0, "Hello world."),
+                expected_segment(1, 1, 2.0, 3.7, "Bye."),
+                expected_segment(2, 2, 30.7, 31.5, "Again."),
+            ]
+        );
 
-        assert_eq!(config.language, Some("en".to_string()));
-        assert!(config.translate_to_english);
-        assert!(!config.include_timestamps);
-        assert_eq!(config.max_tokens, Some(500));
-        assert_eq!(config.temperature, 0.7);
-        assert!(config.use_beam_search);
-        assert_eq!(config.beam_size, Some(10));
-        assert!(!config.suppress_blank);
-        assert!(config.word_timestamps);
+        let json = serde_json::to_value(&emitted[1]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"event": "segments_discarded", "pass": 0})
+        );
     }
 
     #[test]
-    fn test_update_config_from_options() {
-        let base_config = TranscriptionConfig {
-            language: Some("fr".to_string()),
-            translate_to_english: false,
-            include_timestamps: true,
-            max_tokens: None,
-            temperature: 0.0,
-            use_beam_search: false,
-            beam_size: None,
-            suppress_blank: true,
-            word_timestamps: false,
-        };
+    fn test_offset_emitter_shifts_segments_of_a_piece() {
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&emitted);
+        let emitter: SegmentEmitter = Arc::new(move |event| sink.lock().unwrap().push(event));
+
+        let emitter = offset_emitter(emitter, 2500);
+        let discarded = StreamEvent::Discarded(SegmentsDiscardedEvent {
+            event: "segments_discarded".to_string(),
+            pass: 0,
+        });
+        emitter(discarded.clone());
+        emitter(StreamEvent::Segment(SegmentEvent {
+            event: "segment".to_string(),
+            pass: 1,
+            index: 0,
+            start: 0.5,
+            end: 1.0,
+            text: "Later.".to_string(),
+        }));
 
-        let options = TranscriptionOptions {
-            language: Some("en".to_string()),
-            translate_to_english: Some(true),
-            include_timestamps: Some(false),
-            max_tokens: Some(500),
-            temperature: Some(0.7),
-            use_beam_search: Some(true),
-            beam_size: Some(10),
-            suppress_blank: Some(false),
-            word_timestamps: Some(true),
+        let emitted = emitted.lock().unwrap();
+        assert_eq!(emitted[0], discarded);
+        let StreamEvent::Segment(ref segment) = emitted[1] else {
+            panic!("expected a segment");
         };
+        assert_eq!((segment.start, segment.end), (3.0, 3.5));
+    }
 
-        let updated_config = update_config_from_options(&base_config, &options);
+    #[test]
+    fn test_segment_stream_discards_every_pass_of_a_retried_request() {
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&emitted);
+        let emitter: SegmentEmitter = Arc::new(move |event| sink.lock().unwrap().push(event));
+        let stream = Arc::new(SegmentStream::new(emitter, 0));
+        let mut callback = stream.callback();
+
+        // A beam search pass over two windows is replaced by a greedy retry
+        let request = stream.mark();
+        for _ in 0..2 {
+            stream.begin_pass();
+            callback(SegmentCallbackData {
+                segment: 0,
+                start_timestamp: 0,
+                end_timestamp: 100,
+                text: String::new(),
+            });
+        }
+        stream.discard_since(request);
+        stream.begin_pass();
+        callback(SegmentCallbackData {
+            segment: 0,
+            start_timestamp: 0,
+            end_timestamp: 100,
+            text: " Found.".to_string(),
+        });
 
-        // Should update from options
-        assert_eq!(updated_config.language, Some("en".to_string()));
-        assert!(updated_config.translate_to_english);
-        assert!(!updated_config.include_timestamps);
-        assert_eq!(updated_config.max_tokens, Some(500));
-        assert_eq!(updated_config.temperature, 0.7);
-        assert!(updated_config.use_beam_search);
-        assert_eq!(updated_config.beam_size, Some(10));
-        assert!(!updated_config.suppress_blank);
-        assert!(updated_config.word_timestamps);
+        let emitted = emitted.lock().unwrap();
+        let discarded: Vec<u32> = emitted
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Discarded(discarded) => Some(discarded.pass),
+                StreamEvent::Segment(_) => None,
+            })
+            .collect();
+        assert_eq!(discarded, vec![0, 1]);
+        let StreamEvent::Segment(last) = emitted.last().unwrap() else {
+            panic!("expected a segment last");
+        };
+        assert_eq!((last.pass, last.index), (2, 0));
     }
 
     #[test]
-    fn test_json_error_display() {
-        let error = JsonError::InvalidJson("test error".to_string());
-        assert_eq!(format!("{}", error), "Invalid JSON: test error");
+    fn test_low_memory_source_converts_bounded_windows() {
+        // Ten minutes of a sawtooth at 16 kHz
+        let sample_count = 10 * 60 * audio::SAMPLE_RATE as usize;
+        let bytes: Vec<u8> = (0..sample_count)
+            .flat_map(|n| (((n % 2000) as i16 - 1000) * 16).to_le_bytes())
+            .collect();
+        let source = SampleSource::Pcm16 {
+            bytes: &bytes,
+            pre_emphasis: Some(0.97),
+        };
+        assert_eq!(source.len(), sample_count);
+
+        let window_samples = audio::ms_to_samples(30_000);
+        let windows =
+            audio::split_windows(source.len(), window_samples, audio::ms_to_samples(1000));
+        assert_eq!(windows.len(), 21);
+
+        let mut expected: Vec<f32> = audio::pcm16_samples(&bytes).collect();
+        audio::apply_pre_emphasis(&mut expected, 0.97);
+        for window in windows {
+            let samples = source.window(window.clone());
+            assert!(matches!(samples, Cow::Owned(_)));
+            assert!(samples.len() <= window_samples);
+            assert_eq!(&samples[..], &expected[window]);
+        }
+    }
 
-        let error = JsonError::MissingField("audio_data".to_string());
-        assert_eq!(format!("{}", error), "Missing required field: audio_data");
+    #[test]
+    fn test_cap_segments_with_large_segment_set() {
+        let segments: Vec<TranscriptionSegment> = (0..100_000)
+            .map(|i| TranscriptionSegment {
+                start: i as f32 * 0.01,
+                end: (i + 1) as f32 * 0.01,
+                text: format!("w{}", i),
+                confidence: None,
+                likely_new_speaker: None,
+            })
+            .collect();
+        let text_buffers: Vec<*const u8> = segments
+            .iter()
+            .map(|segment| segment.text.as_ptr())
+            .collect();
 
-        let error = JsonError::InvalidFieldValue("language".to_string(), "invalid".to_string());
-        assert_eq!(
-            format!("{}", error),
-            "Invalid value for field 'language': invalid"
+        let text = join_segment_text(&segments);
+        assert!(text.starts_with("w0 w1 w2"));
+        assert!(text.ends_with("w99999"));
+
+        // Kept segments are moved through unchanged, not cloned
+        let mut kept = keep_non_empty_segments(segments);
+        assert_eq!(cap_segments(&mut kept, 1000), Some(99_000));
+        assert_eq!(kept.len(), 1000);
+        assert!(
+            kept.iter()
+                .zip(&text_buffers)
+                .all(|(segment, &buffer)| segment.text.as_ptr() == buffer)
         );
 
-        let error = JsonError::InvalidBase64("decode error".to_string());
-        assert_eq!(
-            format!("{}", error),
-            "Invalid base64 encoding: decode error"
-        );
+        assert_eq!(cap_segments(&mut kept, 1000), None);
+        assert_eq!(cap_segments(&mut kept, 5000), None);
+    }
 
-        let error = JsonError::AudioDataError("empty data".to_string());
-        assert_eq!(format!("{}", error), "Audio data error: empty data");
+    #[test]
+    fn test_silent_audio_skips_inference() {
+        let skip_silent = TranscriptionConfig {
+            skip_silent: true,
+            ..Default::default()
+        };
+        let silence = vec![0u8; 32000];
+        assert!(skips_as_silent(&skip_silent, &silence));
+
+        // Without the flag silent audio is still transcribed
+        assert!(!skips_as_silent(&TranscriptionConfig::default(), &silence));
+
+        // One loud frame in the middle is enough to run inference
+        let mut speech = silence.clone();
+        for sample in speech[16000..16320].chunks_exact_mut(2) {
+            sample.copy_from_slice(&8000i16.to_le_bytes());
+        }
+        assert!(!skips_as_silent(&skip_silent, &speech));
     }
 
     #[test]
-    fn test_validation_error() {
-        let error = ValidationError::new("field_name", "error message");
-        assert_eq!(error.field, "field_name");
-        assert_eq!(error.message, "error message");
+    fn test_check_model_architecture() {
+        assert!(check_model_architecture(true, true).is_ok());
+
+        for (has_encoder, has_decoder, missing) in [
+            (false, true, "encoder"),
+            (true, false, "decoder"),
+            (false, false, "encoder and decoder"),
+        ] {
+            let error = check_model_architecture(has_encoder, has_decoder).unwrap_err();
+            assert_eq!(error.error_code(), "INVALID_MODEL_ARCHITECTURE");
+            assert_eq!(
+                error.to_string(),
+                format!("Invalid model architecture: model has no {}", missing)
+            );
+        }
+    }
+
+    #[test]
+    fn test_thread_count_uses_configured_threads() {
+        for threads in [1, 8] {
+            let config = TranscriptionConfig {
+                threads: Some(threads),
+                ..Default::default()
+            };
+            assert_eq!(thread_count(&config), threads);
+        }
+
+        let unset = TranscriptionConfig::default();
+        let available = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(thread_count(&unset), available);
+
+        // Deterministic decoding stays on one thread regardless
+        let deterministic = TranscriptionConfig {
+            threads: Some(8),
+            deterministic: true,
+            ..Default::default()
+        };
+        assert_eq!(thread_count(&deterministic), 1);
+    }
+
+    #[test]
+    fn test_patience_reaches_beam_search_strategy() {
+        let options = TranscriptionOptions {
+            use_beam_search: Some(true),
+            patience: Some(2.0),
+            ..Default::default()
+        };
+        let config = request_config(&TranscriptionConfig::default(), Some(&options)).unwrap();
+        match sampling_strategy(&config) {
+            SamplingStrategy::BeamSearch { patience, .. } => assert_eq!(patience, 2.0),
+            SamplingStrategy::Greedy { .. } => panic!("expected beam search"),
+        }
+
+        let beam = TranscriptionConfig {
+            use_beam_search: true,
+            ..Default::default()
+        };
+        match sampling_strategy(&beam) {
+            SamplingStrategy::BeamSearch { patience, .. } => {
+                assert_eq!(patience, DEFAULT_PATIENCE)
+            }
+            SamplingStrategy::Greedy { .. } => panic!("expected beam search"),
+        }
     }
 }
@@ -0,0 +1,189 @@
+//! Transcription worker
+//!
+//! The loaded model is owned by a single worker thread that runs jobs in the
+//! order they are queued. Each session (stdin, or one socket connection)
+//! reads, buffers and answers its own requests and only hands the
+//! transcription itself, model swaps and keepalive inferences to the worker,
+//! so a slow or idle client never holds the model.
+
+use std::time::Instant;
+
+use log::{debug, warn};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::audio::AudioData;
+use crate::transcription::{
+    SegmentEmitter, TranscriptionError, TranscriptionResult, offset_emitter,
+};
+use crate::{ServerInfo, ServerState, swap_model, transcribe_request};
+
+/// Work run by the worker that owns the model
+pub enum Job {
+    /// Transcribe one request with its options merged over the server configuration
+    Transcribe {
+        /// Audio and options of the request
+        audio: Box<AudioData>,
+        /// When the request was read, for the reported queue wait
+        received_at: Instant,
+        /// Receiver for segments decoded before the pass finishes (with `--stream-segments`)
+        emitter: Option<SegmentEmitter>,
+        /// Where the result is sent
+        reply: oneshot::Sender<Result<TranscriptionResult, TranscriptionError>>,
+    },
+    /// Replace the loaded model with the one at `path`
+    LoadModel {
+        /// Path of the model to load
+        path: String,
+        /// Where the new server information is sent
+        reply: oneshot::Sender<Result<ServerInfo, String>>,
+    },
+    /// Run a keepalive inference on the loaded model
+    Warm {
+        /// Where the outcome is sent
+        reply: oneshot::Sender<Result<(), TranscriptionError>>,
+    },
+}
+
+/// Handle for queueing jobs on the worker, shared by every session
+#[derive(Debug, Clone)]
+pub struct WorkQueue {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl WorkQueue {
+    /// Create a queue and the receiving end a worker takes jobs from
+    ///
+    /// # Returns
+    /// * `(WorkQueue, mpsc::UnboundedReceiver<Job>)` - Queue handle and its job receiver
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<Job>) {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        (Self { jobs }, receiver)
+    }
+
+    /// Queue a job and wait for its reply
+    ///
+    /// # Returns
+    /// * `Option<T>` - The reply, None if the worker has stopped
+    async fn submit<T>(&self, job: impl FnOnce(oneshot::Sender<T>) -> Job) -> Option<T> {
+        let (reply, response) = oneshot::channel();
+        self.jobs.send(job(reply)).ok()?;
+        response.await.ok()
+    }
+
+    /// Transcribe a request on the worker
+    ///
+    /// # Arguments
+    /// * `audio` - Audio and options of the request
+    /// * `received_at` - When the request was read
+    /// * `emitter` - Receiver for segments decoded before the pass finishes
+    ///
+    /// # Returns
+    /// * `Result<TranscriptionResult, TranscriptionError>` - Result, or an error for invalid options or a failed pass
+    pub async fn transcribe(
+        &self,
+        audio: AudioData,
+        received_at: Instant,
+        emitter: Option<SegmentEmitter>,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        self.submit(|reply| Job::Transcribe {
+            audio: Box::new(audio),
+            received_at,
+            emitter,
+            reply,
+        })
+        .await
+        .unwrap_or_else(|| Err(worker_stopped()))
+    }
+
+    /// Replace the loaded model on the worker
+    ///
+    /// # Arguments
+    /// * `path` - Path of the model to load
+    ///
+    /// # Returns
+    /// * `Result<ServerInfo, String>` - Server information for the new model, error message on failure
+    pub async fn load_model(&self, path: &str) -> Result<ServerInfo, String> {
+        let path = path.to_string();
+        self.submit(|reply| Job::LoadModel { path, reply })
+            .await
+            .unwrap_or_else(|| Err(worker_stopped().to_string()))
+    }
+
+    /// Run a keepalive inference on the worker
+    ///
+    /// # Returns
+    /// * `Option<Result<(), TranscriptionError>>` - Outcome of the inference, None if the worker has stopped
+    pub async fn warm(&self) -> Option<Result<(), TranscriptionError>> {
+        self.submit(|reply| Job::Warm { reply }).await
+    }
+}
+
+/// Error reported to requests queued after the worker stopped
+fn worker_stopped() -> TranscriptionError {
+    TranscriptionError::TranscriptionFailed("Transcription worker stopped".to_string())
+}
+
+/// Start the worker that owns the model
+///
+/// The worker runs on its own thread so inference never blocks the tasks
+/// reading requests and writing results. It stops once every queue handle
+/// has been dropped.
+///
+/// # Arguments
+/// * `server_state` - Server state holding the loaded model
+///
+/// # Returns
+/// * `io::Result<WorkQueue>` - Queue feeding the worker, error if its thread cannot be started
+pub fn spawn(server_state: ServerState) -> std::io::Result<WorkQueue> {
+    let (queue, jobs) = WorkQueue::channel();
+    std::thread::Builder::new()
+        .name("transcription-worker".to_string())
+        .spawn(move || run(server_state, jobs))?;
+    Ok(queue)
+}
+
+/// Run jobs in the order they were queued until the queue closes
+///
+/// # Arguments
+/// * `server_state` - Server state holding the loaded model
+/// * `jobs` - Receiving end of the queue
+fn run(mut server_state: ServerState, mut jobs: mpsc::UnboundedReceiver<Job>) {
+    while let Some(job) = jobs.blocking_recv() {
+        match job {
+            Job::Transcribe {
+                audio,
+                received_at,
+                emitter,
+                reply,
+            } => {
+                let queue_wait = received_at.elapsed();
+                let offset_ms = audio.offset_ms;
+                server_state
+                    .transcription_service
+                    .set_segment_emitter(emitter.map(|emitter| offset_emitter(emitter, offset_ms)));
+                let service = &server_state.transcription_service;
+                let result = transcribe_request(service.config(), &audio, |audio, config| {
+                    service.transcribe_with_config(&audio.data, &audio.input_audio, config)
+                })
+                .map(|mut result| {
+                    if let Some(metrics) = &mut result.metrics {
+                        metrics.queue_wait_ms = queue_wait.as_millis() as u64;
+                    }
+                    result
+                });
+                if reply.send(result).is_err() {
+                    debug!("Session ended before its transcription finished");
+                }
+            }
+            Job::LoadModel { path, reply } => {
+                if reply.send(swap_model(&mut server_state, &path)).is_err() {
+                    warn!("Session ended before the model swap finished");
+                }
+            }
+            Job::Warm { reply } => {
+                let _ = reply.send(server_state.transcription_service.warm());
+            }
+        }
+    }
+    debug!("Work queue closed, stopping the transcription worker");
+}